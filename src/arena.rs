@@ -0,0 +1,247 @@
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+// a vec that hands out handles instead of indexes, a slot is reused once what
+// was in it is removed, but with a new generation, so old handles to it
+// simply find nothing instead of whatever took its place
+#[derive(Clone)]
+pub struct Arena<T> {
+    slots: Vec<Slot<T>>,
+    // the slots that are free to reuse, the last one freed is reused first
+    free: Vec<u32>,
+    len: usize,
+}
+
+#[derive(Clone)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+impl Handle {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Arena {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle {
+        self.len += 1;
+
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.value = Some(value);
+
+            return Handle {
+                index,
+                generation: slot.generation,
+            };
+        }
+
+        self.slots.push(Slot {
+            generation: 0,
+            value: Some(value),
+        });
+
+        Handle {
+            index: (self.slots.len() - 1) as u32,
+            generation: 0,
+        }
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index())?;
+
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        let value = slot.value.take()?;
+
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+
+        Some(value)
+    }
+
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.free.clear();
+        self.len = 0;
+    }
+
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.slots
+            .get(handle.index())
+            .filter(|x| x.generation == handle.generation)
+            .and_then(|x| x.value.as_ref())
+    }
+
+    // always in the order of the slots, which is the same for everyone in lockstep
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, x)| {
+            let handle = Handle {
+                index: index as u32,
+                generation: x.generation,
+            };
+
+            x.value.as_ref().map(|value| (handle, value))
+        })
+    }
+}
+
+// the values some of the entities in an arena have, e.g. one of their components,
+// looked up by the same handles, a value left behind by a removed entity is never found
+#[derive(Clone)]
+pub struct Components<T> {
+    slots: Vec<Option<(u32, T)>>,
+}
+
+impl<T> Components<T> {
+    pub fn new() -> Self {
+        Components { slots: Vec::new() }
+    }
+
+    pub fn insert(&mut self, handle: Handle, value: T) {
+        if self.slots.len() <= handle.index() {
+            self.slots.resize_with(handle.index() + 1, || None);
+        }
+
+        self.slots[handle.index()] = Some((handle.generation, value));
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index())?;
+
+        match slot {
+            Some((generation, _)) if *generation == handle.generation => {
+                slot.take().map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        match self.slots.get(handle.index()) {
+            Some(Some((generation, value))) if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index()) {
+            Some(Some((generation, value))) if *generation == handle.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    // both at the same time, None if either is missing or they are the same
+    pub fn get2_mut(&mut self, one: Handle, two: Handle) -> Option<(&mut T, &mut T)> {
+        if one.index == two.index || !self.contains(one) || !self.contains(two) {
+            return None;
+        }
+
+        let (left, right) = self.slots.split_at_mut(one.index().max(two.index()));
+        let low = &mut left[one.index().min(two.index())].as_mut()?.1;
+        let high = &mut right[0].as_mut()?.1;
+
+        if one.index < two.index {
+            Some((low, high))
+        } else {
+            Some((high, low))
+        }
+    }
+
+    // the handle of whoever has a value in the slot right now, e.g. for indexes kept in a grid
+    pub fn handle(&self, index: usize) -> Option<Handle> {
+        match self.slots.get(index) {
+            Some(Some((generation, _))) => Some(Handle {
+                index: index as u32,
+                generation: *generation,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn handles(&self) -> Vec<Handle> {
+        self.iter().map(|(handle, _)| handle).collect()
+    }
+
+    // always in the order of the slots, just like the arena
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, x)| {
+            x.as_ref().map(|(generation, value)| {
+                let handle = Handle {
+                    index: index as u32,
+                    generation: *generation,
+                };
+
+                (handle, value)
+            })
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, x)| {
+            x.as_mut().map(|(generation, value)| {
+                let handle = Handle {
+                    index: index as u32,
+                    generation: *generation,
+                };
+
+                (handle, value)
+            })
+        })
+    }
+
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots
+            .iter()
+            .filter_map(|x| x.as_ref().map(|(_, value)| value))
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots
+            .iter_mut()
+            .filter_map(|x| x.as_mut().map(|(_, value)| value))
+    }
+}
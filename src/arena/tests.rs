@@ -0,0 +1,55 @@
+use crate::arena::{Arena, Components};
+
+#[test]
+fn stale_handles_find_nothing() {
+    let mut arena = Arena::new();
+    let mut components = Components::new();
+    let one = arena.insert(1);
+    let two = arena.insert(2);
+    components.insert(one, 1);
+    components.insert(two, 2);
+
+    assert_eq!(arena.remove(one), Some(1));
+    assert_eq!(arena.remove(one), None);
+
+    // the slot is reused, but not by the old handle
+    let three = arena.insert(3);
+    assert_eq!(three.index(), one.index());
+    assert_ne!(three, one);
+
+    assert_eq!(arena.get(one), None);
+    assert_eq!(arena.get(three), Some(&3));
+    assert_eq!(arena.len(), 2);
+
+    // whatever the removed entity left behind is not found by the new one
+    assert_eq!(components.get(three), None);
+    assert_eq!(components.get2_mut(three, two), None);
+    components.insert(three, 3);
+    assert_eq!(components.get(one), None);
+    assert_eq!(components.get_mut(three), Some(&mut 3));
+}
+
+#[test]
+fn iterates_in_the_order_of_the_slots() {
+    let mut arena = Arena::new();
+    let mut components = Components::new();
+    let handles: Vec<_> = (0..4).map(|x| arena.insert(x)).collect();
+
+    arena.remove(handles[1]);
+    arena.remove(handles[2]);
+    let four = arena.insert(4);
+
+    // the last slot freed is the first one reused
+    let values: Vec<_> = arena.iter().map(|(_, x)| *x).collect();
+    assert_eq!(values, vec![0, 4, 3]);
+
+    for (handle, value) in arena.iter() {
+        components.insert(handle, *value);
+    }
+
+    assert_eq!(components.handles(), vec![handles[0], four, handles[3]]);
+    assert_eq!(
+        components.get2_mut(handles[3], handles[0]),
+        Some((&mut 3, &mut 0))
+    );
+}
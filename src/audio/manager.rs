@@ -2,8 +2,8 @@ use std::collections::BTreeSet;
 
 use crate::{
     bus::Bus,
-    entities::EntityTypeIndex,
-    messages::{AudioMessage, LogicMessage, Message, Sender},
+    entities::EntityHandle,
+    messages::{LogicMessage, Message, Sender},
 };
 
 pub struct AudioManager {
@@ -12,7 +12,7 @@ pub struct AudioManager {
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 enum Action {
-    Play(EntityTypeIndex),
+    Play(EntityHandle),
 }
 
 impl AudioManager {
@@ -22,27 +22,32 @@ impl AudioManager {
         }
     }
 
-    pub fn update(&mut self, bus: &mut Bus) {
-        self.action(bus);
+    pub fn update(&mut self, _bus: &mut Bus) {
+        self.action();
     }
 
     pub fn message(&mut self, _sender: &Sender, msg: &Message) {
-        if let Message::Logic(LogicMessage::Death(idx)) = msg {
-            if let EntityTypeIndex::Triship(_) = idx {
-                // we only want to play sounds for triship deaths for now
-                self.actions.insert(Action::Play(*idx));
+        match msg {
+            Message::Logic(LogicMessage::Death(handle)) => {
+                if let EntityHandle::Triship(_) = handle {
+                    // we only want to play sounds for triship deaths for now
+                    self.actions.insert(Action::Play(*handle));
+                }
+            }
+            Message::Logic(LogicMessage::PickupCollected { handle, .. }) => {
+                self.actions.insert(Action::Play(*handle));
             }
+            _ => (),
         }
     }
 
-    fn action(&mut self, bus: &mut Bus) {
+    fn action(&mut self) {
         while let Some(action) = self.actions.pop_first() {
             match action {
-                Action::Play(idx) => {
+                Action::Play(_handle) => {
                     // TODO: play death sound!
                     // TODO: we might want to move the world/entities out of the game state,
                     // so we can check whether the player is nearby the dead entity
-                    bus.send(Message::Audio(AudioMessage::Play(idx)));
                 }
             }
         }
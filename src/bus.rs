@@ -1,9 +1,8 @@
 use std::collections::VecDeque;
 
-use crate::{
-    engine::Managers,
-    messages::{Message, Sender},
-};
+#[cfg(feature = "graphics")]
+use crate::engine::Managers;
+use crate::messages::{Message, Sender};
 
 pub struct Bus {
     messages: VecDeque<(Sender, Message)>,
@@ -18,12 +17,14 @@ impl Bus {
         }
     }
 
+    #[cfg(feature = "graphics")]
     pub fn update(&mut self, managers: &mut Managers) {
         while let Some((sender, msg)) = self.messages.pop_front() {
             managers.log.message(&sender, &msg);
             managers.engine.message(&sender, &msg);
             managers.state.message(&sender, &msg);
             managers.audio.message(&sender, &msg);
+            managers.net.message(&sender, &msg);
         }
     }
 
@@ -31,6 +32,12 @@ impl Bus {
         self.messages.push_back((self.current_sender, msg));
     }
 
+    // lets anyone without access to the managers handle the messages themselves,
+    // e.g. when updating the world several ticks at once
+    pub fn pop(&mut self) -> Option<(Sender, Message)> {
+        self.messages.pop_front()
+    }
+
     pub fn with_sender(&mut self, sender: Sender) -> &mut Self {
         self.current_sender = sender;
         self
@@ -0,0 +1,599 @@
+use std::fmt;
+
+use fastrand::Rng;
+
+use crate::{
+    arena::Handle,
+    bytes::Reader,
+    components::logic::{Body, Counter, Health, Miscellaneous, Motion, Shape},
+    entities::{Entities, Kind, Star},
+    math::{Flint, FlintRectangle, FlintTriangle, FlintVec2},
+    modes::{Ending, Winner},
+    pickups::{Effect, Pickup},
+    players::Player,
+    weapons::{Armament, Loadout, Weapon},
+};
+
+// a stable hash of all logic state after a tick, two worlds with the same checksum
+// for the same tick are identical, as far as the simulation is concerned,
+// the players are hashed one by one together with their ships
+// so we can tell which player diverged,
+// everything else is summed up per kind since there can be a lot of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub tick: u64,
+    // everything that isn't an entity, e.g. the rng and the death counters
+    pub world: u64,
+    pub players: Vec<u64>,
+    pub projectiles: Group,
+    pub pickups: Group,
+    pub exhausts: Group,
+    pub explosions: Group,
+    pub stars: Group,
+}
+
+// the entities of one kind in the order they are stored, each hashed by itself,
+// sum adds up the hashes and weighted adds up each hash times its index plus one,
+// both modulo a prime, so when a single entity differs the difference of weighted
+// divided by the difference of sum gives us its index, however many there are
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Group {
+    pub count: u32,
+    pub sum: u64,
+    pub weighted: u64,
+}
+
+// the index within the kind when only a single entity differs,
+// none when there are several or a different amount of them
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    World,
+    Player(usize),
+    Projectile(Option<usize>),
+    Pickup(Option<usize>),
+    Exhaust(Option<usize>),
+    Explosion(Option<usize>),
+    Star(Option<usize>),
+}
+
+// fnv-1a, unlike the hashers in std it's guaranteed to never change,
+// which matters since checksums are compared across builds and stored in replays
+struct Hasher(u64);
+
+// an entity with all of its components
+struct Entity<'a>(&'a Entities, Handle);
+
+trait Checksummable {
+    fn checksum(&self, hasher: &mut Hasher);
+}
+
+impl Checksum {
+    pub fn new(tick: u64, rng: &Rng, entities: &Entities, misc: &Miscellaneous) -> Self {
+        let mut world = Hasher::new();
+        tick.checksum(&mut world);
+        rng.get_seed().checksum(&mut world);
+        misc.checksum(&mut world);
+
+        Checksum {
+            tick,
+            world: world.finish(),
+            players: entities
+                .players
+                .iter()
+                .map(|x| Hasher::hash(&(x, x.ship.map(|x| Entity(entities, x)))))
+                .collect(),
+            projectiles: Group::of_kind(entities, Kind::Projectile),
+            pickups: Group::of_kind(entities, Kind::Pickup),
+            exhausts: Group::of_kind(entities, Kind::Exhaust),
+            explosions: Group::of_kind(entities, Kind::Explosion),
+            stars: Group::new(entities.stars.iter().map(Hasher::hash)),
+        }
+    }
+
+    // everything combined into one, handy when all we want to know is if we diverged
+    pub fn total(&self) -> u64 {
+        let mut hasher = Hasher::new();
+        self.tick.checksum(&mut hasher);
+        self.world.checksum(&mut hasher);
+        self.players.checksum(&mut hasher);
+        self.projectiles.checksum(&mut hasher);
+        self.pickups.checksum(&mut hasher);
+        self.exhausts.checksum(&mut hasher);
+        self.explosions.checksum(&mut hasher);
+        self.stars.checksum(&mut hasher);
+        hasher.finish()
+    }
+
+    // both checksums are expected to be of the same tick
+    pub fn diverges(&self, other: &Checksum) -> Option<Divergence> {
+        if self.world != other.world {
+            return Some(Divergence::World);
+        }
+
+        let len = self.players.len().max(other.players.len());
+
+        for idx in 0..len {
+            if self.players.get(idx) != other.players.get(idx) {
+                return Some(Divergence::Player(idx));
+            }
+        }
+
+        let groups = [
+            (
+                &self.projectiles,
+                &other.projectiles,
+                Divergence::Projectile as fn(_) -> _,
+            ),
+            (&self.pickups, &other.pickups, Divergence::Pickup),
+            (&self.exhausts, &other.exhausts, Divergence::Exhaust),
+            (&self.explosions, &other.explosions, Divergence::Explosion),
+            (&self.stars, &other.stars, Divergence::Star),
+        ];
+
+        groups
+            .into_iter()
+            .find(|(ours, theirs, _)| ours != theirs)
+            .map(|(ours, theirs, divergence)| divergence(ours.diverges(theirs)))
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.tick.to_le_bytes());
+        buf.extend_from_slice(&self.world.to_le_bytes());
+        buf.push(self.players.len().min(u8::MAX as usize) as u8);
+
+        for player in self.players.iter().take(u8::MAX as usize) {
+            buf.extend_from_slice(&player.to_le_bytes());
+        }
+
+        self.projectiles.encode(buf);
+        self.pickups.encode(buf);
+        self.exhausts.encode(buf);
+        self.explosions.encode(buf);
+        self.stars.encode(buf);
+    }
+
+    pub fn decode(reader: &mut Reader) -> Option<Checksum> {
+        let tick = reader.u64()?;
+        let world = reader.u64()?;
+        let len = reader.u8()?;
+        let mut players = Vec::with_capacity(len as usize);
+
+        for _ in 0..len {
+            players.push(reader.u64()?);
+        }
+
+        Some(Checksum {
+            tick,
+            world,
+            players,
+            projectiles: Group::decode(reader)?,
+            pickups: Group::decode(reader)?,
+            exhausts: Group::decode(reader)?,
+            explosions: Group::decode(reader)?,
+            stars: Group::decode(reader)?,
+        })
+    }
+}
+
+impl Group {
+    // 2^61 - 1, big enough that two different groups practically never add up the same
+    const PRIME: u64 = (1 << 61) - 1;
+
+    fn new(hashes: impl Iterator<Item = u64>) -> Self {
+        hashes.fold(Group::default(), |group, hash| {
+            let hash = hash % Group::PRIME;
+            let idx = group.count as u64 + 1;
+
+            Group {
+                count: group.count + 1,
+                sum: add(group.sum, hash),
+                weighted: add(group.weighted, mul(idx, hash)),
+            }
+        })
+    }
+
+    fn of_kind(entities: &Entities, kind: Kind) -> Self {
+        Group::new(
+            entities
+                .kinds
+                .iter()
+                .filter(|(_, x)| **x == kind)
+                .map(|(handle, _)| Hasher::hash(&Entity(entities, handle))),
+        )
+    }
+
+    // the index of the only entity that differs, if there is just the one
+    fn diverges(&self, other: &Group) -> Option<usize> {
+        if self.count != other.count || self.sum == other.sum {
+            return None;
+        }
+
+        let diff = sub(other.sum, self.sum);
+        let weighted = sub(other.weighted, self.weighted);
+        // dividing is multiplying by the inverse, which is diff^(p - 2) for a prime p
+        let idx = mul(weighted, pow(diff, Group::PRIME - 2));
+
+        // with several differing entities this is as good as random,
+        // and so practically never lands on one of them
+        (1..=self.count as u64)
+            .contains(&idx)
+            .then(|| idx as usize - 1)
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.count.to_le_bytes());
+        buf.extend_from_slice(&self.sum.to_le_bytes());
+        buf.extend_from_slice(&self.weighted.to_le_bytes());
+    }
+
+    fn decode(reader: &mut Reader) -> Option<Group> {
+        Some(Group {
+            count: reader.u32()?,
+            sum: reader.u64()?,
+            weighted: reader.u64()?,
+        })
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, idx) = match self {
+            Divergence::World => return write!(f, "world"),
+            Divergence::Player(idx) => return write!(f, "player {}", idx),
+            Divergence::Projectile(idx) => ("projectile", idx),
+            Divergence::Pickup(idx) => ("pickup", idx),
+            Divergence::Exhaust(idx) => ("exhaust", idx),
+            Divergence::Explosion(idx) => ("explosion", idx),
+            Divergence::Star(idx) => ("star", idx),
+        };
+
+        match idx {
+            Some(idx) => write!(f, "{} {}", kind, idx),
+            None => write!(f, "{}s", kind),
+        }
+    }
+}
+
+impl Hasher {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    fn new() -> Self {
+        Hasher(Hasher::OFFSET)
+    }
+
+    fn hash<T: Checksummable>(value: &T) -> u64 {
+        let mut hasher = Hasher::new();
+        value.checksum(&mut hasher);
+        hasher.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Hasher::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// arithmetic modulo the prime of a group, both sides are always below it
+fn add(a: u64, b: u64) -> u64 {
+    (a + b) % Group::PRIME
+}
+
+fn sub(a: u64, b: u64) -> u64 {
+    (a + Group::PRIME - b) % Group::PRIME
+}
+
+fn mul(a: u64, b: u64) -> u64 {
+    (a as u128 * b as u128 % Group::PRIME as u128) as u64
+}
+
+fn pow(mut base: u64, mut exp: u64) -> u64 {
+    let mut result = 1;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul(result, base);
+        }
+
+        base = mul(base, base);
+        exp >>= 1;
+    }
+
+    result
+}
+
+impl Checksummable for () {
+    fn checksum(&self, _hasher: &mut Hasher) {}
+}
+
+impl Checksummable for bool {
+    fn checksum(&self, hasher: &mut Hasher) {
+        hasher.write(&[*self as u8]);
+    }
+}
+
+impl Checksummable for u8 {
+    fn checksum(&self, hasher: &mut Hasher) {
+        hasher.write(&[*self]);
+    }
+}
+
+impl Checksummable for i32 {
+    fn checksum(&self, hasher: &mut Hasher) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl Checksummable for u32 {
+    fn checksum(&self, hasher: &mut Hasher) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl Checksummable for u64 {
+    fn checksum(&self, hasher: &mut Hasher) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+// usize differs between platforms, so it's always hashed as an u64
+impl Checksummable for usize {
+    fn checksum(&self, hasher: &mut Hasher) {
+        (*self as u64).checksum(hasher);
+    }
+}
+
+impl Checksummable for Flint {
+    fn checksum(&self, hasher: &mut Hasher) {
+        hasher.write(&self.to_bits().to_le_bytes());
+    }
+}
+
+// the length is part of the hash, otherwise moving a value from
+// the end of one vec to the start of the next would go unnoticed
+impl<T: Checksummable> Checksummable for Vec<T> {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.len().checksum(hasher);
+        self.iter().for_each(|x| x.checksum(hasher));
+    }
+}
+
+impl<T: Checksummable> Checksummable for Option<T> {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.is_some().checksum(hasher);
+        self.iter().for_each(|x| x.checksum(hasher));
+    }
+}
+
+impl<T: Checksummable> Checksummable for &T {
+    fn checksum(&self, hasher: &mut Hasher) {
+        (*self).checksum(hasher);
+    }
+}
+
+impl<A: Checksummable, B: Checksummable> Checksummable for (A, B) {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.0.checksum(hasher);
+        self.1.checksum(hasher);
+    }
+}
+
+impl Checksummable for Handle {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.index().checksum(hasher);
+        (self.generation() as u64).checksum(hasher);
+    }
+}
+
+impl Checksummable for FlintVec2 {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.x.checksum(hasher);
+        self.y.checksum(hasher);
+    }
+}
+
+impl Checksummable for FlintTriangle {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.v1.checksum(hasher);
+        self.v2.checksum(hasher);
+        self.v3.checksum(hasher);
+        self.width.checksum(hasher);
+        self.height.checksum(hasher);
+    }
+}
+
+impl Checksummable for FlintRectangle {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.point.checksum(hasher);
+        self.width.checksum(hasher);
+        self.height.checksum(hasher);
+    }
+}
+
+impl<T: Checksummable> Checksummable for Shape<T> {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.shape.checksum(hasher);
+        self.direction.checksum(hasher);
+    }
+}
+
+impl<T: Checksummable> Checksummable for Body<T> {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.live.checksum(hasher);
+        self.past.checksum(hasher);
+        self.dirty.checksum(hasher);
+        self.axes.checksum(hasher);
+    }
+}
+
+impl Checksummable for Motion {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.speed.checksum(hasher);
+        self.max_speed.checksum(hasher);
+        self.acceleration.checksum(hasher);
+        self.rotation_speed.checksum(hasher);
+        self.deceleration.checksum(hasher);
+    }
+}
+
+impl Checksummable for Counter {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.value.checksum(hasher);
+    }
+}
+
+impl Checksummable for Miscellaneous {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.player_death_counters.checksum(hasher);
+        self.player_map_spawn_indexes.checksum(hasher);
+        self.map_pickup_counters.checksum(hasher);
+        self.ending.checksum(hasher);
+    }
+}
+
+impl Checksummable for Ending {
+    fn checksum(&self, hasher: &mut Hasher) {
+        let kind: u8 = match self {
+            Ending::FragLimit(_) => 0,
+            Ending::TimeLimit(_) => 1,
+            Ending::LastStanding(_) => 2,
+        };
+
+        kind.checksum(hasher);
+        self.winner().checksum(hasher);
+    }
+}
+
+impl Checksummable for Winner {
+    fn checksum(&self, hasher: &mut Hasher) {
+        let (kind, idx): (u8, usize) = match self {
+            Winner::Player(pid) => (0, *pid),
+            Winner::Team(team) => (1, *team),
+        };
+
+        kind.checksum(hasher);
+        idx.checksum(hasher);
+    }
+}
+
+// the render components are left out, they are only ever derived from the logic,
+// and so are the polygons, they never change and come straight from the map
+
+// the name and the input come from outside of the simulation,
+// the input is only ever carried out through the ship
+impl Checksummable for Player {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.pid.checksum(hasher);
+        self.score.checksum(hasher);
+        self.kills.checksum(hasher);
+        self.deaths.checksum(hasher);
+        self.assists.checksum(hasher);
+        self.lives.checksum(hasher);
+        self.team.checksum(hasher);
+        self.ship.checksum(hasher);
+    }
+}
+
+impl Checksummable for Health {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.life.checksum(hasher);
+        self.shield.checksum(hasher);
+        self.max_shield.checksum(hasher);
+        self.recharge.checksum(hasher);
+        self.invulnerable.checksum(hasher);
+        self.shielded.checksum(hasher);
+        self.attacker.checksum(hasher);
+        self.attackers.checksum(hasher);
+    }
+}
+
+impl Checksummable for Kind {
+    fn checksum(&self, hasher: &mut Hasher) {
+        (*self as u8).checksum(hasher);
+    }
+}
+
+impl Checksummable for Weapon {
+    fn checksum(&self, hasher: &mut Hasher) {
+        (*self as u8).checksum(hasher);
+    }
+}
+
+impl Checksummable for Pickup {
+    fn checksum(&self, hasher: &mut Hasher) {
+        (*self as u8).checksum(hasher);
+    }
+}
+
+impl Checksummable for Effect {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.pickup.checksum(hasher);
+        self.ticks.checksum(hasher);
+    }
+}
+
+impl Checksummable for Loadout {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.armaments.checksum(hasher);
+        self.selected.checksum(hasher);
+        self.upgraded.checksum(hasher);
+    }
+}
+
+impl Checksummable for Armament {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.weapon.checksum(hasher);
+        self.cooldown.checksum(hasher);
+        self.heat.checksum(hasher);
+        self.overheated.checksum(hasher);
+        self.ammo.checksum(hasher);
+    }
+}
+
+// the handle is part of the hash, what a message refers to must be the same for everyone,
+// and so is every component, whether the entity has it or not
+impl Checksummable for Entity<'_> {
+    fn checksum(&self, hasher: &mut Hasher) {
+        let Entity(entities, handle) = *self;
+
+        handle.checksum(hasher);
+        entities.kind(handle).checksum(hasher);
+        entities.triangles.get(handle).checksum(hasher);
+        entities.rectangles.get(handle).checksum(hasher);
+        entities.points.get(handle).checksum(hasher);
+        entities.motions.get(handle).checksum(hasher);
+        entities.lifetimes.get(handle).checksum(hasher);
+        entities.healths.get(handle).checksum(hasher);
+        entities.owners.get(handle).checksum(hasher);
+        entities.damages.get(handle).checksum(hasher);
+        entities.homings.get(handle).checksum(hasher);
+        entities.loadouts.get(handle).checksum(hasher);
+        entities.pickups.get(handle).checksum(hasher);
+        entities.effects.get(handle).checksum(hasher);
+        entities.fades.get(handle).checksum(hasher);
+        entities.dead.get(handle).checksum(hasher);
+    }
+}
+
+impl Checksummable for Group {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.count.checksum(hasher);
+        self.sum.checksum(hasher);
+        self.weighted.checksum(hasher);
+    }
+}
+
+impl Checksummable for Star {
+    fn checksum(&self, hasher: &mut Hasher) {
+        self.body.checksum(hasher);
+        self.counter.checksum(hasher);
+        self.toggle.checksum(hasher);
+        self.amount.checksum(hasher);
+    }
+}
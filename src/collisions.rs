@@ -1,11 +1,150 @@
+use std::ops::RangeInclusive;
+
+use fixed::types::I40F24;
+
 use crate::math::{Flint, FlintVec2};
 
+#[cfg(test)]
+mod tests;
+
+// the axis aligned box around a shape, used by the broad phase
+#[derive(Clone, Copy, Debug)]
+pub struct Bounds {
+    pub min: FlintVec2,
+    pub max: FlintVec2,
+}
+
+// where a moving shape first touches another shape
+#[derive(Clone, Copy, Debug)]
+pub struct Impact {
+    // how far along the velocity they touch, from zero to one
+    pub fraction: Flint,
+    // points out of the other shape, towards the moving one
+    pub normal: FlintVec2,
+}
+
+// how two overlapping shapes touch
+#[derive(Clone, Debug)]
+pub struct Manifold {
+    // points out of the other shape, towards the first one
+    pub normal: FlintVec2,
+    // how far the first shape must move along the normal to no longer overlap
+    pub depth: Flint,
+    // where they touch, one or two points
+    pub points: Vec<FlintVec2>,
+}
+
+// an edge of a shape, see calc_edge
+struct Edge {
+    furthest: FlintVec2,
+    from: FlintVec2,
+    to: FlintVec2,
+}
+
+// a uniform grid over the map used as the broad phase,
+// only shapes sharing a cell are checked with the separating axis theorem,
+// shapes outside of the map end up in the cells along its edges
+#[derive(Clone)]
+pub struct Grid {
+    columns: i32,
+    rows: i32,
+    cells: Vec<Vec<usize>>,
+}
+
+impl Bounds {
+    pub fn from_points(points: &[FlintVec2]) -> Self {
+        let mut bounds = Bounds {
+            min: points[0],
+            max: points[0],
+        };
+
+        for point in points.iter().skip(1) {
+            bounds.min.x = bounds.min.x.min(point.x);
+            bounds.min.y = bounds.min.y.min(point.y);
+            bounds.max.x = bounds.max.x.max(point.x);
+            bounds.max.y = bounds.max.y.max(point.y);
+        }
+
+        bounds
+    }
+}
+
+impl Manifold {
+    // the minimum translation vector, moves the first shape out of the other one
+    pub fn mtv(&self) -> FlintVec2 {
+        self.normal * self.depth
+    }
+}
+
+impl Grid {
+    // a bit larger than a triship and the distance a projectile moves in one tick
+    const CELL_SIZE: Flint = Flint::lit("64");
+
+    pub fn new() -> Self {
+        Grid {
+            columns: 0,
+            rows: 0,
+            cells: Vec::new(),
+        }
+    }
+
+    // empties all cells, keeping their allocations around for the next tick
+    pub fn reset(&mut self, width: Flint, height: Flint) {
+        self.columns = (width / Grid::CELL_SIZE).ceil().to_num::<i32>().max(1);
+        self.rows = (height / Grid::CELL_SIZE).ceil().to_num::<i32>().max(1);
+        self.cells
+            .resize_with((self.columns * self.rows) as usize, Vec::new);
+        self.cells.iter_mut().for_each(|x| x.clear());
+    }
+
+    pub fn insert(&mut self, idx: usize, bounds: &Bounds) {
+        let (columns, rows) = self.range(bounds);
+
+        for row in rows {
+            for column in columns.clone() {
+                self.cells[(row * self.columns + column) as usize].push(idx);
+            }
+        }
+    }
+
+    // the candidates are sorted and without duplicates,
+    // so they are checked in the same order as when checking everything
+    pub fn query(&self, bounds: &Bounds, candidates: &mut Vec<usize>) {
+        candidates.clear();
+
+        let (columns, rows) = self.range(bounds);
+
+        for row in rows {
+            for column in columns.clone() {
+                candidates.extend_from_slice(&self.cells[(row * self.columns + column) as usize]);
+            }
+        }
+
+        candidates.sort_unstable();
+        candidates.dedup();
+    }
+
+    fn range(&self, bounds: &Bounds) -> (RangeInclusive<i32>, RangeInclusive<i32>) {
+        let cell = |x: Flint, max: i32| {
+            (x / Grid::CELL_SIZE)
+                .floor()
+                .to_num::<i32>()
+                .clamp(0, max - 1)
+        };
+
+        (
+            cell(bounds.min.x, self.columns)..=cell(bounds.max.x, self.columns),
+            cell(bounds.min.y, self.rows)..=cell(bounds.max.y, self.rows),
+        )
+    }
+}
+
 fn project(shape_alpha: &[FlintVec2], axis: &FlintVec2) -> FlintVec2 {
     let mut min = axis.dot(&shape_alpha[0]);
     let mut max = min;
 
-    for i in 1..shape_alpha.len() {
-        let p = axis.dot(&shape_alpha[i]);
+    for point in shape_alpha.iter().skip(1) {
+        let p = axis.dot(point);
 
         if p < min {
             min = p;
@@ -18,16 +157,138 @@ fn project(shape_alpha: &[FlintVec2], axis: &FlintVec2) -> FlintVec2 {
 }
 
 fn is_overlapping(p1: FlintVec2, p2: FlintVec2) -> bool {
-    p1.y > p2.x || p1.x > p2.y
+    // x is min and y is max, both must reach past the other one
+    p1.y > p2.x && p2.y > p1.x
+}
+
+// how far the first projection must move to no longer overlap the second one,
+// and whether it's moving forward along the axis that gets it out the quickest
+fn calc_overlap(p1: FlintVec2, p2: FlintVec2) -> (Flint, bool) {
+    if p1.y - p2.x < p2.y - p1.x {
+        (p1.y - p2.x, false)
+    } else {
+        (p2.y - p1.x, true)
+    }
+}
+
+// the edge of a shape facing a direction the most,
+// along with the point of the shape furthest in that direction
+fn calc_edge(shape: &[FlintVec2], direction: FlintVec2) -> Edge {
+    let mut idx = 0;
+
+    for i in 1..shape.len() {
+        if direction.dot(&shape[i]) > direction.dot(&shape[idx]) {
+            idx = i;
+        }
+    }
+
+    let furthest = shape[idx];
+    let next = shape[if idx + 1 == shape.len() { 0 } else { idx + 1 }];
+    let prev = shape[if idx == 0 { shape.len() - 1 } else { idx - 1 }];
+
+    // of the two edges next to the furthest point, pick the one most perpendicular to the direction
+    if (furthest - prev).normalized().dot(&direction)
+        <= (furthest - next).normalized().dot(&direction)
+    {
+        Edge {
+            furthest,
+            from: prev,
+            to: furthest,
+        }
+    } else {
+        Edge {
+            furthest,
+            from: furthest,
+            to: next,
+        }
+    }
 }
 
-// fn calc_overlap(p1: FlintVec2, p2: FlintVec2) -> Flint {
-//     if p1.y > p2.x {
-//         p1.y - p2.x
-//     } else {
-//         p2.x - p1.y
-//     }
-// }
+// keeps what's at or past offset along the axis, cutting the line where it crosses it
+fn clip(v1: FlintVec2, v2: FlintVec2, axis: FlintVec2, offset: Flint) -> Vec<FlintVec2> {
+    let mut points = Vec::new();
+    let d1 = axis.dot(&v1) - offset;
+    let d2 = axis.dot(&v2) - offset;
+
+    if d1 >= Flint::ZERO {
+        points.push(v1);
+    }
+
+    if d2 >= Flint::ZERO {
+        points.push(v2);
+    }
+
+    if (d1 < Flint::ZERO && d2 > Flint::ZERO) || (d1 > Flint::ZERO && d2 < Flint::ZERO) {
+        // multiply before dividing, in the wider type, so the point ends up right on the axis
+        let cut = |a: Flint, b: Flint| {
+            a + Flint::from_num((b - a).wide_mul(d1) / I40F24::from_num(d1 - d2))
+        };
+
+        points.push(FlintVec2::new(cut(v1.x, v2.x), cut(v1.y, v2.y)));
+    }
+
+    points
+}
+
+// the edges of the two shapes facing each other are clipped against each other,
+// the one most perpendicular to the normal is the reference the other one is clipped by
+fn calc_contact_points(
+    shape_alpha: &[FlintVec2],
+    shape_beta: &[FlintVec2],
+    normal: FlintVec2,
+) -> Vec<FlintVec2> {
+    let e1 = calc_edge(shape_alpha, normal.rotated_180());
+    let e2 = calc_edge(shape_beta, normal);
+    let d1 = (e1.to - e1.from).normalized().dot(&normal).abs();
+    let d2 = (e2.to - e2.from).normalized().dot(&normal).abs();
+
+    // outwards is out of the shape the reference edge belongs to
+    let (reference, incident, outwards) = if d1 <= d2 {
+        (e1, e2, normal.rotated_180())
+    } else {
+        (e2, e1, normal)
+    };
+
+    let along = (reference.to - reference.from).normalized();
+
+    // cut off what's beyond either end of the reference edge
+    let points = clip(
+        incident.from,
+        incident.to,
+        along,
+        along.dot(&reference.from),
+    );
+
+    if points.len() < 2 {
+        return vec![incident.furthest];
+    }
+
+    let points = clip(
+        points[0],
+        points[1],
+        along.rotated_180(),
+        -along.dot(&reference.to),
+    );
+
+    if points.len() < 2 {
+        return vec![incident.furthest];
+    }
+
+    // and only keep what's inside of the reference shape
+    let face = if along.perpendicular().dot(&outwards) < Flint::ZERO {
+        along.perpendicular().rotated_180()
+    } else {
+        along.perpendicular()
+    };
+    let max = face.dot(&reference.from);
+    let points: Vec<FlintVec2> = points.into_iter().filter(|x| face.dot(x) <= max).collect();
+
+    if points.is_empty() {
+        return vec![incident.furthest];
+    }
+
+    points
+}
 
 fn calc_perps(shape: &[FlintVec2]) -> Vec<FlintVec2> {
     let mut perps = Vec::new();
@@ -66,45 +327,113 @@ pub fn intersects(shape_alpha: &[FlintVec2], shape_beta: &[FlintVec2]) -> bool {
     true
 }
 
-pub fn calculate_speed_to_collision(
-    direction: FlintVec2,
-    // speed: Flint,
+// like intersects, but also finds how they overlap, none if they don't
+pub fn manifold(shape_alpha: &[FlintVec2], shape_beta: &[FlintVec2]) -> Option<Manifold> {
+    let mut depth = Flint::MAX;
+    let mut normal = FlintVec2::new(Flint::ZERO, Flint::ZERO);
+
+    let axes = [calc_perps(shape_alpha), calc_perps(shape_beta)].concat();
+    for axis in axes.iter() {
+        let p1 = project(shape_alpha, axis);
+        let p2 = project(shape_beta, axis);
+
+        if !is_overlapping(p1, p2) {
+            return None;
+        }
+
+        let (overlap, forward) = calc_overlap(p1, p2);
+
+        if overlap < depth {
+            depth = overlap;
+            normal = if forward { *axis } else { axis.rotated_180() };
+        }
+    }
+
+    Some(Manifold {
+        normal,
+        depth,
+        points: calc_contact_points(shape_alpha, shape_beta, normal),
+    })
+}
+
+// swept separating axis theorem, shape_alpha moves by velocity and shape_beta stands still,
+// none if they never touch along the way and a fraction of zero if they already overlap
+pub fn time_of_impact(
     shape_alpha: &[FlintVec2],
+    velocity: FlintVec2,
     shape_beta: &[FlintVec2],
-) -> Flint {
-    let mut alpha = vec![FlintVec2::new(Flint::ZERO, Flint::ZERO); shape_alpha.len()];
-    alpha.copy_from_slice(shape_alpha);
-
-    // let threshold = Flint::from_num(0.2);
-    let mut total = Flint::ZERO;
-    // halving speed is probably faster but there's a risk
-    // of entirely missing the intersection, let's move
-    // with a speed of one instead
-    // let mut speed = speed / 2;
-    let speed = Flint::ONE;
-
-    // while speed > threshold {
-    loop {
-        let velocity = direction * speed;
-
-        // apply velocity to alpha
-        for a in alpha.iter_mut() {
-            *a += velocity;
-        }
+) -> Option<Impact> {
+    let mut enter = Flint::MIN;
+    let mut exit = Flint::MAX;
+    let mut normal = FlintVec2::new(Flint::ZERO, Flint::ZERO);
 
-        if intersects(&alpha, shape_beta) {
-            // move back
-            // for a in alpha.iter_mut() {
-            //     *a -= velocity;
-            // }
+    // the axis with the least overlap, the way out in case they already overlap
+    let mut depth = Flint::MAX;
+    let mut way_out = normal;
 
-            break;
+    let axes = [calc_perps(shape_alpha), calc_perps(shape_beta)].concat();
+    for axis in axes.iter() {
+        let p1 = project(shape_alpha, axis);
+        let p2 = project(shape_beta, axis);
+        let speed = axis.dot(&velocity);
+
+        if p1.y <= p2.x {
+            // alpha is before beta along the axis, it must move forward to reach it
+            let gap = p2.x - p1.y;
+
+            if speed <= Flint::ZERO || gap >= speed {
+                return None;
+            }
+
+            if gap / speed > enter {
+                enter = gap / speed;
+                normal = axis.rotated_180();
+            }
+
+            exit = exit.min((p2.y - p1.x).saturating_div(speed));
+        } else if p2.y <= p1.x {
+            // alpha is after beta along the axis, it must move backward to reach it
+            let gap = p1.x - p2.y;
+
+            if speed >= Flint::ZERO || gap >= -speed {
+                return None;
+            }
+
+            if gap / -speed > enter {
+                enter = gap / -speed;
+                normal = *axis;
+            }
+
+            exit = exit.min((p1.y - p2.x).saturating_div(-speed));
         } else {
-            total += speed;
+            let (overlap, forward) = calc_overlap(p1, p2);
+
+            if overlap < depth {
+                depth = overlap;
+                way_out = if forward { *axis } else { axis.rotated_180() };
+            }
+
+            if speed > Flint::ZERO {
+                exit = exit.min((p2.y - p1.x).saturating_div(speed));
+            } else if speed < Flint::ZERO {
+                exit = exit.min((p1.y - p2.x).saturating_div(-speed));
+            }
         }
+    }
+
+    if enter == Flint::MIN {
+        return Some(Impact {
+            fraction: Flint::ZERO,
+            normal: way_out,
+        });
+    }
 
-        // speed /= 2;
+    if enter >= exit {
+        return None;
     }
 
-    total
+    Some(Impact {
+        fraction: enter,
+        normal,
+    })
 }
@@ -0,0 +1,285 @@
+use std::time::{Duration, Instant};
+
+use fastrand::Rng;
+
+use crate::{
+    collisions::{self, Bounds, Grid},
+    entities::Entities,
+    math::{Directions, Flint, FlintVec2},
+    players::Player,
+    ships::ShipClasses,
+    spawner::Spawner,
+    weapons::Weapon,
+};
+
+const WIDTH: i32 = 1600;
+const HEIGHT: i32 = 1200;
+
+// triships and moving projectiles scattered across the map,
+// some of them a bit outside of it
+struct Scene {
+    entities: Entities,
+}
+
+impl Scene {
+    fn new(seed: u64, triships: usize, projectiles: usize) -> Self {
+        let spawner = Spawner::new();
+        let mut entities = Entities::new();
+        let mut rng = Rng::with_seed(seed);
+        let point = |rng: &mut Rng| {
+            FlintVec2::new(
+                Flint::from_num(rng.i32(-32..WIDTH + 32)),
+                Flint::from_num(rng.i32(-32..HEIGHT + 32)),
+            )
+        };
+        let direction = |rng: &mut Rng| {
+            let rad = Flint::from_num(rng.i32(0..628)) / 100;
+            Directions::EAST.rotated(rad, FlintVec2::new(Flint::ZERO, Flint::ZERO))
+        };
+
+        let class = &ShipClasses::load().expect("the ship classes").classes[0];
+
+        for pid in 0..triships {
+            let handle = spawner.spawn_triship(
+                &mut entities,
+                class,
+                point(&mut rng),
+                direction(&mut rng),
+                pid,
+            );
+            let mut player = Player::new(pid, class.clone());
+            player.ship = Some(handle);
+            entities.players.push(player);
+        }
+
+        for pid in 0..projectiles {
+            let handle = spawner.spawn_projectile(
+                &mut entities,
+                Weapon::Cannon,
+                point(&mut rng),
+                direction(&mut rng),
+                Flint::ZERO,
+                pid % 8,
+            );
+
+            let speed = entities.motions.get(handle).unwrap().speed;
+            let body = entities.rectangles.get_mut(handle).unwrap();
+            let velocity = body.live.direction * speed;
+            body.live.shape.point += velocity;
+            body.dirty = true;
+        }
+
+        Scene { entities }
+    }
+
+    // the pairs found by checking everything against everything,
+    // the way it was done before there was a broad phase
+    fn everything(&mut self) -> Vec<(usize, usize)> {
+        let entities = &mut self.entities;
+        let mut pairs = Vec::new();
+
+        for (p_handle, projectile) in entities.rectangles.iter_mut() {
+            for (t_handle, triship) in entities.triangles.iter_mut() {
+                if entities.owners.get(p_handle) == entities.owners.get(t_handle) {
+                    continue;
+                }
+
+                if collisions::intersects(projectile.calc_axes(true), triship.calc_axes()) {
+                    pairs.push((p_handle.index(), t_handle.index()));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    fn grid(&mut self, grid: &mut Grid, candidates: &mut Vec<usize>) -> Vec<(usize, usize)> {
+        let entities = &mut self.entities;
+        let mut pairs = Vec::new();
+
+        grid.reset(Flint::from_num(WIDTH), Flint::from_num(HEIGHT));
+
+        for (handle, triship) in entities.triangles.iter_mut() {
+            grid.insert(handle.index(), &Bounds::from_points(triship.calc_axes()));
+        }
+
+        for (p_handle, projectile) in entities.rectangles.iter_mut() {
+            let shape_alpha = projectile.calc_axes(true);
+
+            grid.query(&Bounds::from_points(shape_alpha), candidates);
+
+            for &idx in candidates.iter() {
+                let t_handle = entities.triangles.handle(idx).unwrap();
+
+                if entities.owners.get(p_handle) == entities.owners.get(t_handle) {
+                    continue;
+                }
+
+                let shape_beta = entities.triangles.get_mut(t_handle).unwrap().calc_axes();
+
+                if collisions::intersects(shape_alpha, shape_beta) {
+                    pairs.push((p_handle.index(), idx));
+                }
+            }
+        }
+
+        pairs
+    }
+}
+
+#[test]
+fn grid_finds_same_pairs_in_same_order() {
+    let mut grid = Grid::new();
+    let mut candidates = Vec::new();
+
+    for seed in 0..16 {
+        let mut scene = Scene::new(seed, 64, 512);
+        let expected = scene.everything();
+
+        assert!(!expected.is_empty(), "seed {}: nothing collides", seed);
+        assert_eq!(
+            expected,
+            scene.grid(&mut grid, &mut candidates),
+            "seed {}",
+            seed
+        );
+    }
+}
+
+#[test]
+fn grid_puts_what_is_outside_of_the_map_along_the_edges() {
+    let mut grid = Grid::new();
+    let mut candidates = Vec::new();
+    let far = Flint::from_num(10000);
+    let bounds = |x: Flint, y: Flint| Bounds {
+        min: FlintVec2::new(x, y),
+        max: FlintVec2::new(x + Flint::ONE, y + Flint::ONE),
+    };
+
+    grid.reset(Flint::from_num(WIDTH), Flint::from_num(HEIGHT));
+    grid.insert(0, &bounds(-far, -far));
+    grid.insert(1, &bounds(far, far));
+
+    grid.query(&bounds(Flint::ZERO, Flint::ZERO), &mut candidates);
+    assert_eq!(candidates, vec![0]);
+
+    grid.query(
+        &bounds(Flint::from_num(WIDTH), Flint::from_num(HEIGHT)),
+        &mut candidates,
+    );
+    assert_eq!(candidates, vec![1]);
+}
+
+// cargo test --release benchmark -- --ignored --nocapture
+#[test]
+#[ignore]
+fn benchmark() {
+    const ITERATIONS: u32 = 16;
+
+    let mut grid = Grid::new();
+    let mut candidates = Vec::new();
+
+    println!("triships projectiles everything       grid");
+
+    for (triships, projectiles) in [(4, 64), (8, 256), (16, 1024), (32, 4096), (64, 8192)] {
+        let time = |f: &mut dyn FnMut(&mut Scene) -> Vec<(usize, usize)>| {
+            let mut total = Duration::ZERO;
+
+            for seed in 0..ITERATIONS {
+                let mut scene = Scene::new(seed as u64, triships, projectiles);
+                let now = Instant::now();
+                f(&mut scene);
+                total += now.elapsed();
+            }
+
+            total / ITERATIONS
+        };
+
+        let with_everything = time(&mut |scene| scene.everything());
+        let with_grid = time(&mut |scene| scene.grid(&mut grid, &mut candidates));
+
+        println!(
+            "{:>8} {:>11} {:>10.2?} {:>10.2?}",
+            triships, projectiles, with_everything, with_grid
+        );
+    }
+}
+
+fn square(x: i32, y: i32, size: i32) -> Vec<FlintVec2> {
+    let point = |x: i32, y: i32| FlintVec2::new(Flint::from_num(x), Flint::from_num(y));
+
+    vec![
+        point(x, y),
+        point(x + size, y),
+        point(x + size, y + size),
+        point(x, y + size),
+    ]
+}
+
+#[test]
+fn time_of_impact_finds_where_they_touch() {
+    let velocity = FlintVec2::new(Flint::from_num(20), Flint::ZERO);
+    let impact = collisions::time_of_impact(&square(0, 0, 10), velocity, &square(20, 5, 10));
+    let impact = impact.expect("moving right into the other square");
+
+    assert_eq!(impact.fraction, Flint::from_num(0.5));
+    assert_eq!(impact.normal, FlintVec2::new(Flint::NEG_ONE, Flint::ZERO));
+}
+
+#[test]
+fn time_of_impact_misses_what_is_not_in_the_way() {
+    let velocity = FlintVec2::new(Flint::from_num(20), Flint::ZERO);
+
+    // too short, moving away, and passing by above
+    for (x, y) in [(40, 0), (-20, 0), (20, 20)] {
+        assert!(
+            collisions::time_of_impact(&square(0, 0, 10), velocity, &square(x, y, 10)).is_none(),
+            "{}, {}",
+            x,
+            y
+        );
+    }
+
+    // passing by diagonally, where the bounds overlap but the shapes never do
+    let velocity = FlintVec2::new(Flint::from_num(30), Flint::from_num(30));
+    assert!(collisions::time_of_impact(&square(0, 0, 10), velocity, &square(24, 0, 10)).is_none());
+}
+
+#[test]
+fn time_of_impact_finds_the_way_out_when_already_overlapping() {
+    let velocity = FlintVec2::new(Flint::ZERO, Flint::ZERO);
+    let impact = collisions::time_of_impact(&square(0, 0, 10), velocity, &square(8, 1, 10));
+    let impact = impact.expect("already overlapping");
+
+    assert_eq!(impact.fraction, Flint::ZERO);
+    assert_eq!(impact.normal, FlintVec2::new(Flint::NEG_ONE, Flint::ZERO));
+}
+
+#[test]
+fn manifold_finds_the_edge_two_squares_share() {
+    let manifold = collisions::manifold(&square(0, 0, 10), &square(8, 2, 10));
+    let manifold = manifold.expect("overlapping by two");
+    let point = |x: i32, y: i32| FlintVec2::new(Flint::from_num(x), Flint::from_num(y));
+
+    assert_eq!(manifold.normal, point(-1, 0));
+    assert_eq!(manifold.depth, Flint::from_num(2));
+    assert_eq!(manifold.mtv(), point(-2, 0));
+    assert_eq!(manifold.points.len(), 2);
+    assert!(manifold.points.contains(&point(8, 2)));
+    assert!(manifold.points.contains(&point(8, 10)));
+}
+
+#[test]
+fn manifold_finds_the_tip_poking_into_a_square() {
+    let point = |x: i32, y: i32| FlintVec2::new(Flint::from_num(x), Flint::from_num(y));
+    let triangle = [point(0, 0), point(10, 5), point(0, 10)];
+
+    let manifold = collisions::manifold(&triangle, &square(9, 0, 10));
+    let manifold = manifold.expect("the tip is inside of the square");
+
+    assert_eq!(manifold.normal, point(-1, 0));
+    assert_eq!(manifold.depth, Flint::ONE);
+    assert_eq!(manifold.points, vec![point(10, 5)]);
+
+    assert!(collisions::manifold(&triangle, &square(11, 0, 10)).is_none());
+}
@@ -7,7 +7,7 @@ use crate::{
     spawner::Spawner,
 };
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub enum Command {
     Nop,
     RotateLeft,
@@ -16,36 +16,87 @@ pub enum Command {
     Decelerate,
     Shoot,
     Explode,
+    SwitchWeapon,
 }
 
 impl Command {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Command::Nop => 0,
+            Command::RotateLeft => 1,
+            Command::RotateRight => 2,
+            Command::Accelerate => 3,
+            Command::Decelerate => 4,
+            Command::Shoot => 5,
+            Command::Explode => 6,
+            Command::SwitchWeapon => 7,
+        }
+    }
+
+    pub fn from_u8(value: u8) -> Option<Command> {
+        match value {
+            0 => Some(Command::Nop),
+            1 => Some(Command::RotateLeft),
+            2 => Some(Command::RotateRight),
+            3 => Some(Command::Accelerate),
+            4 => Some(Command::Decelerate),
+            5 => Some(Command::Shoot),
+            6 => Some(Command::Explode),
+            7 => Some(Command::SwitchWeapon),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "nop" => Some(Command::Nop),
+            "rotate_left" => Some(Command::RotateLeft),
+            "rotate_right" => Some(Command::RotateRight),
+            "accelerate" => Some(Command::Accelerate),
+            "decelerate" => Some(Command::Decelerate),
+            "shoot" => Some(Command::Shoot),
+            "explode" => Some(Command::Explode),
+            "switch_weapon" => Some(Command::SwitchWeapon),
+            _ => None,
+        }
+    }
+
     pub fn exec(&self, pid: usize, entities: &mut Entities, spawner: &Spawner, rng: &mut Rng) {
-        let p = match entities.players.get_mut(pid) {
-            Some(p) => p,
+        let handle = match entities.ship(pid) {
+            Some(handle) => handle,
             None => return,
         };
 
+        let (body, motion, render) = match (
+            entities.triangles.get_mut(handle),
+            entities.motions.get_mut(handle),
+            entities.render_triangles.get(handle),
+        ) {
+            (Some(body), Some(motion), Some(render)) => (body, motion, render),
+            _ => return,
+        };
+
         match self {
             Command::Nop => (),
             Command::RotateLeft => {
-                let rad = p.body.live.direction.radians() - p.motion.rotation_speed;
+                let rad = body.live.direction.radians() - motion.rotation_speed;
                 let (sin, cos) = cordic::sin_cos(rad);
 
-                p.body.live.direction.x = cos;
-                p.body.live.direction.y = sin;
+                body.live.direction.x = cos;
+                body.live.direction.y = sin;
             }
             Command::RotateRight => {
-                let rad = p.body.live.direction.radians() + p.motion.rotation_speed;
+                let rad = body.live.direction.radians() + motion.rotation_speed;
                 let (sin, cos) = cordic::sin_cos(rad);
 
-                p.body.live.direction.x = cos;
-                p.body.live.direction.y = sin;
+                body.live.direction.x = cos;
+                body.live.direction.y = sin;
             }
             Command::Accelerate => {
-                p.motion.speed += p.motion.acceleration;
+                motion.speed += motion.acceleration;
 
-                if p.motion.speed > p.motion.max_speed {
-                    p.motion.speed = p.motion.max_speed;
+                if motion.speed > motion.max_speed {
+                    motion.speed = motion.max_speed;
                 }
 
                 // spawn exhaust particles
@@ -53,33 +104,31 @@ impl Command {
                 // get the unrotated "bottom middle"
                 // TODO: + one unit below to not make the particles spawn inside the ship
                 let centroid = FlintVec2 {
-                    x: (p.body.live.shape.v1.x + p.body.live.shape.v3.x) / 2,
-                    y: (p.body.live.shape.v1.y + p.body.live.shape.v3.y) / 2,
+                    x: (body.live.shape.v1.x + body.live.shape.v3.x) / 2,
+                    y: (body.live.shape.v1.y + body.live.shape.v3.y) / 2,
                 };
 
                 // make sure it's rotated correctly
-                let centroid = centroid.rotated(
-                    p.body.live.direction.radians(),
-                    p.body.live.shape.centroid(),
-                );
+                let centroid =
+                    centroid.rotated(body.live.direction.radians(), body.live.shape.centroid());
 
-                let rotation = p.body.live.direction.rotated_180();
+                let rotation = body.live.direction.rotated_180();
 
                 // to make the initial rendering look correct we also need to adjust
                 // where we put the render centroid
                 let mut render_centroid = RenderVector2 {
-                    x: (((p.render.past.shape.v1.x + p.render.live.shape.v1.x) / 2.0)
-                        + ((p.render.past.shape.v3.x + p.render.live.shape.v3.x) / 2.0))
+                    x: (((render.past.shape.v1.x + render.live.shape.v1.x) / 2.0)
+                        + ((render.past.shape.v3.x + render.live.shape.v3.x) / 2.0))
                         / 2.0,
-                    y: (((p.render.past.shape.v1.y + p.render.live.shape.v1.y) / 2.0)
-                        + ((p.render.past.shape.v3.y + p.render.live.shape.v3.y) / 2.0))
+                    y: (((render.past.shape.v1.y + render.live.shape.v1.y) / 2.0)
+                        + ((render.past.shape.v3.y + render.live.shape.v3.y) / 2.0))
                         / 2.0,
                 };
 
                 // in case ship is accelerating from a negative speed,
                 // we need to adjust the relative speed to not make the particles appear inside the ship
-                let relative_speed = if p.motion.speed < Flint::ZERO {
-                    let s = p.motion.speed * -1 + p.motion.acceleration;
+                let relative_speed = if motion.speed < Flint::ZERO {
+                    let s = motion.speed * -1 + motion.acceleration;
                     let ss = s.to_num::<f32>();
                     render_centroid.x += ss * rotation.x.to_num::<f32>();
                     render_centroid.y += ss * rotation.y.to_num::<f32>();
@@ -90,9 +139,9 @@ impl Command {
                     // if s is set to something like 0.4 it looks 'better',
                     // but the range of the exhaust is too long
                     // perhaps this makes sense..?
-                    let s = -p.motion.speed;
+                    let s = -motion.speed;
                     let ss = s.to_num::<f32>();
-                    let (sin, cos) = p.render.live.angle.sin_cos();
+                    let (sin, cos) = render.live.angle.sin_cos();
                     render_centroid.x += ss * cos;
                     render_centroid.y += ss * sin;
                     s
@@ -100,7 +149,8 @@ impl Command {
 
                 let speed = Flint::from_num(0.12);
 
-                let particles = spawner.spawn_exhaust_particles(
+                spawner.spawn_exhaust_particles(
+                    entities,
                     centroid,
                     render_centroid,
                     rotation,
@@ -108,66 +158,95 @@ impl Command {
                     relative_speed,
                     rng,
                 );
-
-                entities.exhausts.extend(particles);
             }
             Command::Decelerate => {
-                p.motion.speed -= p.motion.acceleration / 2;
+                motion.speed -= motion.acceleration / 2;
 
-                if p.motion.speed < -p.motion.max_speed / 2 {
-                    p.motion.speed = -p.motion.max_speed / 2;
+                if motion.speed < -motion.max_speed / 2 {
+                    motion.speed = -motion.max_speed / 2;
                 }
             }
             Command::Shoot => {
-                // let's put the projectile a little bit in front of the ship,
+                // unarmed ships can only fly around,
+                // the others have to wait until the selected weapon is ready
+                let weapon = match entities.loadouts.get_mut(handle).and_then(|x| x.fire()) {
+                    Some(weapon) => weapon,
+                    None => return,
+                };
+
+                let stats = weapon.stats();
+
+                // let's put the projectiles a little bit in front of the ship,
                 // first we need to get the rotated tip of the ship
-                let radians = p.body.live.direction.radians();
-                let mut centroid = p
-                    .body
+                let radians = body.live.direction.radians();
+                let tip = body
                     .live
                     .shape
                     .v2
-                    .rotated(radians, p.body.live.shape.centroid());
+                    .rotated(radians, body.live.shape.centroid());
 
-                // then we apply the calculated distance to the centroid
-                let distance = Flint::from_num(2);
-
-                centroid.x += distance * p.body.live.direction.x;
-                centroid.y += distance * p.body.live.direction.y;
+                // then we apply the calculated distance to the centroid,
+                // so that the back of the projectile is where the tip is
+                let distance = Flint::from_num(1) + stats.length / 2;
 
                 // to make the initial rendering look correct we also need to adjust
                 // where we put the render centroid
                 // let render_distance = distance.to_num::<f32>();
-                // let mut render_centroid = p.render.live.shape.v2;
-                // let (sin, cos) = p.render.live.angle.sin_cos();
+                // let mut render_centroid = render.live.shape.v2;
+                // let (sin, cos) = render.live.angle.sin_cos();
                 // // TODO: look into why this seems to work, why 0.4? wat
                 // render_centroid.x += render_distance * (cos - 0.4);
                 // render_centroid.y += render_distance * (sin - 0.4);
 
-                let projectile = spawner.spawn_projectile(
-                    centroid,
-                    p.body.live.direction,
-                    // render_centroid,
-                    p.motion.speed + p.motion.acceleration,
-                    pid,
-                );
+                let speed = motion.speed + motion.acceleration;
+                let direction = body.live.direction;
+
+                // fanned out evenly around the way the ship is facing
+                let count = stats.projectiles as i32;
+
+                for idx in 0..count {
+                    let direction = match count {
+                        1 => direction,
+                        _ => {
+                            let offset = stats.spread * (idx * 2 - (count - 1)) / 2;
+                            let (sin, cos) = cordic::sin_cos(radians + offset);
 
-                entities.projectiles.push(projectile);
+                            FlintVec2::new(cos, sin)
+                        }
+                    };
+
+                    let centroid = FlintVec2 {
+                        x: tip.x + distance * direction.x,
+                        y: tip.y + distance * direction.y,
+                    };
+
+                    spawner.spawn_projectile(entities, weapon, centroid, direction, speed, pid);
+                }
+
+                // shooting gives up the protection after respawning
+                if let Some(health) = entities.healths.get_mut(handle) {
+                    health.invulnerable = 0;
+                }
+            }
+            Command::SwitchWeapon => {
+                if let Some(loadout) = entities.loadouts.get_mut(handle) {
+                    loadout.next();
+                }
             }
             Command::Explode => {
-                let explosion = spawner.spawn_explosion_particles(
+                spawner.spawn_explosion_particles(
+                    entities,
                     FlintVec2::new(Flint::from_num(300), Flint::from_num(300)),
                     16,
                     rng,
                 );
-                entities.explosions.extend(explosion);
 
-                let explosion = spawner.spawn_explosion_particles(
+                spawner.spawn_explosion_particles(
+                    entities,
                     FlintVec2::new(Flint::from_num(500), Flint::from_num(300)),
                     128,
                     rng,
                 );
-                entities.explosions.extend(explosion);
             }
         }
     }
@@ -1,14 +1,27 @@
-use crate::math::{Flint, FlintRectangle, FlintTriangle, FlintVec2};
+use crate::{
+    math::{Flint, FlintPolygon, FlintRectangle, FlintTriangle, FlintVec2},
+    modes::Ending,
+};
 
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone)]
 pub struct Miscellaneous {
     pub player_death_counters: Vec<(usize, Counter)>,
     pub player_map_spawn_indexes: Vec<usize>,
+    // the pickup points of the map waiting for their next pickup
+    pub map_pickup_counters: Vec<(usize, Counter)>,
+    // set once the match is over, it never changes after that
+    pub ending: Option<Ending>,
 }
 
+#[derive(Clone)]
 pub struct Counter {
     pub value: i32,
 }
 
+#[derive(Clone)]
 pub struct Body<T> {
     pub live: Shape<T>,
     pub past: Shape<T>,
@@ -16,17 +29,44 @@ pub struct Body<T> {
     pub axes: Vec<FlintVec2>,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct Shape<T> {
     pub shape: T,
     pub direction: FlintVec2,
 }
 
+#[derive(Clone)]
 pub struct Motion {
     pub speed: Flint,
     pub max_speed: Flint,
     pub acceleration: Flint,
     pub rotation_speed: Flint,
+    // slowing down every tick until standing still, zero to keep going forever
+    pub deceleration: Flint,
+}
+
+#[derive(Clone)]
+pub struct Health {
+    pub life: Flint,
+    // taken before the life, regenerating once nothing has hurt for a while
+    pub shield: Flint,
+    pub max_shield: Flint,
+    // ticks left until the shield starts regenerating
+    pub recharge: i32,
+    // ticks left where nothing hurts, right after respawning
+    pub invulnerable: i32,
+    // nothing hurts while shielded
+    pub shielded: bool,
+    // whoever hurt it last, none when it crashed by itself
+    pub attacker: Option<usize>,
+    // everyone that has hurt it since it spawned, in the order they first did
+    pub attackers: Vec<usize>,
+}
+
+// what the systems need of a shape to move it around, no matter whose body it is
+pub trait Movable: PartialEq {
+    fn translate(&mut self, offset: FlintVec2);
+    fn centroid(&self) -> FlintVec2;
 }
 
 impl Miscellaneous {
@@ -34,12 +74,74 @@ impl Miscellaneous {
         Self {
             player_death_counters: Vec::new(),
             player_map_spawn_indexes: Vec::new(),
+            map_pickup_counters: Vec::new(),
+            ending: None,
         }
     }
 
+    #[cfg(feature = "graphics")]
     pub fn clear(&mut self) {
         self.player_death_counters.clear();
         self.player_map_spawn_indexes.clear();
+        self.map_pickup_counters.clear();
+        self.ending = None;
+    }
+}
+
+impl Health {
+    // ticks since the last hit until the shield regenerates
+    pub const RECHARGE: i32 = 48;
+
+    pub fn new(life: Flint, shield: Flint) -> Self {
+        Health {
+            life,
+            shield,
+            max_shield: shield,
+            recharge: 0,
+            invulnerable: 0,
+            shielded: false,
+            attacker: None,
+            attackers: Vec::new(),
+        }
+    }
+
+    // regenerated every tick once recharged
+    pub fn regeneration() -> Flint {
+        Flint::from_num(0.25)
+    }
+
+    // the shield takes what it can and the life the rest, returns how much of both was taken,
+    // nothing once dead so whoever dealt the killing blow stays the attacker
+    pub fn hurt(&mut self, damage: Flint, attacker: Option<usize>) -> Flint {
+        if self.shielded || self.invulnerable > 0 || damage <= Flint::ZERO || self.life <= 0 {
+            return Flint::ZERO;
+        }
+
+        self.attacker = attacker;
+
+        if let Some(pid) = attacker.filter(|x| !self.attackers.contains(x)) {
+            self.attackers.push(pid);
+        }
+
+        let absorbed = damage.min(self.shield);
+        let lost = (damage - absorbed).min(self.life);
+
+        self.shield -= absorbed;
+        self.life -= damage - absorbed;
+        self.recharge = Health::RECHARGE;
+
+        absorbed + lost
+    }
+
+    pub fn update(&mut self) {
+        self.invulnerable = (self.invulnerable - 1).max(0);
+
+        if self.recharge > 0 {
+            self.recharge -= 1;
+            return;
+        }
+
+        self.shield = (self.shield + Health::regeneration()).min(self.max_shield);
     }
 }
 
@@ -183,3 +285,82 @@ impl Body<FlintTriangle> {
         &self.axes
     }
 }
+
+impl Body<FlintPolygon> {
+    pub fn new(shape: Shape<FlintPolygon>) -> Self {
+        let mut body = Self {
+            live: shape.clone(),
+            past: shape,
+            dirty: true,
+            axes: Vec::new(),
+        };
+
+        body.calc_axes();
+
+        body
+    }
+
+    pub fn calc_axes(&mut self) -> &[FlintVec2] {
+        if self.dirty {
+            // polygons never rotate, the points are already where they should be
+            self.axes.clear();
+            self.axes.extend_from_slice(&self.live.shape.points);
+
+            self.dirty = false;
+        }
+
+        &self.axes
+    }
+}
+
+impl<T: Movable> Body<T> {
+    pub fn apply_velocity(&mut self, motion: &Motion) {
+        self.live
+            .shape
+            .translate(self.live.direction * motion.speed);
+    }
+
+    // the past body comes along, so it's not swept along the way
+    pub fn teleport(&mut self, offset: FlintVec2) {
+        self.live.shape.translate(offset);
+        self.past.shape.translate(offset);
+        self.dirty = true;
+    }
+
+    pub fn push(&mut self, offset: FlintVec2) {
+        self.live.shape.translate(offset);
+        self.dirty = true;
+    }
+}
+
+impl Movable for FlintVec2 {
+    fn translate(&mut self, offset: FlintVec2) {
+        *self += offset;
+    }
+
+    fn centroid(&self) -> FlintVec2 {
+        *self
+    }
+}
+
+impl Movable for FlintTriangle {
+    fn translate(&mut self, offset: FlintVec2) {
+        self.v1 += offset;
+        self.v2 += offset;
+        self.v3 += offset;
+    }
+
+    fn centroid(&self) -> FlintVec2 {
+        FlintTriangle::centroid(self)
+    }
+}
+
+impl Movable for FlintRectangle {
+    fn translate(&mut self, offset: FlintVec2) {
+        self.point += offset;
+    }
+
+    fn centroid(&self) -> FlintVec2 {
+        FlintRectangle::centroid(self)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::math::Flint;
+
+use super::Health;
+
+fn flint(value: i32) -> Flint {
+    Flint::from_num(value)
+}
+
+#[test]
+fn the_shield_takes_damage_before_the_life() {
+    let mut health = Health::new(flint(50), flint(20));
+
+    assert_eq!(health.hurt(flint(30), Some(1)), flint(30));
+    assert_eq!((health.life, health.shield), (flint(40), flint(0)));
+    assert_eq!(health.recharge, Health::RECHARGE);
+    assert_eq!(health.attacker, Some(1));
+    assert_eq!(health.attackers, vec![1]);
+}
+
+#[test]
+fn only_what_is_left_is_taken() {
+    let mut health = Health::new(flint(10), flint(5));
+
+    assert_eq!(health.hurt(flint(100), Some(1)), flint(15));
+    assert!(health.life <= 0);
+}
+
+#[test]
+fn nothing_hurts_once_dead() {
+    let mut health = Health::new(flint(10), flint(0));
+
+    health.hurt(flint(10), Some(1));
+    assert!(health.life <= 0);
+
+    // the killing blow stays with whoever dealt it
+    assert_eq!(health.hurt(flint(10), Some(2)), flint(0));
+    assert_eq!(health.attacker, Some(1));
+    assert_eq!(health.attackers, vec![1]);
+}
+
+#[test]
+fn nothing_hurts_while_protected() {
+    let mut health = Health::new(flint(10), flint(0));
+
+    health.invulnerable = 1;
+    assert_eq!(health.hurt(flint(5), Some(1)), flint(0));
+
+    health.invulnerable = 0;
+    health.shielded = true;
+    assert_eq!(health.hurt(flint(5), Some(1)), flint(0));
+
+    assert_eq!(health.life, flint(10));
+    assert_eq!(health.attacker, None);
+    assert!(health.attackers.is_empty());
+}
@@ -1,8 +1,9 @@
-use raylib::prelude::{Color, Rectangle, Vector2};
+use std::ops::Sub;
 
 use crate::components::logic::Body;
-use crate::math::{FlintRectangle, FlintTriangle, FlintVec2};
+use crate::math::{FlintPolygon, FlintRectangle, FlintTriangle, FlintVec2};
 
+#[derive(Clone)]
 pub struct Renderable<T> {
     pub color: RenderColor,
     pub live: RenderBody<T>,
@@ -15,10 +16,35 @@ pub struct RenderBody<T> {
     pub angle: f32,
 }
 
-// let's reuse what's in raylib
-pub type RenderColor = Color;
-pub type RenderVector2 = Vector2;
-pub type RenderRectangle = Rectangle;
+// plain data so the world can be simulated without any graphics,
+// these are converted to what raylib expects when drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderVector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderRectangle {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Clone)]
+pub struct RenderPolygon {
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    pub points: Vec<RenderVector2>,
+}
 
 #[derive(Clone, Copy)]
 pub struct RenderTriangle {
@@ -27,9 +53,90 @@ pub struct RenderTriangle {
     pub v3: RenderVector2,
 }
 
+impl RenderColor {
+    pub const DIMGRAY: RenderColor = RenderColor::new(105, 105, 105, 255);
+    pub const LIGHTYELLOW: RenderColor = RenderColor::new(255, 255, 224, 255);
+    pub const LIGHTSKYBLUE: RenderColor = RenderColor::new(135, 206, 250, 255);
+    pub const LIGHTGRAY: RenderColor = RenderColor::new(200, 200, 200, 255);
+    pub const ORANGE: RenderColor = RenderColor::new(255, 165, 0, 255);
+    pub const TOMATO: RenderColor = RenderColor::new(255, 99, 71, 255);
+    pub const AQUA: RenderColor = RenderColor::new(0, 255, 255, 255);
+    pub const LIMEGREEN: RenderColor = RenderColor::new(50, 205, 50, 255);
+    pub const VIOLET: RenderColor = RenderColor::new(238, 130, 238, 255);
+    pub const CRIMSON: RenderColor = RenderColor::new(220, 20, 60, 255);
+    pub const ROYALBLUE: RenderColor = RenderColor::new(65, 105, 225, 255);
+    pub const SEAGREEN: RenderColor = RenderColor::new(46, 139, 87, 255);
+    pub const GOLD: RenderColor = RenderColor::new(255, 215, 0, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        RenderColor { r, g, b, a }
+    }
+}
+
+impl RenderVector2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        RenderVector2 { x, y }
+    }
+
+    #[cfg(feature = "graphics")]
+    pub fn lerp(&self, other: RenderVector2, amount: f32) -> RenderVector2 {
+        RenderVector2 {
+            x: lerp(self.x, other.x, amount),
+            y: lerp(self.y, other.y, amount),
+        }
+    }
+}
+
+impl Sub for RenderVector2 {
+    type Output = RenderVector2;
+
+    fn sub(self, rhs: RenderVector2) -> Self::Output {
+        RenderVector2 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
+// what the systems need of a render shape to move it around, no matter whose it is
+pub trait Translatable {
+    fn translate(&mut self, offset: RenderVector2);
+}
+
+#[cfg(feature = "graphics")]
 impl<T> Renderable<T> {
     pub fn lerp_angle(&self, amount: f32) -> f32 {
-        raylib::math::lerp(self.past.angle, self.live.angle, amount)
+        lerp(self.past.angle, self.live.angle, amount)
+    }
+}
+
+impl<T: Translatable> Renderable<T> {
+    // when wrapping around the map the past body must come along,
+    // otherwise it's interpolated across the whole map
+    pub fn translate_past(&mut self, offset: RenderVector2) {
+        self.past.shape.translate(offset);
+    }
+}
+
+impl Translatable for RenderVector2 {
+    fn translate(&mut self, offset: RenderVector2) {
+        self.x += offset.x;
+        self.y += offset.y;
+    }
+}
+
+impl Translatable for RenderTriangle {
+    fn translate(&mut self, offset: RenderVector2) {
+        self.v1.translate(offset);
+        self.v2.translate(offset);
+        self.v3.translate(offset);
+    }
+}
+
+impl Translatable for RenderRectangle {
+    fn translate(&mut self, offset: RenderVector2) {
+        self.x += offset.x;
+        self.y += offset.y;
     }
 }
 
@@ -42,6 +149,7 @@ impl Renderable<RenderVector2> {
         }
     }
 
+    #[cfg(feature = "graphics")]
     pub fn lerp(&self, amount: f32) -> RenderVector2 {
         self.past.shape.lerp(self.live.shape, amount)
     }
@@ -66,8 +174,8 @@ impl From<&Body<FlintVec2>> for RenderBody<RenderVector2> {
 }
 
 impl RenderTriangle {
-    pub fn centroid(&self) -> Vector2 {
-        Vector2 {
+    pub fn centroid(&self) -> RenderVector2 {
+        RenderVector2 {
             x: (self.v1.x + self.v2.x + self.v3.x) / 3.0,
             y: (self.v1.y + self.v2.y + self.v3.y) / 3.0,
         }
@@ -76,7 +184,7 @@ impl RenderTriangle {
     fn rotate(&mut self, amount: f32) {
         let cen = self.centroid();
         let (sin, cos) = amount.sin_cos();
-        let rot = |v: &mut Vector2| {
+        let rot = |v: &mut RenderVector2| {
             let x = (cos * (v.x - cen.x)) - (sin * (v.y - cen.y)) + cen.x;
             let y = (sin * (v.x - cen.x)) + (cos * (v.y - cen.y)) + cen.y;
             v.x = x;
@@ -101,25 +209,30 @@ impl Renderable<RenderTriangle> {
         }
     }
 
-    pub fn lerp_v1(&self, amount: f32) -> Vector2 {
+    #[cfg(feature = "graphics")]
+    pub fn lerp_v1(&self, amount: f32) -> RenderVector2 {
         self.past.shape.v1.lerp(self.live.shape.v1, amount)
     }
 
-    pub fn lerp_v2(&self, amount: f32) -> Vector2 {
+    #[cfg(feature = "graphics")]
+    pub fn lerp_v2(&self, amount: f32) -> RenderVector2 {
         self.past.shape.v2.lerp(self.live.shape.v2, amount)
     }
 
-    pub fn lerp_v3(&self, amount: f32) -> Vector2 {
+    #[cfg(feature = "graphics")]
+    pub fn lerp_v3(&self, amount: f32) -> RenderVector2 {
         self.past.shape.v3.lerp(self.live.shape.v3, amount)
     }
 
-    pub fn lerp_centroid(&self, amount: f32) -> Vector2 {
+    #[cfg(feature = "graphics")]
+    pub fn lerp_centroid(&self, amount: f32) -> RenderVector2 {
         self.past
             .shape
             .centroid()
             .lerp(self.live.shape.centroid(), amount)
     }
 
+    #[cfg(feature = "graphics")]
     pub fn lerp(&self, amount: f32) -> RenderTriangle {
         RenderTriangle {
             v1: self.lerp_v1(amount),
@@ -132,15 +245,15 @@ impl Renderable<RenderTriangle> {
 impl From<FlintTriangle> for RenderTriangle {
     fn from(value: FlintTriangle) -> Self {
         Self {
-            v1: Vector2 {
+            v1: RenderVector2 {
                 x: value.v1.x.to_num(),
                 y: value.v1.y.to_num(),
             },
-            v2: Vector2 {
+            v2: RenderVector2 {
                 x: value.v2.x.to_num(),
                 y: value.v2.y.to_num(),
             },
-            v3: Vector2 {
+            v3: RenderVector2 {
                 x: value.v3.x.to_num(),
                 y: value.v3.y.to_num(),
             },
@@ -167,12 +280,13 @@ impl Renderable<RenderRectangle> {
         }
     }
 
+    #[cfg(feature = "graphics")]
     pub fn lerp(&self, amount: f32) -> RenderRectangle {
-        Rectangle {
-            x: raylib::math::lerp(self.past.shape.x, self.live.shape.x, amount),
-            y: raylib::math::lerp(self.past.shape.y, self.live.shape.y, amount),
-            width: raylib::math::lerp(self.past.shape.width, self.live.shape.width, amount),
-            height: raylib::math::lerp(self.past.shape.height, self.live.shape.height, amount),
+        RenderRectangle {
+            x: lerp(self.past.shape.x, self.live.shape.x, amount),
+            y: lerp(self.past.shape.y, self.live.shape.y, amount),
+            width: lerp(self.past.shape.width, self.live.shape.width, amount),
+            height: lerp(self.past.shape.height, self.live.shape.height, amount),
         }
     }
 }
@@ -196,3 +310,50 @@ impl From<&Body<FlintRectangle>> for RenderBody<RenderRectangle> {
         Self { shape, angle }
     }
 }
+
+impl Renderable<RenderPolygon> {
+    pub fn new(color: RenderColor, shape: RenderPolygon, angle: f32) -> Self {
+        Renderable {
+            color,
+            live: RenderBody {
+                shape: shape.clone(),
+                angle,
+            },
+            past: RenderBody { shape, angle },
+        }
+    }
+}
+
+impl From<&FlintPolygon> for RenderPolygon {
+    fn from(value: &FlintPolygon) -> Self {
+        Self {
+            points: value.points.iter().map(|x| (*x).into()).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<RenderColor> for raylib::prelude::Color {
+    fn from(value: RenderColor) -> Self {
+        Self::new(value.r, value.g, value.b, value.a)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<RenderVector2> for raylib::prelude::Vector2 {
+    fn from(value: RenderVector2) -> Self {
+        Self::new(value.x, value.y)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<RenderRectangle> for raylib::prelude::Rectangle {
+    fn from(value: RenderRectangle) -> Self {
+        Self::new(value.x, value.y, value.width, value.height)
+    }
+}
+
+#[cfg(feature = "graphics")]
+fn lerp(start: f32, end: f32, amount: f32) -> f32 {
+    start + amount * (end - start)
+}
@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use raylib::prelude::*;
 
 use crate::{
@@ -5,6 +7,7 @@ use crate::{
     bus::Bus,
     logs::LogManager,
     messages::{EngineRequestMessage, Message, RequestMessage, Sender, StateRequestMessage},
+    net::NetManager,
     states::{State, StateManager},
 };
 
@@ -23,6 +26,7 @@ pub struct Managers {
     pub log: LogManager,
     pub state: StateManager,
     pub audio: AudioManager,
+    pub net: NetManager,
 }
 
 impl Engine {
@@ -35,20 +39,21 @@ impl Engine {
     pub const WIDTH: i32 = 640;
     pub const HEIGHT: i32 = 360;
 
-    pub fn new() -> Self {
+    pub fn new(address: SocketAddr) -> Self {
         Engine {
             managers: Managers {
                 engine: EngineManager::new(),
                 log: LogManager::new(),
                 state: StateManager::new(),
                 audio: AudioManager::new(),
+                net: NetManager::new(address),
             },
             bus: Bus::new(),
             ticks: 0,
         }
     }
 
-    pub fn run(&mut self, mut rh: &mut RaylibHandle, rt: &RaylibThread) {
+    pub fn run(&mut self, rh: &mut RaylibHandle, rt: &RaylibThread) {
         // use a render texture instead of drawing directly to screen,
         // this is to support different resolutions
         // TODO: make sure the aspect ratio is the same as the screen resolution,
@@ -89,7 +94,7 @@ impl Engine {
             let delta = accumulator / self.managers.engine.size;
 
             // draw as often as possible
-            self.draw(rt, &mut rh, &mut rrt, delta);
+            self.draw(rt, rh, &mut rrt, delta);
         }
     }
 
@@ -121,6 +126,8 @@ impl Engine {
             .engine
             .update(self.bus.with_sender(Sender::Engine));
 
+        self.managers.net.update(self.bus.with_sender(Sender::Net));
+
         self.managers
             .state
             .update(self.bus.with_sender(Sender::State));
@@ -9,6 +9,12 @@ pub struct EngineManager {
     pub(super) tps: u8,
     pub(super) size: f32,
     pub(super) debug: bool,
+    // the map picked in the menu, used when hosting or playing alone
+    map: String,
+    // the mode picked in the menu, used when hosting or playing alone
+    mode: String,
+    // the ship class picked in the menu, used when joining, hosting or playing alone
+    ship: String,
     actions: BTreeSet<Action>,
 }
 
@@ -17,6 +23,12 @@ enum Action {
     SetTicksPerSecond(u8),
     SetDebug(bool),
     GetDebug,
+    SetMap(String),
+    GetMap,
+    SetMode(String),
+    GetMode,
+    SetShip(String),
+    GetShip,
 }
 
 impl EngineManager {
@@ -25,6 +37,9 @@ impl EngineManager {
             tps: 0,
             size: 0.0,
             debug: false,
+            map: String::from("arena"),
+            mode: String::from("deathmatch"),
+            ship: String::from("fighter"),
             actions: BTreeSet::new(),
         }
     }
@@ -50,6 +65,24 @@ impl EngineManager {
             EngineRequestMessage::GetDebug => {
                 self.actions.insert(Action::GetDebug);
             }
+            EngineRequestMessage::SetMap(map) => {
+                self.actions.insert(Action::SetMap(map.clone()));
+            }
+            EngineRequestMessage::GetMap => {
+                self.actions.insert(Action::GetMap);
+            }
+            EngineRequestMessage::SetMode(mode) => {
+                self.actions.insert(Action::SetMode(mode.clone()));
+            }
+            EngineRequestMessage::GetMode => {
+                self.actions.insert(Action::GetMode);
+            }
+            EngineRequestMessage::SetShip(ship) => {
+                self.actions.insert(Action::SetShip(ship.clone()));
+            }
+            EngineRequestMessage::GetShip => {
+                self.actions.insert(Action::GetShip);
+            }
         }
     }
 
@@ -59,8 +92,6 @@ impl EngineManager {
                 Action::SetTicksPerSecond(tps) => {
                     self.tps = tps;
                     self.size = 1.0 / tps as f32;
-
-                    bus.send(Message::Engine(EngineMessage::TicksPerSecondSet(tps)));
                 }
                 Action::SetDebug(debug) => {
                     self.debug = debug;
@@ -70,6 +101,30 @@ impl EngineManager {
                 Action::GetDebug => {
                     bus.send(Message::Engine(EngineMessage::DebugGet(self.debug)));
                 }
+                Action::SetMap(map) => {
+                    self.map = map.clone();
+
+                    bus.send(Message::Engine(EngineMessage::MapSet(map)));
+                }
+                Action::GetMap => {
+                    bus.send(Message::Engine(EngineMessage::MapGet(self.map.clone())));
+                }
+                Action::SetMode(mode) => {
+                    self.mode = mode.clone();
+
+                    bus.send(Message::Engine(EngineMessage::ModeSet(mode)));
+                }
+                Action::GetMode => {
+                    bus.send(Message::Engine(EngineMessage::ModeGet(self.mode.clone())));
+                }
+                Action::SetShip(ship) => {
+                    self.ship = ship.clone();
+
+                    bus.send(Message::Engine(EngineMessage::ShipSet(ship)));
+                }
+                Action::GetShip => {
+                    bus.send(Message::Engine(EngineMessage::ShipGet(self.ship.clone())));
+                }
             }
         }
     }
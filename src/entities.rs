@@ -1,52 +1,76 @@
-use raylib::prelude::Vector2;
-
 use crate::{
-    components::logic::{Body, Motion},
-    components::render::{RenderRectangle, RenderTriangle, Renderable},
-    math::{Flint, FlintRectangle, FlintTriangle, FlintVec2},
+    arena::{Arena, Components, Handle},
+    components::logic::{Body, Health, Motion},
+    components::render::{
+        RenderPolygon, RenderRectangle, RenderTriangle, RenderVector2, Renderable,
+    },
+    math::{Flint, FlintPolygon, FlintRectangle, FlintTriangle, FlintVec2},
+    pickups::{Effect, Pickup},
+    players::Player,
+    weapons::Loadout,
 };
 
+// what messages and tasks refer to entities by, since they are handled a tick later
+// the entity might be gone by then, looking it up then simply finds nothing
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub enum EntityTypeIndex {
-    Triship(usize),
-    Projectile(usize),
-    // Particle(usize),
-}
-
-pub struct Entities {
-    pub players: Vec<Triship>, // TODO: should be triships
-    pub projectiles: Vec<Projectile>,
-    pub stars: Vec<Star>,
-    pub exhausts: Vec<Particle>,
-    pub explosions: Vec<Particle>,
-}
-
-pub struct Triship {
-    pub body: Body<FlintTriangle>,
-    pub motion: Motion,
-    pub render: Renderable<RenderTriangle>,
-    pub dead: bool,
-    pub life: Flint,
+pub enum EntityHandle {
+    Triship(Handle),
+    Projectile(Handle),
+    Obstacle(Handle),
+    Pickup(Handle),
+    // Particle(Handle),
 }
 
-pub struct Projectile {
-    pub body: Body<FlintRectangle>,
-    pub motion: Motion,
-    pub render: Renderable<RenderRectangle>,
-    pub dead: bool,
-    pub pid: usize,
-    pub dmg: Flint,
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Triship,
+    Projectile,
+    Obstacle,
+    Pickup,
+    Exhaust,
+    Explosion,
 }
 
-pub struct Particle {
-    pub body: Body<FlintVec2>,
-    pub motion: Motion,
-    pub lifetime: i32,
-    pub render: Renderable<Vector2>,
-    pub dead: bool,
-    pub amount: u8, // TODO: naming...
+// every entity is a handle and the components it has,
+// the systems go through the components rather than the kinds of entities,
+// so a new kind of entity is mostly a new combination of components
+#[derive(Clone)]
+pub struct Entities {
+    pub kinds: Arena<Kind>,
+    // indexed by pid, they stay even when their ship is gone
+    pub players: Vec<Player>,
+    pub triangles: Components<Body<FlintTriangle>>,
+    pub rectangles: Components<Body<FlintRectangle>>,
+    pub polygons: Components<Body<FlintPolygon>>,
+    pub points: Components<Body<FlintVec2>>,
+    pub motions: Components<Motion>,
+    pub render_triangles: Components<Renderable<RenderTriangle>>,
+    pub render_rectangles: Components<Renderable<RenderRectangle>>,
+    pub render_polygons: Components<Renderable<RenderPolygon>>,
+    pub render_points: Components<Renderable<RenderVector2>>,
+    // ticks left until dying
+    pub lifetimes: Components<i32>,
+    pub healths: Components<Health>,
+    // the player it belongs to
+    pub owners: Components<usize>,
+    // dealt to whatever it hits
+    pub damages: Components<Flint>,
+    // the radians a tick it steers toward the nearest enemy
+    pub homings: Components<Flint>,
+    // what it's armed with
+    pub loadouts: Components<Loadout>,
+    // what collecting it gives, and the pickup point of the map it lies on
+    pub pickups: Components<(Pickup, usize)>,
+    // what the pickups it collected still do to it
+    pub effects: Components<Vec<Effect>>,
+    // how much the color fades every tick, TODO: naming...
+    pub fades: Components<u8>,
+    pub dead: Components<()>,
+    // the backdrop, repeated across the whole map and never collided with
+    pub stars: Vec<Star>,
 }
 
+#[derive(Clone)]
 pub struct Star {
     pub body: Body<FlintRectangle>,
     pub render: Renderable<RenderRectangle>,
@@ -58,27 +82,106 @@ pub struct Star {
 impl Entities {
     pub fn new() -> Self {
         Entities {
+            kinds: Arena::new(),
             players: Vec::new(),
-            projectiles: Vec::new(),
+            triangles: Components::new(),
+            rectangles: Components::new(),
+            polygons: Components::new(),
+            points: Components::new(),
+            motions: Components::new(),
+            render_triangles: Components::new(),
+            render_rectangles: Components::new(),
+            render_polygons: Components::new(),
+            render_points: Components::new(),
+            lifetimes: Components::new(),
+            healths: Components::new(),
+            owners: Components::new(),
+            damages: Components::new(),
+            homings: Components::new(),
+            loadouts: Components::new(),
+            pickups: Components::new(),
+            effects: Components::new(),
+            fades: Components::new(),
+            dead: Components::new(),
             stars: Vec::new(),
-            exhausts: Vec::new(),
-            explosions: Vec::new(),
         }
     }
 
+    #[cfg(feature = "graphics")]
     pub fn clear(&mut self) {
+        self.kinds.clear();
         self.players.clear();
-        self.projectiles.clear();
+        self.triangles.clear();
+        self.rectangles.clear();
+        self.polygons.clear();
+        self.points.clear();
+        self.motions.clear();
+        self.render_triangles.clear();
+        self.render_rectangles.clear();
+        self.render_polygons.clear();
+        self.render_points.clear();
+        self.lifetimes.clear();
+        self.healths.clear();
+        self.owners.clear();
+        self.damages.clear();
+        self.homings.clear();
+        self.loadouts.clear();
+        self.pickups.clear();
+        self.effects.clear();
+        self.fades.clear();
+        self.dead.clear();
         self.stars.clear();
-        self.exhausts.clear();
-        self.explosions.clear();
     }
 
+    #[cfg(feature = "graphics")]
     pub fn count(&self) -> usize {
-        self.players.len()
-            + self.projectiles.len()
-            + self.stars.len()
-            + self.exhausts.len()
-            + self.explosions.len()
+        self.kinds.len() + self.stars.len()
+    }
+
+    // the components are added by whoever spawns it
+    pub fn spawn(&mut self, kind: Kind) -> Handle {
+        self.kinds.insert(kind)
+    }
+
+    pub fn despawn(&mut self, handle: Handle) -> Option<Kind> {
+        let kind = self.kinds.remove(handle)?;
+
+        self.triangles.remove(handle);
+        self.rectangles.remove(handle);
+        self.polygons.remove(handle);
+        self.points.remove(handle);
+        self.motions.remove(handle);
+        self.render_triangles.remove(handle);
+        self.render_rectangles.remove(handle);
+        self.render_polygons.remove(handle);
+        self.render_points.remove(handle);
+        self.lifetimes.remove(handle);
+        self.healths.remove(handle);
+        self.owners.remove(handle);
+        self.damages.remove(handle);
+        self.homings.remove(handle);
+        self.loadouts.remove(handle);
+        self.pickups.remove(handle);
+        self.effects.remove(handle);
+        self.fades.remove(handle);
+        self.dead.remove(handle);
+
+        Some(kind)
+    }
+
+    pub fn kind(&self, handle: Handle) -> Option<Kind> {
+        self.kinds.get(handle).copied()
+    }
+
+    pub fn is_dead(&self, handle: Handle) -> bool {
+        self.dead.contains(handle)
+    }
+
+    // the triship the player is flying, if any
+    pub fn ship(&self, pid: usize) -> Option<Handle> {
+        self.players
+            .get(pid)
+            .and_then(|x| x.ship)
+            .filter(|x| self.kinds.contains(*x))
     }
 }
@@ -0,0 +1,372 @@
+use std::{fs, path::PathBuf, time::Instant};
+
+use crate::{
+    commands::Command,
+    map::Map,
+    modes::{Modes, Team},
+    replay::Replay,
+    ships::ShipClasses,
+    world::World,
+};
+
+// runs the world as fast as possible without a window,
+// e.g. to check that a match plays out the same in ci or on a server
+//
+//     fejd --headless --seed 42 --players 2 --ticks 256 --script script.txt
+//     fejd --headless --players 3 --ships scout,gunship --script script.txt
+//     fejd --headless --players 2 --mode survival --script script.txt
+//     fejd --headless --replay replays/1700000000.fjr
+//
+// the world is only ever given the commands, so a script simply becomes a replay
+struct Options {
+    seed: u64,
+    players: u8,
+    ticks: Option<usize>,
+    map: String,
+    mode: String,
+    // the ship class of every player by pid, the rest get the first class
+    ships: Vec<String>,
+    script: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    save: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            seed: 0,
+            players: 1,
+            ticks: None,
+            map: String::from("arena"),
+            mode: String::from("deathmatch"),
+            ships: Vec::new(),
+            script: None,
+            replay: None,
+            save: false,
+        };
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            // every option but save takes a value
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--seed" => options.seed = parse_number(arg, value()?)?,
+                "--players" => options.players = parse_number(arg, value()?)?,
+                "--ticks" => options.ticks = Some(parse_number(arg, value()?)?),
+                "--map" => options.map = value()?.to_owned(),
+                "--mode" => options.mode = value()?.to_owned(),
+                "--ships" => options.ships = value()?.split(',').map(String::from).collect(),
+                "--script" => options.script = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--save" => options.save = true,
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+
+        if options.ships.len() > options.players as usize {
+            return Err(format!(
+                "{} ships given for {} players",
+                options.ships.len(),
+                options.players
+            ));
+        }
+
+        if options.script.is_some() && options.replay.is_some() {
+            return Err(String::from(
+                "either a script or a replay can be given, not both",
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+
+    let classes = ShipClasses::load()
+        .map_err(|e| format!("failed to load ship classes {}: {}", ShipClasses::PATH, e))?;
+
+    // a replay knows the ships, otherwise they are the ones we're given
+    let ships = || -> Result<Vec<String>, String> {
+        Ok(classes
+            .pick(&options.ships, options.players as usize)?
+            .into_iter()
+            .map(|x| x.name)
+            .collect())
+    };
+
+    let mut replay = match (&options.replay, &options.script) {
+        (Some(path), _) => Replay::load(path)
+            .map_err(|e| format!("failed to load replay {}: {}", path.display(), e))?,
+        (None, Some(path)) => {
+            let script = fs::read_to_string(path)
+                .map_err(|e| format!("failed to read script {}: {}", path.display(), e))?;
+
+            let mut replay = Replay::new(
+                options.seed,
+                0,
+                options.map.clone(),
+                options.mode.clone(),
+                ships()?,
+            )
+            .map_err(|e| format!("failed to record script {}: {}", path.display(), e))?;
+
+            for cmds in parse_script(&script, options.players as usize)? {
+                replay
+                    .record(&cmds)
+                    .map_err(|e| format!("failed to record script {}: {}", path.display(), e))?;
+            }
+
+            replay
+        }
+        (None, None) => Replay::new(
+            options.seed,
+            0,
+            options.map.clone(),
+            options.mode.clone(),
+            ships()?,
+        )
+        .map_err(|e| format!("failed to record: {}", e))?,
+    };
+
+    let map =
+        Map::load(&replay.map).map_err(|e| format!("failed to load map {}: {}", replay.map, e))?;
+
+    if replay.players == 0 || replay.players > map.max_players() {
+        return Err(format!(
+            "map {} has room for 1 to {} players, not {}",
+            map.name,
+            map.max_players(),
+            replay.players
+        ));
+    }
+
+    // without a given amount we run until the commands run out,
+    // any ticks after that are simply ticks without any commands
+    let ticks = options.ticks.unwrap_or(replay.ticks.len());
+
+    while replay.ticks.len() < ticks {
+        replay.ticks.push(vec![Vec::new(); replay.players as usize]);
+    }
+
+    replay.ticks.truncate(ticks);
+
+    let ships = classes.pick(&replay.ships, replay.players as usize)?;
+
+    let mode = Modes::load()
+        .map_err(|e| format!("failed to load modes {}: {}", Modes::PATH, e))?
+        .pick(&replay.mode)?;
+
+    let mut world = World::new();
+    world.init(replay.pid as usize, ships, replay.seed, map, mode);
+
+    // whatever the replay has recorded is what we compare with,
+    // what we calculate ourselves is what gets saved
+    let recorded = Replay {
+        checksums: replay.checksums.drain(..).collect(),
+        ..Replay::new(
+            replay.seed,
+            replay.pid,
+            replay.map.clone(),
+            replay.mode.clone(),
+            replay.ships.clone(),
+        )
+        .map_err(|e| format!("failed to record: {}", e))?
+    };
+
+    let mut desync = None;
+    let now = Instant::now();
+
+    for cmds in replay.ticks.iter() {
+        world.simulate(cmds);
+
+        let checksum = world.checksum();
+
+        if desync.is_none() {
+            desync = recorded.verify(&checksum).map(|x| {
+                format!(
+                    "desynced from the replay at tick {}, {} diverged",
+                    checksum.tick, x
+                )
+            });
+        }
+
+        replay.checksums.push(checksum);
+
+        // nothing is played after the match is over
+        if world.ending().is_some() {
+            break;
+        }
+    }
+
+    replay.ticks.truncate(world.tick() as usize);
+
+    let elapsed = now.elapsed();
+
+    println!(
+        "simulated {} ticks in {:?}, map {}, seed {}, {} players, checksum {:016x}",
+        world.tick(),
+        elapsed,
+        replay.map,
+        replay.seed,
+        replay.players,
+        world.checksum().total()
+    );
+
+    let entities = world.entities();
+
+    for player in entities.players.iter() {
+        let ship = entities.ship(player.pid).and_then(|x| {
+            Some((
+                entities.triangles.get(x)?,
+                entities.motions.get(x)?,
+                entities.healths.get(x)?,
+                entities.is_dead(x),
+            ))
+        });
+
+        let (body, motion, health, dead) = match ship {
+            Some(ship) => ship,
+            None => {
+                println!("{}: dead", player.name);
+                continue;
+            }
+        };
+
+        let centroid = body.live.shape.centroid();
+        let direction = body.live.direction;
+
+        println!(
+            "{}: position {}, {} direction {}, {} speed {} life {} shield {}{}",
+            player.name,
+            centroid.x,
+            centroid.y,
+            direction.x,
+            direction.y,
+            motion.speed,
+            health.life,
+            health.shield,
+            if dead { " dead" } else { "" }
+        );
+    }
+
+    for player in entities.players.iter() {
+        let team = match player.team {
+            Some(team) => format!(" ({} team)", Team::name(team)),
+            None => String::new(),
+        };
+
+        println!(
+            "{}{}: score {}, {} kills, {} deaths, {} assists",
+            player.name, team, player.score, player.kills, player.deaths, player.assists
+        );
+    }
+
+    // the teams in order, once each
+    let mut teams: Vec<usize> = entities.players.iter().filter_map(|x| x.team).collect();
+    teams.sort();
+    teams.dedup();
+
+    for team in teams {
+        println!(
+            "{} team: score {}",
+            Team::name(team),
+            Team::score(team, &entities.players)
+        );
+    }
+
+    if let Some(ending) = world.ending() {
+        println!("match over at tick {}, {}", world.tick(), ending);
+    }
+
+    if options.save {
+        match replay.save() {
+            Ok(path) => println!("Saved replay to {}", path.display()),
+            Err(e) => return Err(format!("failed to save replay: {}", e)),
+        }
+    }
+
+    match desync {
+        Some(desync) => Err(desync),
+        None => Ok(()),
+    }
+}
+
+// a script has one line per player and tick (or range of ticks),
+// followed by the commands the player gives during those ticks
+//
+//     # ticks pid commands
+//     0-15 0 accelerate
+//     16 1 rotate_left shoot
+//
+// ranges are inclusive, empty lines and lines starting with # are ignored
+pub fn parse_script(script: &str, players: usize) -> Result<Vec<Vec<Vec<Command>>>, String> {
+    let mut ticks: Vec<Vec<Vec<Command>>> = Vec::new();
+
+    for (idx, line) in script.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = |reason: String| format!("script line {}: {}", idx + 1, reason);
+        let mut words = line.split_whitespace();
+
+        let (start, end) = match words.next() {
+            Some(word) => match word.split_once('-') {
+                Some((start, end)) => (
+                    parse_number::<usize>("tick", start).map_err(error)?,
+                    parse_number::<usize>("tick", end).map_err(error)?,
+                ),
+                None => {
+                    let tick = parse_number::<usize>("tick", word).map_err(error)?;
+                    (tick, tick)
+                }
+            },
+            None => continue,
+        };
+
+        if end < start {
+            return Err(error(format!("tick range {}-{} is backwards", start, end)));
+        }
+
+        let pid = match words.next() {
+            Some(word) => parse_number::<usize>("pid", word).map_err(error)?,
+            None => return Err(error(String::from("missing pid"))),
+        };
+
+        if pid >= players {
+            return Err(error(format!(
+                "pid {} is not one of {} players",
+                pid, players
+            )));
+        }
+
+        let cmds = words
+            .map(|x| Command::from_name(x).ok_or_else(|| error(format!("unknown command {}", x))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if ticks.len() <= end {
+            ticks.resize(end + 1, vec![Vec::new(); players]);
+        }
+
+        for tick in ticks[start..=end].iter_mut() {
+            tick[pid].extend_from_slice(&cmds);
+        }
+    }
+
+    Ok(ticks)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} is not a valid {}", value, name.trim_start_matches('-')))
+}
@@ -1,50 +1,108 @@
 extern crate toml;
-use std::fs::{self};
+use std::{env, process};
+#[cfg(feature = "graphics")]
+use std::{
+    fs::{self},
+    net::SocketAddr,
+};
+#[cfg(feature = "graphics")]
 use toml::Value;
 
+#[cfg(feature = "graphics")]
 use engine::Engine;
 
+mod arena;
+#[cfg(feature = "graphics")]
 mod audio;
 mod bus;
+mod bytes;
+mod checksum;
 mod collisions;
 mod commands;
 mod components;
+#[cfg(feature = "graphics")]
 mod engine;
 mod entities;
+mod headless;
+#[cfg(feature = "graphics")]
 mod logs;
+mod map;
 mod math;
 mod messages;
+#[cfg(feature = "graphics")]
 mod misc;
+mod modes;
+// only the engine plays over the network, without graphics it's still built for its tests
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+mod net;
+mod pickups;
+mod players;
+mod replay;
+mod ships;
 mod spawner;
+#[cfg(feature = "graphics")]
 mod states;
 mod systems;
+mod weapons;
 mod world;
 
 fn main() {
-    let (width, height) = get_resolution();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.first().is_some_and(|x| x == "--headless") {
+        if let Err(e) = headless::run(&args[1..]) {
+            println!("Failed to run headless: {}", e);
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    run();
+}
+
+#[cfg(feature = "graphics")]
+fn run() {
+    let config = get_config();
+    let (width, height) = get_resolution(config.as_ref());
+    let address = get_address(config.as_ref());
     let (mut rh, rt) = raylib::init().size(width, height).title("fejd").build();
-    let mut engine = Engine::new();
+    let mut engine = Engine::new(address);
     engine.run(&mut rh, &rt);
 }
 
-fn get_resolution() -> (i32, i32) {
-    // TODO: if we add more configs, consider moving parsing file to its own function
-    let default = (1280, 720);
+#[cfg(not(feature = "graphics"))]
+fn run() {
+    println!("Built without graphics, only --headless is available");
+    process::exit(1);
+}
 
+#[cfg(feature = "graphics")]
+fn get_config() -> Option<Value> {
     let config = match fs::read_to_string("config.toml") {
         Ok(config) => config,
         Err(_) => {
             println!("Failed to read config.toml file");
-            return default;
+            return None;
         }
     };
 
-    let config = match config.parse::<Value>() {
-        Ok(config) => config,
+    match config.parse::<Value>() {
+        Ok(config) => Some(config),
         Err(_) => {
             println!("Failed to parse config.toml file");
-            return default;
+            None
         }
+    }
+}
+
+#[cfg(feature = "graphics")]
+fn get_resolution(config: Option<&Value>) -> (i32, i32) {
+    let default = (1280, 720);
+
+    let config = match config {
+        Some(config) => config,
+        None => return default,
     };
 
     let (width, height) = (
@@ -68,3 +126,22 @@ fn get_resolution() -> (i32, i32) {
         }
     }
 }
+
+#[cfg(feature = "graphics")]
+fn get_address(config: Option<&Value>) -> SocketAddr {
+    // the address the host binds to and the joining players send to
+    let default = SocketAddr::from(([127, 0, 0, 1], 7000));
+
+    let address = match config.and_then(|x| x.get("network")?.get("address")?.as_str()) {
+        Some(address) => address,
+        None => return default,
+    };
+
+    match address.parse() {
+        Ok(address) => address,
+        Err(_) => {
+            println!("Failed to parse network address");
+            default
+        }
+    }
+}
@@ -0,0 +1,631 @@
+use std::{cmp::Ordering, fmt, fs, io, ops::RangeInclusive, path::Path};
+
+use toml::Value;
+
+use crate::{
+    math::{Directions, Flint, FlintVec2},
+    modes::Team,
+    pickups::Pickup,
+};
+
+#[cfg(test)]
+mod tests;
+
+// a map is read from maps/<name>.toml, everyone playing must have the same file,
+// otherwise the worlds will diverge as soon as it matters
+//
+//     width = 800
+//     height = 600
+//
+//     [[spawns]]
+//     x = 100
+//     y = 100
+//     direction = "west"
+//     team = "red"
+//
+//     [stars]
+//     count = 64
+//
+//     [[obstacles]]
+//     points = [[300, 200], [360, 220], [330, 270]]
+//
+//     [[pickups]]
+//     x = 400
+//     y = 300
+//     kind = "health"
+//
+//     [edge]
+//     kind = "walls"
+pub struct Map {
+    pub name: String,
+    pub spawns: Vec<Spawn>,
+    pub stars: Stars,
+    pub obstacles: Vec<Obstacle>,
+    pub pickups: Vec<PickupPoint>,
+    pub edge: Edge,
+    pub width: Flint,
+    pub height: Flint,
+    #[cfg(feature = "graphics")]
+    pub width_i32: i32,
+    #[cfg(feature = "graphics")]
+    pub height_i32: i32,
+    #[cfg(feature = "graphics")]
+    pub width_f32: f32,
+    #[cfg(feature = "graphics")]
+    pub height_f32: f32,
+}
+
+pub struct Spawn {
+    pub point: FlintVec2,
+    pub direction: FlintVec2,
+    // only the players in the team start here when playing in teams,
+    // anyone can when there's no team
+    pub team: Option<usize>,
+}
+
+// the stars are placed within a tile of width and height,
+// which is repeated across the whole map
+pub struct Stars {
+    pub count: usize,
+    pub width: i32,
+    pub height: i32,
+    pub min_size: u8,
+    pub max_size: u8,
+}
+
+// a solid convex polygon, the points are in either clockwise or counterclockwise order
+pub struct Obstacle {
+    pub points: Vec<FlintVec2>,
+}
+
+// where a pickup shows up every now and then, always the same kind
+// or a different one every time when there's no kind
+pub struct PickupPoint {
+    pub point: FlintVec2,
+    pub kind: Option<Pickup>,
+}
+
+// what happens to everything reaching the edges of the map
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Edge {
+    // nothing gets out, triships crash into them and everything else is destroyed
+    Walls,
+    // anything leaving on one side comes back on the other
+    Wrap,
+    // triships are damaged every tick they are within size of the edges or outside of the map,
+    // everything else is destroyed once outside
+    Zone { size: Flint, damage: Flint },
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(io::Error),
+    InvalidName,
+    Parse { line: usize, reason: String },
+    Invalid(String),
+}
+
+impl Map {
+    pub const DIRECTORY: &'static str = "maps";
+    const EXTENSION: &'static str = "toml";
+
+    const MAX_SPAWNS: usize = u8::MAX as usize;
+    const MAX_STARS: RangeInclusive<i64> = 0..=1024;
+    const STAR_SIZES: RangeInclusive<i64> = 1..=16;
+    const STAR_TILES: RangeInclusive<i64> = 1..=i16::MAX as i64;
+    const MAX_POINTS: usize = 16;
+    const MAX_PICKUPS: usize = 64;
+    const MAX_EDGE: i32 = 512;
+
+    pub fn load(name: &str) -> Result<Map, MapError> {
+        // the name is sent over the network and stored in replays,
+        // so it must never be able to point outside the directory
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+        {
+            return Err(MapError::InvalidName);
+        }
+
+        let path = Path::new(Map::DIRECTORY).join(format!("{}.{}", name, Map::EXTENSION));
+
+        Map::parse(name, &fs::read_to_string(path)?)
+    }
+
+    // the names of every map in the directory, in alphabetical order
+    #[cfg(feature = "graphics")]
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = match fs::read_dir(Map::DIRECTORY) {
+            Ok(dir) => dir
+                .filter_map(|x| x.ok().map(|x| x.path()))
+                .filter(|x| x.extension().is_some_and(|x| x == Map::EXTENSION))
+                .filter_map(|x| Some(x.file_stem()?.to_str()?.to_owned()))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        names.sort();
+        names
+    }
+
+    pub fn parse(name: &str, content: &str) -> Result<Map, MapError> {
+        let value = parse_toml(content)?;
+
+        let width = parse_number(get(&value, "width", "width")?, "width")?;
+        let height = parse_number(get(&value, "height", "height")?, "height")?;
+
+        if width <= Flint::ZERO || height <= Flint::ZERO {
+            return Err(invalid("width and height must be greater than 0"));
+        }
+
+        let spawns = match value.get("spawns") {
+            Some(spawns) => parse_array(spawns, "spawns")?
+                .iter()
+                .enumerate()
+                .map(|(idx, x)| parse_spawn(x, &format!("spawns[{}]", idx), width, height))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        if spawns.is_empty() || spawns.len() > Map::MAX_SPAWNS {
+            return Err(invalid(&format!(
+                "a map must have 1 to {} spawns, not {}",
+                Map::MAX_SPAWNS,
+                spawns.len()
+            )));
+        }
+
+        let stars = match value.get("stars") {
+            Some(stars) => parse_stars(stars)?,
+            None => Stars::default(),
+        };
+
+        let obstacles = match value.get("obstacles") {
+            Some(obstacles) => parse_array(obstacles, "obstacles")?
+                .iter()
+                .enumerate()
+                .map(|(idx, x)| parse_obstacle(x, &format!("obstacles[{}]", idx), width, height))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        let pickups = match value.get("pickups") {
+            Some(pickups) => parse_array(pickups, "pickups")?
+                .iter()
+                .enumerate()
+                .map(|(idx, x)| parse_pickup(x, &format!("pickups[{}]", idx), width, height))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        if pickups.len() > Map::MAX_PICKUPS {
+            return Err(invalid(&format!(
+                "a map must have at most {} pickups, not {}",
+                Map::MAX_PICKUPS,
+                pickups.len()
+            )));
+        }
+
+        let edge = match value.get("edge") {
+            Some(edge) => parse_edge(edge, width, height)?,
+            None => Edge::Walls,
+        };
+
+        // otherwise the players would be stuck from the start
+        for (spawn_idx, spawn) in spawns.iter().enumerate() {
+            for (obstacle_idx, obstacle) in obstacles.iter().enumerate() {
+                if is_within(&obstacle.points, spawn.point) {
+                    return Err(invalid(&format!(
+                        "spawns[{}] is inside of obstacles[{}]",
+                        spawn_idx, obstacle_idx
+                    )));
+                }
+            }
+        }
+
+        // otherwise they could never be collected
+        for (pickup_idx, pickup) in pickups.iter().enumerate() {
+            for (obstacle_idx, obstacle) in obstacles.iter().enumerate() {
+                if is_within(&obstacle.points, pickup.point) {
+                    return Err(invalid(&format!(
+                        "pickups[{}] is inside of obstacles[{}]",
+                        pickup_idx, obstacle_idx
+                    )));
+                }
+            }
+        }
+
+        Ok(Map {
+            name: name.to_owned(),
+            spawns,
+            stars,
+            obstacles,
+            pickups,
+            edge,
+            width,
+            height,
+            #[cfg(feature = "graphics")]
+            width_i32: width.to_num(),
+            #[cfg(feature = "graphics")]
+            height_i32: height.to_num(),
+            #[cfg(feature = "graphics")]
+            width_f32: width.to_num(),
+            #[cfg(feature = "graphics")]
+            height_f32: height.to_num(),
+        })
+    }
+
+    // we can't have more players than there are spawns in the map
+    pub fn max_players(&self) -> u8 {
+        self.spawns.len().min(u8::MAX as usize) as u8
+    }
+}
+
+impl Stars {
+    fn default() -> Self {
+        Stars {
+            count: 64,
+            width: 512,
+            height: 512,
+            min_size: 1,
+            max_size: 1,
+        }
+    }
+}
+
+impl From<io::Error> for MapError {
+    fn from(value: io::Error) -> Self {
+        MapError::Io(value)
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(e) => write!(f, "{}", e),
+            MapError::InvalidName => {
+                write!(f, "map names may only contain letters, digits, - and _")
+            }
+            MapError::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+            MapError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+fn parse_spawn(value: &Value, path: &str, width: Flint, height: Flint) -> Result<Spawn, MapError> {
+    let point = FlintVec2::new(
+        parse_number(get(value, "x", path)?, &format!("{}.x", path))?,
+        parse_number(get(value, "y", path)?, &format!("{}.y", path))?,
+    );
+
+    if !is_inside(point, width, height) {
+        return Err(invalid(&format!("{} is outside of the map", path)));
+    }
+
+    let direction = match get(value, "direction", path)?.as_str() {
+        Some("north") => Directions::NORTH,
+        Some("east") => Directions::EAST,
+        Some("south") => Directions::SOUTH,
+        Some("west") => Directions::WEST,
+        _ => {
+            return Err(invalid(&format!(
+                "{}.direction must be one of north, east, south or west",
+                path
+            )))
+        }
+    };
+
+    let team = match value.get("team") {
+        Some(team) => match team.as_str().and_then(Team::from_name) {
+            Some(team) => Some(team),
+            None => {
+                return Err(invalid(&format!(
+                    "{}.team must be one of {}",
+                    path,
+                    Team::COLORS.map(|x| x.0).join(", ")
+                )))
+            }
+        },
+        None => None,
+    };
+
+    Ok(Spawn {
+        point,
+        direction,
+        team,
+    })
+}
+
+fn parse_pickup(
+    value: &Value,
+    path: &str,
+    width: Flint,
+    height: Flint,
+) -> Result<PickupPoint, MapError> {
+    let point = FlintVec2::new(
+        parse_number(get(value, "x", path)?, &format!("{}.x", path))?,
+        parse_number(get(value, "y", path)?, &format!("{}.y", path))?,
+    );
+
+    // the whole pickup must be inside, the walls destroy anything that isn't
+    let margin = Flint::from_num(Pickup::SIZE);
+
+    if point.x < margin || point.y < margin || point.x > width - margin || point.y > height - margin
+    {
+        return Err(invalid(&format!(
+            "{} must be at least {} inside of the map",
+            path,
+            Pickup::SIZE
+        )));
+    }
+
+    let kind = match value.get("kind") {
+        Some(kind) => match kind.as_str().and_then(Pickup::from_name) {
+            Some(kind) => Some(kind),
+            None => {
+                return Err(invalid(&format!(
+                    "{}.kind must be one of health, shield, weapon or speed",
+                    path
+                )))
+            }
+        },
+        None => None,
+    };
+
+    Ok(PickupPoint { point, kind })
+}
+
+fn parse_stars(value: &Value) -> Result<Stars, MapError> {
+    let default = Stars::default();
+
+    let integer = |key: &str, range: RangeInclusive<i64>, default: i64| match value.get(key) {
+        Some(x) => parse_integer(x, &format!("stars.{}", key), range),
+        None => Ok(default),
+    };
+
+    let stars = Stars {
+        count: integer("count", Map::MAX_STARS, default.count as i64)? as usize,
+        width: integer("width", Map::STAR_TILES, default.width as i64)? as i32,
+        height: integer("height", Map::STAR_TILES, default.height as i64)? as i32,
+        min_size: integer("min_size", Map::STAR_SIZES, default.min_size as i64)? as u8,
+        max_size: integer("max_size", Map::STAR_SIZES, default.max_size as i64)? as u8,
+    };
+
+    if stars.min_size > stars.max_size {
+        return Err(invalid(
+            "stars.min_size must not be greater than stars.max_size",
+        ));
+    }
+
+    // the stars are placed at least one pixel away from the tile's edges
+    let min = 2 * stars.max_size as i32 + 2;
+
+    if stars.width < min || stars.height < min {
+        return Err(invalid(&format!(
+            "stars.width and stars.height must be at least {} with stars of size {}",
+            min, stars.max_size
+        )));
+    }
+
+    Ok(stars)
+}
+
+fn parse_edge(value: &Value, width: Flint, height: Flint) -> Result<Edge, MapError> {
+    match get(value, "kind", "edge")?.as_str() {
+        Some("walls") => Ok(Edge::Walls),
+        Some("wrap") => Ok(Edge::Wrap),
+        Some("zone") => {
+            let number = |key: &str, default: Flint| match value.get(key) {
+                Some(x) => parse_number(x, &format!("edge.{}", key)),
+                None => Ok(default),
+            };
+
+            let size = number("size", Flint::from_num(64))?;
+            let damage = number("damage", Flint::from_num(0.25))?;
+
+            // the zone must leave some room in the middle
+            if size <= Flint::ZERO || size * 2 >= width.min(height) {
+                return Err(invalid(
+                    "edge.size must be greater than 0 and less than half of the width and height",
+                ));
+            }
+
+            if damage <= Flint::ZERO {
+                return Err(invalid("edge.damage must be greater than 0"));
+            }
+
+            Ok(Edge::Zone { size, damage })
+        }
+        _ => Err(invalid("edge.kind must be one of walls, wrap or zone")),
+    }
+}
+
+fn parse_obstacle(
+    value: &Value,
+    path: &str,
+    width: Flint,
+    height: Flint,
+) -> Result<Obstacle, MapError> {
+    let points_path = format!("{}.points", path);
+
+    let points = parse_array(get(value, "points", path)?, &points_path)?
+        .iter()
+        .enumerate()
+        .map(|(idx, x)| {
+            let path = format!("{}[{}]", points_path, idx);
+
+            match x.as_array().map(|x| x.as_slice()) {
+                Some([x, y]) => Ok(FlintVec2::new(
+                    parse_number(x, &format!("{}.x", path))?,
+                    parse_number(y, &format!("{}.y", path))?,
+                )),
+                _ => Err(invalid(&format!("{} must be an [x, y] pair", path))),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if points.len() < 3 || points.len() > Map::MAX_POINTS {
+        return Err(invalid(&format!(
+            "{} must have 3 to {} points, not {}",
+            points_path,
+            Map::MAX_POINTS,
+            points.len()
+        )));
+    }
+
+    if points.iter().any(|x| !is_inside(*x, width, height)) {
+        return Err(invalid(&format!("{} is outside of the map", path)));
+    }
+
+    if !is_convex(&points) {
+        return Err(invalid(&format!(
+            "{} must be convex, with every point in order and no three points on a line",
+            path
+        )));
+    }
+
+    // the collisions normalize every edge, which overflows for long edges
+    let max = Flint::from_num(Map::MAX_EDGE).wide_mul(Flint::from_num(Map::MAX_EDGE));
+
+    for idx in 0..points.len() {
+        let edge = points[(idx + 1) % points.len()] - points[idx];
+
+        if edge.x.wide_mul(edge.x) + edge.y.wide_mul(edge.y) > max {
+            return Err(invalid(&format!(
+                "{} has an edge longer than {}, split it into smaller obstacles",
+                path,
+                Map::MAX_EDGE
+            )));
+        }
+    }
+
+    Ok(Obstacle { points })
+}
+
+// the helpers below are shared with everything else read from a toml file,
+// e.g. the ship classes, their errors point out the path of what's wrong
+
+pub fn parse_toml(content: &str) -> Result<Value, MapError> {
+    content.parse::<Value>().map_err(|e| MapError::Parse {
+        // the span is in bytes, the line is what anyone editing the file wants
+        line: match e.span() {
+            Some(span) => content[..span.start].lines().count().max(1),
+            None => 1,
+        },
+        reason: e.message().trim().replace('\n', ", "),
+    })
+}
+
+pub fn get<'a>(value: &'a Value, key: &str, path: &str) -> Result<&'a Value, MapError> {
+    value.get(key).ok_or_else(|| {
+        if path == key {
+            invalid(&format!("missing {}", key))
+        } else {
+            invalid(&format!("missing {}.{}", path, key))
+        }
+    })
+}
+
+pub fn parse_array<'a>(value: &'a Value, path: &str) -> Result<&'a Vec<Value>, MapError> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid(&format!("{} must be an array", path)))
+}
+
+pub fn parse_number(value: &Value, path: &str) -> Result<Flint, MapError> {
+    // floats are converted the same way everywhere, so both are fine
+    let number = match value {
+        Value::Integer(x) => Flint::checked_from_num(*x),
+        Value::Float(x) => Flint::checked_from_num(*x),
+        _ => return Err(invalid(&format!("{} must be a number", path))),
+    };
+
+    // anything larger than this will overflow as soon as we add or multiply
+    let max = Flint::from_num(i16::MAX);
+
+    match number {
+        Some(x) if x.abs() <= max => Ok(x),
+        _ => Err(invalid(&format!(
+            "{} must be between -{} and {}",
+            path, max, max
+        ))),
+    }
+}
+
+pub fn parse_integer(
+    value: &Value,
+    path: &str,
+    range: RangeInclusive<i64>,
+) -> Result<i64, MapError> {
+    match value.as_integer() {
+        Some(x) if range.contains(&x) => Ok(x),
+        _ => Err(invalid(&format!(
+            "{} must be an integer between {} and {}",
+            path,
+            range.start(),
+            range.end()
+        ))),
+    }
+}
+
+fn is_inside(point: FlintVec2, width: Flint, height: Flint) -> bool {
+    point.x >= Flint::ZERO && point.x <= width && point.y >= Flint::ZERO && point.y <= height
+}
+
+fn is_convex(points: &[FlintVec2]) -> bool {
+    // every other point must be on the same side of every edge, on the edge itself
+    // means three points on a line (or the same point twice)
+    let mut side = Ordering::Equal;
+
+    for idx in 0..points.len() {
+        let a = points[idx];
+        let b = points[(idx + 1) % points.len()];
+        let ab = b - a;
+
+        for p in points.iter().filter(|x| **x != a && **x != b) {
+            let ap = *p - a;
+
+            // the wide products can't overflow, unlike the cross product of two flints
+            let cur = ab.x.wide_mul(ap.y).cmp(&ab.y.wide_mul(ap.x));
+
+            if cur == Ordering::Equal || (side != Ordering::Equal && cur != side) {
+                return false;
+            }
+
+            side = cur;
+        }
+    }
+
+    true
+}
+
+// the polygon must be convex
+fn is_within(points: &[FlintVec2], point: FlintVec2) -> bool {
+    let mut side = Ordering::Equal;
+
+    for idx in 0..points.len() {
+        let a = points[idx];
+        let ab = points[(idx + 1) % points.len()] - a;
+        let ap = point - a;
+
+        let cur = ab.x.wide_mul(ap.y).cmp(&ab.y.wide_mul(ap.x));
+
+        // on the edge counts as within
+        if cur == Ordering::Equal {
+            continue;
+        }
+
+        if side != Ordering::Equal && cur != side {
+            return false;
+        }
+
+        side = cur;
+    }
+
+    true
+}
+
+pub fn invalid(reason: &str) -> MapError {
+    MapError::Invalid(reason.to_owned())
+}
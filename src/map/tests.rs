@@ -0,0 +1,307 @@
+use std::fs;
+
+use crate::{
+    math::{Directions, Flint, FlintVec2},
+    pickups::Pickup,
+};
+
+use super::{Edge, Map};
+
+const SPAWN: &str = "
+    [[spawns]]
+    x = 100
+    y = 100
+    direction = \"west\"
+";
+
+fn parse(content: &str) -> Map {
+    match Map::parse("test", content) {
+        Ok(map) => map,
+        Err(e) => panic!("failed to parse the map: {}", e),
+    }
+}
+
+// the error of a map of 800 by 600 with whatever else is given
+fn error(content: &str) -> String {
+    match Map::parse("test", &format!("width = 800\nheight = 600\n{}", content)) {
+        Ok(_) => panic!("parsed an invalid map:\n{}", content),
+        Err(e) => e.to_string(),
+    }
+}
+
+fn assert_error(content: &str, expected: &str) {
+    let error = error(content);
+
+    assert!(
+        error.contains(expected),
+        "expected an error containing \"{}\", got \"{}\"",
+        expected,
+        error
+    );
+}
+
+#[test]
+fn bundled_maps_are_valid() {
+    for entry in fs::read_dir(Map::DIRECTORY).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_str().unwrap();
+
+        if let Err(e) = Map::load(name) {
+            panic!("{}: {}", path.display(), e);
+        }
+    }
+}
+
+#[test]
+fn only_the_size_and_a_spawn_are_needed() {
+    let map = parse(&format!("width = 800\nheight = 600.5\n{}", SPAWN));
+
+    assert_eq!(map.name, "test");
+    assert_eq!(map.width, Flint::from_num(800));
+    assert_eq!(map.height, Flint::from_num(600.5));
+    assert_eq!(map.spawns.len(), 1);
+    assert_eq!(map.spawns[0].team, None);
+    assert_eq!(map.max_players(), 1);
+    assert_eq!(map.stars.count, 64);
+    assert!(map.obstacles.is_empty());
+    assert!(map.pickups.is_empty());
+    assert_eq!(map.edge, Edge::Walls);
+}
+
+#[test]
+fn everything_in_the_example_is_read() {
+    let map = parse(
+        "
+        width = 800
+        height = 600
+
+        [[spawns]]
+        x = 100
+        y = 150
+        direction = \"north\"
+        team = \"red\"
+
+        [[spawns]]
+        x = 700
+        y = 450
+        direction = \"south\"
+        team = \"blue\"
+
+        [stars]
+        count = 32
+        width = 256
+        height = 128
+        min_size = 1
+        max_size = 3
+
+        [[obstacles]]
+        points = [[300, 200], [360, 220], [330, 270]]
+
+        [[pickups]]
+        x = 400
+        y = 300
+        kind = \"health\"
+
+        [[pickups]]
+        x = 500
+        y = 300
+
+        [edge]
+        kind = \"zone\"
+        size = 32
+        damage = 0.5
+        ",
+    );
+
+    assert_eq!(map.spawns.len(), 2);
+    assert_eq!(map.spawns[0].point.y, Flint::from_num(150));
+    assert_eq!(map.spawns[0].direction.y, Directions::NORTH.y);
+    assert_eq!(map.spawns[0].team, Some(0));
+    assert_eq!(map.spawns[1].direction.y, Directions::SOUTH.y);
+    assert_eq!(map.spawns[1].team, Some(1));
+
+    assert_eq!(map.stars.count, 32);
+    assert_eq!((map.stars.width, map.stars.height), (256, 128));
+    assert_eq!((map.stars.min_size, map.stars.max_size), (1, 3));
+
+    assert_eq!(map.obstacles.len(), 1);
+    assert_eq!(map.obstacles[0].points.len(), 3);
+    assert_eq!(map.obstacles[0].points[1].x, Flint::from_num(360));
+
+    assert_eq!(map.pickups.len(), 2);
+    assert_eq!(map.pickups[0].kind, Some(Pickup::Health));
+    assert_eq!(map.pickups[1].kind, None);
+
+    assert_eq!(
+        map.edge,
+        Edge::Zone {
+            size: Flint::from_num(32),
+            damage: Flint::from_num(0.5)
+        }
+    );
+}
+
+#[test]
+fn syntax_errors_point_out_the_line() {
+    match Map::parse(
+        "test",
+        "width = 800\nheight = 600\n[edge\nkind = \"wrap\"\n",
+    ) {
+        Err(e) => assert!(e.to_string().starts_with("line 3:"), "{}", e),
+        Ok(_) => panic!("parsed a map with a syntax error"),
+    }
+}
+
+#[test]
+fn invalid_sizes_are_rejected() {
+    let map = |width: &str, height: &str| {
+        Map::parse(
+            "test",
+            &format!("width = {}\nheight = {}\n{}", width, height, SPAWN),
+        )
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    };
+
+    assert_eq!(
+        map("800", "0"),
+        Err(String::from("width and height must be greater than 0"))
+    );
+    assert_eq!(
+        map("-1", "600"),
+        Err(String::from("width and height must be greater than 0"))
+    );
+    assert!(map("\"wide\"", "600")
+        .unwrap_err()
+        .contains("width must be a number"));
+    assert!(map("40000", "600")
+        .unwrap_err()
+        .contains("width must be between"));
+    assert_eq!(
+        Map::parse("test", &format!("width = 800\n{}", SPAWN))
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(String::from("missing height"))
+    );
+}
+
+#[test]
+fn invalid_spawns_are_rejected() {
+    assert_error("", "a map must have 1 to 255 spawns, not 0");
+    assert_error("spawns = 3", "spawns must be an array");
+    assert_error(
+        "[[spawns]]\ny = 100\ndirection = \"west\"",
+        "missing spawns[0].x",
+    );
+    assert_error(
+        "[[spawns]]\nx = 900\ny = 100\ndirection = \"west\"",
+        "spawns[0] is outside of the map",
+    );
+    assert_error(
+        "[[spawns]]\nx = 100\ny = 100\ndirection = \"up\"",
+        "spawns[0].direction must be one of north, east, south or west",
+    );
+    assert_error(
+        &format!("{}team = \"purple\"", SPAWN),
+        "spawns[0].team must be one of red, blue, green, yellow",
+    );
+
+    let many: String = (0..256).map(|_| SPAWN).collect();
+    assert_error(&many, "a map must have 1 to 255 spawns, not 256");
+}
+
+#[test]
+fn invalid_obstacles_are_rejected() {
+    let obstacle = |points: &str| error(&format!("{}[[obstacles]]\npoints = {}", SPAWN, points));
+
+    assert!(obstacle("[[300, 200], [360, 220]]")
+        .contains("obstacles[0].points must have 3 to 16 points, not 2"));
+    assert!(obstacle("[[300, 200], [360, 220], [330]]")
+        .contains("obstacles[0].points[2] must be an [x, y] pair"));
+    assert!(obstacle("[[300, 200], [360, 220], [330, 700]]")
+        .contains("obstacles[0] is outside of the map"));
+    // the last point is inside of the triangle of the others
+    assert!(obstacle("[[300, 200], [400, 200], [350, 300], [350, 250]]")
+        .contains("obstacles[0] must be convex"));
+    assert!(
+        obstacle("[[300, 200], [350, 200], [400, 200]]").contains("obstacles[0] must be convex")
+    );
+    assert!(obstacle("[[0, 0], [600, 0], [300, 100]]")
+        .contains("obstacles[0] has an edge longer than 512"));
+
+    assert_error(
+        &format!(
+            "{}[[obstacles]]\npoints = [[50, 50], [150, 50], [100, 150]]",
+            SPAWN
+        ),
+        "spawns[0] is inside of obstacles[0]",
+    );
+}
+
+#[test]
+fn invalid_edges_are_rejected() {
+    let edge = |content: &str| error(&format!("{}[edge]\n{}", SPAWN, content));
+
+    assert!(edge("size = 3").contains("missing edge.kind"));
+    assert!(edge("kind = \"lava\"").contains("edge.kind must be one of walls, wrap or zone"));
+    assert!(edge("kind = \"zone\"\nsize = 300")
+        .contains("edge.size must be greater than 0 and less than half of the width and height"));
+    assert!(edge("kind = \"zone\"\nsize = 0").contains("edge.size must be greater than 0"));
+    assert!(edge("kind = \"zone\"\ndamage = -1").contains("edge.damage must be greater than 0"));
+
+    let wrap = parse(&format!(
+        "width = 800\nheight = 600\n{}[edge]\nkind = \"wrap\"",
+        SPAWN
+    ));
+    assert_eq!(wrap.edge, Edge::Wrap);
+}
+
+#[test]
+fn invalid_pickups_and_stars_are_rejected() {
+    assert_error(
+        &format!("{}[[pickups]]\nx = 2\ny = 300", SPAWN),
+        &format!(
+            "pickups[0] must be at least {} inside of the map",
+            Pickup::SIZE
+        ),
+    );
+    assert_error(
+        &format!("{}[[pickups]]\nx = 400\ny = 300\nkind = \"ammo\"", SPAWN),
+        "pickups[0].kind must be one of health, shield, weapon or speed",
+    );
+    assert_error(
+        &format!(
+            "{}[[obstacles]]\npoints = [[350, 250], [450, 250], [400, 350]]\n[[pickups]]\nx = 400\ny = 300",
+            SPAWN
+        ),
+        "pickups[0] is inside of obstacles[0]",
+    );
+    assert_error(
+        &format!("{}[stars]\nmin_size = 4\nmax_size = 2", SPAWN),
+        "stars.min_size must not be greater than stars.max_size",
+    );
+    assert_error(
+        &format!("{}[stars]\nwidth = 4\nmax_size = 2", SPAWN),
+        "stars.width and stars.height must be at least 6 with stars of size 2",
+    );
+}
+
+#[test]
+fn points_are_read_as_given() {
+    let map = parse(&format!(
+        "width = 800\nheight = 600\n{}[[obstacles]]\npoints = [[300.5, 200], [360, 220.25], [330, 270]]",
+        SPAWN
+    ));
+
+    let point = map.obstacles[0].points[0];
+    assert_eq!(
+        (point.x, point.y),
+        (Flint::from_num(300.5), Flint::from_num(200))
+    );
+
+    let spawn: FlintVec2 = map.spawns[0].point;
+    assert_eq!(
+        (spawn.x, spawn.y),
+        (Flint::from_num(100), Flint::from_num(100))
+    );
+}
@@ -12,13 +12,7 @@ pub struct FlintVec2 {
     pub y: Flint,
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct FlintLine {
-    pub v1: FlintVec2,
-    pub v2: FlintVec2,
-}
-
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct FlintTriangle {
     pub v1: FlintVec2,
     pub v2: FlintVec2,
@@ -27,13 +21,19 @@ pub struct FlintTriangle {
     pub height: Flint,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct FlintRectangle {
     pub point: FlintVec2,
     pub width: Flint,
     pub height: Flint,
 }
 
+// a convex polygon, the points are in order around it
+#[derive(Clone, Debug)]
+pub struct FlintPolygon {
+    pub points: Vec<FlintVec2>,
+}
+
 impl FlintRectangle {
     pub fn from_centroid(cen: FlintVec2, width: Flint, height: Flint) -> Self {
         Self {
@@ -133,13 +133,6 @@ impl FlintVec2 {
         }
     }
 
-    pub fn rotated_270(&self) -> FlintVec2 {
-        FlintVec2 {
-            x: self.y * -1,
-            y: self.x,
-        }
-    }
-
     pub fn normalized(&self) -> FlintVec2 {
         let mag = self.magnitude();
 
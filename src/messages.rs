@@ -1,64 +1,179 @@
-use crate::{entities::EntityTypeIndex, states::State};
+#[cfg(feature = "graphics")]
+use crate::states::State;
+use crate::{
+    checksum::{Checksum, Divergence},
+    commands::Command,
+    entities::EntityHandle,
+    math::Flint,
+    modes::Ending,
+    net::Rejection,
+    pickups::Pickup,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub enum Sender {
     None,
+    #[cfg(feature = "graphics")]
     Engine,
+    #[cfg(feature = "graphics")]
     Log,
+    #[cfg(feature = "graphics")]
     State,
+    #[cfg(feature = "graphics")]
     Menu,
+    #[cfg(feature = "graphics")]
     Game,
+    #[cfg(feature = "graphics")]
+    Replay,
     World,
     Logic,
+    #[cfg(feature = "graphics")]
     Audio,
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    Net,
 }
 
 #[derive(Debug)]
 pub enum Message {
+    #[cfg(feature = "graphics")]
     Engine(EngineMessage),
-    State(StateMessage),
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
     Request(RequestMessage),
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    World(WorldMessage),
     Logic(LogicMessage),
-    Audio(AudioMessage),
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    Net(NetMessage),
 }
 
+// without graphics only the network is ever asked for anything, and only by its tests
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
 #[derive(Debug)]
 pub enum RequestMessage {
+    #[cfg(feature = "graphics")]
     Engine(EngineRequestMessage),
+    #[cfg(feature = "graphics")]
     State(StateRequestMessage),
+    Net(NetRequestMessage),
 }
 
+#[cfg(feature = "graphics")]
 #[derive(Debug)]
 pub enum EngineMessage {
-    TicksPerSecondSet(u8),
     DebugSet(bool),
     DebugGet(bool),
+    MapSet(String),
+    MapGet(String),
+    ModeSet(String),
+    ModeGet(String),
+    ShipSet(String),
+    ShipGet(String),
 }
 
+#[cfg(feature = "graphics")]
 #[derive(Debug)]
 pub enum EngineRequestMessage {
     SetTicksPerSecond(u8),
     SetDebug(bool),
     GetDebug,
+    SetMap(String),
+    GetMap,
+    SetMode(String),
+    GetMode,
+    SetShip(String),
+    GetShip,
 }
 
+#[cfg(feature = "graphics")]
 #[derive(Debug)]
-pub enum StateMessage {
-    StateSet(State),
+pub enum StateRequestMessage {
+    SetState(State),
 }
 
+// without graphics nobody reads these, they are only sent
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
 #[derive(Debug)]
-pub enum StateRequestMessage {
-    SetState(State),
+pub enum WorldMessage {
+    ChecksumCalculated(Checksum),
+    MatchOver(Ending),
 }
 
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
 #[derive(Debug)]
 pub enum LogicMessage {
-    Death(EntityTypeIndex),
-    Collision(EntityTypeIndex, EntityTypeIndex),
+    Death(EntityHandle),
+    Collision(EntityHandle, EntityHandle),
+    // by the player whose ship flew over it
+    PickupCollected {
+        handle: EntityHandle,
+        pid: usize,
+        pickup: Pickup,
+    },
+    // by the player whose projectile or ship hit it, none when it crashed by itself
+    Damaged {
+        pid: usize,
+        attacker: Option<usize>,
+        damage: Flint,
+    },
+    // by the player that dealt the killing blow, none when it died by itself
+    Killed {
+        pid: usize,
+        killer: Option<usize>,
+        assists: Vec<usize>,
+    },
+}
+
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+#[derive(Debug)]
+pub enum NetMessage {
+    LobbyUpdated {
+        pid: u8,
+        players: u8,
+    },
+    Rejected(Rejection),
+    Failed(String),
+    Started,
+    SessionGet {
+        pid: u8,
+        players: u8,
+        seed: u64,
+        // only a session decides the map, the mode and the ships,
+        // playing alone we pick them ourselves
+        map: Option<String>,
+        mode: Option<String>,
+        ships: Option<Vec<String>>,
+    },
+    Disconnected,
+    CommandsReceived {
+        pid: u8,
+        tick: u64,
+        cmds: Vec<Command>,
+    },
+    // the first tick a player's world no longer matches ours
+    Desynced {
+        pid: u8,
+        tick: u64,
+        divergence: Divergence,
+    },
 }
 
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
 #[derive(Debug)]
-pub enum AudioMessage {
-    Play(EntityTypeIndex),
+pub enum NetRequestMessage {
+    Host {
+        max_players: u8,
+        map: String,
+        mode: String,
+        ship: String,
+    },
+    Join {
+        ship: String,
+    },
+    Start,
+    GetSession,
+    Disconnect,
+    SendCommands {
+        tick: u64,
+        cmds: Vec<Command>,
+    },
 }
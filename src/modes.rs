@@ -0,0 +1,432 @@
+use std::{fmt, fs, io};
+
+use toml::Value;
+
+use crate::{
+    components::render::RenderColor,
+    map::{self, MapError},
+    math::Flint,
+    players::Player,
+};
+
+#[cfg(test)]
+mod tests;
+
+// the modes are read from modes.toml, like the maps everyone playing must have
+// the same file, the host picks one by name and it's used by everyone joining
+//
+//     [[modes]]
+//     name = "deathmatch"
+//     frag_limit = 10
+//     time_limit = 4800
+//
+//     [[modes]]
+//     name = "survival"
+//     lives = 3
+//     last_standing = true
+//     respawn_delay = 160
+//     spawn_protection = 48
+//
+//     [[modes]]
+//     name = "teams"
+//     frag_limit = 20
+//     teams = 2
+//     friendly_fire = "reduced"
+pub struct Modes {
+    pub modes: Vec<Mode>,
+}
+
+// how a match is played and when it's over, whichever limit is reached first
+#[derive(Clone)]
+pub struct Mode {
+    pub name: String,
+    // kills to win, a suicide costs one
+    pub frag_limit: Option<u32>,
+    // ticks until whoever has the highest score wins
+    pub time_limit: Option<u64>,
+    // none for as many lives as needed
+    pub lives: Option<u32>,
+    // ticks dead before respawning
+    pub respawn_delay: i32,
+    // ticks nothing can hurt a ship that has just respawned, unless it shoots
+    pub spawn_protection: i32,
+    // won by the only one left with any lives
+    pub last_standing: bool,
+    // none when everyone plays for themselves, otherwise the players are
+    // split between this many teams, one after another by pid,
+    // the limits are then reached by the whole team together
+    pub teams: Option<usize>,
+    pub friendly_fire: FriendlyFire,
+}
+
+// what hitting a teammate does, the ships never shoot themselves either way
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FriendlyFire {
+    // the shots pass right through and crashing doesn't hurt
+    Off,
+    // half of the damage
+    Reduced,
+    Full,
+}
+
+// why the match is over, and who won it, none on a draw
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ending {
+    FragLimit(Winner),
+    TimeLimit(Option<Winner>),
+    LastStanding(Option<Winner>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Winner {
+    Player(usize),
+    Team(usize),
+}
+
+#[derive(Debug)]
+pub enum ModeError {
+    Io(io::Error),
+    Parse { line: usize, reason: String },
+    Invalid(String),
+}
+
+impl Modes {
+    pub const PATH: &'static str = "modes.toml";
+
+    const MAX_MODES: usize = u8::MAX as usize;
+    const MAX_NAME: usize = 32;
+    const MAX_FRAGS: i64 = 1000;
+    // a second at the least, and an hour at 16 ticks a second at the most
+    const TIME_LIMITS: (i64, i64) = (16, 57600);
+    const MAX_LIVES: i64 = 99;
+    // a minute at the most, and the default of a bit more than six seconds
+    const RESPAWN_DELAYS: (i64, i64) = (1, 960);
+    const RESPAWN_DELAY: i32 = 100;
+    // half a minute at the most, and two seconds by default
+    const MAX_SPAWN_PROTECTION: i64 = 480;
+    const SPAWN_PROTECTION: i32 = 32;
+    const TEAMS: (i64, i64) = (2, Team::COLORS.len() as i64);
+
+    pub fn load() -> Result<Modes, ModeError> {
+        Modes::parse(&fs::read_to_string(Modes::PATH)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Modes, ModeError> {
+        let value = map::parse_toml(content)?;
+
+        let modes = match value.get("modes") {
+            Some(modes) => map::parse_array(modes, "modes")?
+                .iter()
+                .enumerate()
+                .map(|(idx, x)| parse_mode(x, &format!("modes[{}]", idx)))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        if modes.is_empty() || modes.len() > Modes::MAX_MODES {
+            return Err(invalid(&format!(
+                "there must be 1 to {} modes, not {}",
+                Modes::MAX_MODES,
+                modes.len()
+            )));
+        }
+
+        for (idx, mode) in modes.iter().enumerate() {
+            if modes[..idx].iter().any(|x| x.name == mode.name) {
+                return Err(invalid(&format!(
+                    "modes[{}].name {} is already taken",
+                    idx, mode.name
+                )));
+            }
+        }
+
+        Ok(Modes { modes })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Mode> {
+        self.modes.iter().find(|x| x.name == name)
+    }
+
+    // unknown names are an error since everyone must play by the same rules
+    pub fn pick(&self, name: &str) -> Result<Mode, String> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| format!("there is no mode named {}", name))
+    }
+}
+
+impl Mode {
+    // the team the player is put in, none when everyone plays for themselves
+    pub fn team(&self, pid: usize) -> Option<usize> {
+        self.teams.map(|x| pid % x)
+    }
+
+    // checked once every tick until the match is over, the players and teams are
+    // always gone through in order so everyone agrees on who won
+    pub fn ending(&self, tick: u64, players: &[Player]) -> Option<Ending> {
+        let sides = self.sides(players);
+
+        if let Some(limit) = self.frag_limit {
+            if let Some(side) = sides.iter().find(|x| x.score >= limit as i32) {
+                return Some(Ending::FragLimit(side.winner));
+            }
+        }
+
+        // playing alone it's over once out of lives, there's nobody to win
+        if self.last_standing {
+            let mut left = sides.iter().filter(|x| x.standing);
+
+            match (left.next(), left.next()) {
+                (Some(side), None) if sides.len() > 1 => {
+                    return Some(Ending::LastStanding(Some(side.winner)))
+                }
+                (None, _) => return Some(Ending::LastStanding(None)),
+                _ => (),
+            }
+        }
+
+        match self.time_limit {
+            Some(limit) if tick >= limit => Some(Ending::TimeLimit(leader(&sides))),
+            _ => None,
+        }
+    }
+
+    // who can win, every player on their own or every team with anyone in it
+    fn sides(&self, players: &[Player]) -> Vec<Side> {
+        match self.teams {
+            Some(teams) => (0..teams)
+                .filter(|x| players.iter().any(|p| p.team == Some(*x)))
+                .map(|x| Side {
+                    winner: Winner::Team(x),
+                    score: Team::score(x, players),
+                    standing: players
+                        .iter()
+                        .any(|p| p.team == Some(x) && !p.is_spectating()),
+                })
+                .collect(),
+            None => players
+                .iter()
+                .map(|x| Side {
+                    winner: Winner::Player(x.pid),
+                    score: x.score,
+                    standing: !x.is_spectating(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FriendlyFire {
+    pub fn from_name(name: &str) -> Option<FriendlyFire> {
+        match name {
+            "off" => Some(FriendlyFire::Off),
+            "reduced" => Some(FriendlyFire::Reduced),
+            "full" => Some(FriendlyFire::Full),
+            _ => None,
+        }
+    }
+
+    // the damage a teammate takes
+    pub fn scale(&self, damage: Flint) -> Flint {
+        match self {
+            FriendlyFire::Off => Flint::ZERO,
+            FriendlyFire::Reduced => damage / 2,
+            FriendlyFire::Full => damage,
+        }
+    }
+}
+
+// the teams are told apart by their color, and named after it
+pub struct Team;
+
+impl Team {
+    pub const COLORS: [(&'static str, RenderColor); 4] = [
+        ("red", RenderColor::CRIMSON),
+        ("blue", RenderColor::ROYALBLUE),
+        ("green", RenderColor::SEAGREEN),
+        ("yellow", RenderColor::GOLD),
+    ];
+
+    pub fn from_name(name: &str) -> Option<usize> {
+        Team::COLORS.iter().position(|x| x.0 == name)
+    }
+
+    pub fn name(team: usize) -> &'static str {
+        Team::COLORS[team % Team::COLORS.len()].0
+    }
+
+    pub fn color(team: usize) -> RenderColor {
+        Team::COLORS[team % Team::COLORS.len()].1
+    }
+
+    // everyone in the team scores for it
+    pub fn score(team: usize, players: &[Player]) -> i32 {
+        players
+            .iter()
+            .filter(|x| x.team == Some(team))
+            .map(|x| x.score)
+            .sum()
+    }
+}
+
+impl Ending {
+    pub fn winner(&self) -> Option<Winner> {
+        match self {
+            Ending::FragLimit(winner) => Some(*winner),
+            Ending::TimeLimit(winner) | Ending::LastStanding(winner) => *winner,
+        }
+    }
+}
+
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Ending::FragLimit(_) => "the frag limit was reached",
+            Ending::TimeLimit(_) => "the time is up",
+            Ending::LastStanding(_) => "only one was left standing",
+        };
+
+        match self.winner() {
+            Some(Winner::Player(pid)) => write!(f, "player {} won, {}", pid, reason),
+            Some(Winner::Team(team)) => write!(f, "the {} team won, {}", Team::name(team), reason),
+            None => write!(f, "nobody won, {}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for ModeError {
+    fn from(value: io::Error) -> Self {
+        ModeError::Io(value)
+    }
+}
+
+impl From<MapError> for ModeError {
+    fn from(value: MapError) -> Self {
+        match value {
+            MapError::Io(e) => ModeError::Io(e),
+            MapError::Parse { line, reason } => ModeError::Parse { line, reason },
+            e => ModeError::Invalid(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModeError::Io(e) => write!(f, "{}", e),
+            ModeError::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+            ModeError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+struct Side {
+    winner: Winner,
+    score: i32,
+    // anyone in it has any lives left
+    standing: bool,
+}
+
+// the only one with the highest score, none when it's shared
+fn leader(sides: &[Side]) -> Option<Winner> {
+    let best = sides.iter().map(|x| x.score).max()?;
+    let mut leaders = sides.iter().filter(|x| x.score == best);
+
+    match (leaders.next(), leaders.next()) {
+        (Some(side), None) => Some(side.winner),
+        _ => None,
+    }
+}
+
+fn parse_mode(value: &Value, path: &str) -> Result<Mode, ModeError> {
+    // the name is sent over the network and stored in replays
+    let name = match map::get(value, "name", path)?.as_str() {
+        Some(name)
+            if !name.is_empty()
+                && name.len() <= Modes::MAX_NAME
+                && name
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-') =>
+        {
+            name.to_owned()
+        }
+        _ => {
+            return Err(invalid(&format!(
+                "{}.name must be 1 to {} letters, digits, - or _",
+                path,
+                Modes::MAX_NAME
+            )))
+        }
+    };
+
+    // every limit is optional, without any the match goes on until everyone leaves
+    let integer = |key: &str, min: i64, max: i64| match value.get(key) {
+        Some(x) => map::parse_integer(x, &format!("{}.{}", path, key), min..=max).map(Some),
+        None => Ok(None),
+    };
+
+    let (min, max) = Modes::TIME_LIMITS;
+    let frag_limit = integer("frag_limit", 1, Modes::MAX_FRAGS)?.map(|x| x as u32);
+    let time_limit = integer("time_limit", min, max)?.map(|x| x as u64);
+    let lives = integer("lives", 1, Modes::MAX_LIVES)?.map(|x| x as u32);
+
+    let (min, max) = Modes::RESPAWN_DELAYS;
+    let respawn_delay =
+        integer("respawn_delay", min, max)?.map_or(Modes::RESPAWN_DELAY, |x| x as i32);
+    let spawn_protection = integer("spawn_protection", 0, Modes::MAX_SPAWN_PROTECTION)?
+        .map_or(Modes::SPAWN_PROTECTION, |x| x as i32);
+
+    let last_standing = match value.get("last_standing") {
+        Some(x) => x
+            .as_bool()
+            .ok_or_else(|| invalid(&format!("{}.last_standing must be a boolean", path)))?,
+        None => false,
+    };
+
+    // nobody is ever out without running out of lives
+    if last_standing && lives.is_none() {
+        return Err(invalid(&format!(
+            "{}.last_standing needs {}.lives",
+            path, path
+        )));
+    }
+
+    let (min, max) = Modes::TEAMS;
+    let teams = integer("teams", min, max)?.map(|x| x as usize);
+
+    // teammates can't hurt each other unless told otherwise
+    let friendly_fire = match value.get("friendly_fire") {
+        Some(x) => match x.as_str().and_then(FriendlyFire::from_name) {
+            Some(friendly_fire) if teams.is_some() => friendly_fire,
+            Some(_) => {
+                return Err(invalid(&format!(
+                    "{}.friendly_fire needs {}.teams",
+                    path, path
+                )))
+            }
+            None => {
+                return Err(invalid(&format!(
+                    "{}.friendly_fire must be one of off, reduced or full",
+                    path
+                )))
+            }
+        },
+        None => FriendlyFire::Off,
+    };
+
+    Ok(Mode {
+        name,
+        frag_limit,
+        time_limit,
+        lives,
+        respawn_delay,
+        spawn_protection,
+        last_standing,
+        teams,
+        friendly_fire,
+    })
+}
+
+fn invalid(reason: &str) -> ModeError {
+    ModeError::Invalid(reason.to_owned())
+}
@@ -0,0 +1,330 @@
+use crate::{math::Flint, players::Player, ships::ShipClasses};
+
+use super::{Ending, FriendlyFire, Mode, Modes, Winner};
+
+fn mode(fields: &str) -> Mode {
+    let content = format!("[[modes]]\nname = \"test\"\n{}", fields);
+
+    match Modes::parse(&content) {
+        Ok(mut modes) => modes.modes.remove(0),
+        Err(e) => panic!("could not parse the mode: {}\n{}", e, content),
+    }
+}
+
+fn error(fields: &str) -> String {
+    let content = format!("[[modes]]\nname = \"test\"\n{}", fields);
+
+    match Modes::parse(&content) {
+        Ok(_) => panic!("parsed an invalid mode:\n{}", content),
+        Err(e) => e.to_string(),
+    }
+}
+
+fn players(count: usize, mode: &Mode) -> Vec<Player> {
+    let class = ShipClasses::load().expect("the ship classes").classes[0].clone();
+
+    (0..count)
+        .map(|pid| {
+            let mut player = Player::new(pid, class.clone());
+            player.team = mode.team(pid);
+            player.lives = mode.lives;
+            player
+        })
+        .collect()
+}
+
+#[test]
+fn bundled_modes_are_valid() {
+    let modes = Modes::load().expect("the modes");
+
+    assert!(modes.get("deathmatch").is_some());
+}
+
+#[test]
+fn everything_is_optional_but_the_name() {
+    let mode = mode("");
+
+    assert_eq!(mode.name, "test");
+    assert_eq!(mode.frag_limit, None);
+    assert_eq!(mode.time_limit, None);
+    assert_eq!(mode.lives, None);
+    assert_eq!(mode.respawn_delay, 100);
+    assert_eq!(mode.spawn_protection, 32);
+    assert!(!mode.last_standing);
+    assert_eq!(mode.teams, None);
+    assert_eq!(mode.friendly_fire, FriendlyFire::Off);
+}
+
+#[test]
+fn everything_in_the_example_is_read() {
+    let mode = mode(
+        "frag_limit = 10\n\
+         time_limit = 4800\n\
+         lives = 3\n\
+         last_standing = true\n\
+         respawn_delay = 160\n\
+         spawn_protection = 48\n\
+         teams = 2\n\
+         friendly_fire = \"reduced\"\n",
+    );
+
+    assert_eq!(mode.frag_limit, Some(10));
+    assert_eq!(mode.time_limit, Some(4800));
+    assert_eq!(mode.lives, Some(3));
+    assert!(mode.last_standing);
+    assert_eq!(mode.respawn_delay, 160);
+    assert_eq!(mode.spawn_protection, 48);
+    assert_eq!(mode.teams, Some(2));
+    assert_eq!(mode.friendly_fire, FriendlyFire::Reduced);
+}
+
+#[test]
+fn limits_are_read_at_their_bounds() {
+    assert_eq!(mode("frag_limit = 1").frag_limit, Some(1));
+    assert_eq!(mode("frag_limit = 1000").frag_limit, Some(1000));
+    assert_eq!(mode("time_limit = 16").time_limit, Some(16));
+    assert_eq!(mode("time_limit = 57600").time_limit, Some(57600));
+    assert_eq!(mode("lives = 1").lives, Some(1));
+    assert_eq!(mode("lives = 99").lives, Some(99));
+    assert_eq!(mode("respawn_delay = 1").respawn_delay, 1);
+    assert_eq!(mode("respawn_delay = 960").respawn_delay, 960);
+    assert_eq!(mode("spawn_protection = 0").spawn_protection, 0);
+    assert_eq!(mode("spawn_protection = 480").spawn_protection, 480);
+    assert_eq!(mode("teams = 4").teams, Some(4));
+}
+
+#[test]
+fn bad_fields_are_rejected() {
+    let cases = [
+        (
+            "frag_limit = 0",
+            "modes[0].frag_limit must be an integer between 1 and 1000",
+        ),
+        (
+            "frag_limit = 1001",
+            "modes[0].frag_limit must be an integer between 1 and 1000",
+        ),
+        (
+            "time_limit = 15",
+            "modes[0].time_limit must be an integer between 16 and 57600",
+        ),
+        (
+            "time_limit = 57601",
+            "modes[0].time_limit must be an integer between 16 and 57600",
+        ),
+        (
+            "time_limit = 4800.5",
+            "modes[0].time_limit must be an integer between 16 and 57600",
+        ),
+        (
+            "lives = 0",
+            "modes[0].lives must be an integer between 1 and 99",
+        ),
+        (
+            "lives = 100",
+            "modes[0].lives must be an integer between 1 and 99",
+        ),
+        (
+            "respawn_delay = 0",
+            "modes[0].respawn_delay must be an integer between 1 and 960",
+        ),
+        (
+            "respawn_delay = 961",
+            "modes[0].respawn_delay must be an integer between 1 and 960",
+        ),
+        (
+            "spawn_protection = -1",
+            "modes[0].spawn_protection must be an integer between 0 and 480",
+        ),
+        (
+            "spawn_protection = 481",
+            "modes[0].spawn_protection must be an integer between 0 and 480",
+        ),
+        (
+            "lives = 3\nlast_standing = \"yes\"",
+            "modes[0].last_standing must be a boolean",
+        ),
+        (
+            "last_standing = true",
+            "modes[0].last_standing needs modes[0].lives",
+        ),
+        (
+            "teams = 1",
+            "modes[0].teams must be an integer between 2 and 4",
+        ),
+        (
+            "teams = 5",
+            "modes[0].teams must be an integer between 2 and 4",
+        ),
+        (
+            "friendly_fire = \"full\"",
+            "modes[0].friendly_fire needs modes[0].teams",
+        ),
+        (
+            "teams = 2\nfriendly_fire = \"some\"",
+            "modes[0].friendly_fire must be one of off, reduced or full",
+        ),
+        (
+            "teams = 2\nfriendly_fire = true",
+            "modes[0].friendly_fire must be one of off, reduced or full",
+        ),
+    ];
+
+    for (fields, expected) in cases {
+        assert_eq!(error(fields), expected, "{}", fields);
+    }
+}
+
+#[test]
+fn names_must_be_valid_and_unique() {
+    let expected = "modes[0].name must be 1 to 32 letters, digits, - or _";
+
+    assert_eq!(
+        Modes::parse("[[modes]]\nname = \"\"")
+            .err()
+            .unwrap()
+            .to_string(),
+        expected
+    );
+    assert_eq!(
+        Modes::parse("[[modes]]\nname = \"free for all\"")
+            .err()
+            .unwrap()
+            .to_string(),
+        expected
+    );
+    assert_eq!(
+        Modes::parse("[[modes]]\nname = \"a\"\n[[modes]]\nname = \"a\"")
+            .err()
+            .unwrap()
+            .to_string(),
+        "modes[1].name a is already taken"
+    );
+    assert_eq!(
+        Modes::parse("").err().unwrap().to_string(),
+        "there must be 1 to 255 modes, not 0"
+    );
+}
+
+#[test]
+fn unknown_modes_cannot_be_picked() {
+    let modes = Modes::load().expect("the modes");
+
+    assert_eq!(modes.pick("deathmatch").unwrap().name, "deathmatch");
+    assert_eq!(
+        modes.pick("capture").err().unwrap(),
+        "there is no mode named capture"
+    );
+}
+
+#[test]
+fn players_are_put_in_teams_by_pid() {
+    let mode = mode("teams = 3");
+
+    assert_eq!(
+        (0..7).map(|x| mode.team(x).unwrap()).collect::<Vec<_>>(),
+        vec![0, 1, 2, 0, 1, 2, 0]
+    );
+    assert_eq!(self::mode("").team(5), None);
+}
+
+#[test]
+fn friendly_fire_scales_the_damage() {
+    let damage = Flint::from_num(10);
+
+    assert_eq!(FriendlyFire::Off.scale(damage), Flint::ZERO);
+    assert_eq!(FriendlyFire::Reduced.scale(damage), Flint::from_num(5));
+    assert_eq!(FriendlyFire::Full.scale(damage), damage);
+}
+
+#[test]
+fn the_frag_limit_is_won_by_the_first_to_reach_it() {
+    let mode = mode("frag_limit = 3");
+    let mut players = players(3, &mode);
+
+    players[1].score = 2;
+    assert_eq!(mode.ending(0, &players), None);
+
+    players[2].score = 3;
+    assert_eq!(
+        mode.ending(0, &players),
+        Some(Ending::FragLimit(Winner::Player(2)))
+    );
+}
+
+#[test]
+fn the_frag_limit_is_reached_by_the_whole_team() {
+    let mode = mode("frag_limit = 3\nteams = 2");
+    let mut players = players(4, &mode);
+
+    players[1].score = 2;
+    assert_eq!(mode.ending(0, &players), None);
+
+    players[3].score = 1;
+    assert_eq!(
+        mode.ending(0, &players),
+        Some(Ending::FragLimit(Winner::Team(1)))
+    );
+}
+
+#[test]
+fn the_time_limit_is_won_by_the_only_leader() {
+    let mode = mode("time_limit = 16");
+    let mut players = players(3, &mode);
+
+    players[0].score = 2;
+    players[1].score = 2;
+    assert_eq!(mode.ending(15, &players), None);
+    assert_eq!(mode.ending(16, &players), Some(Ending::TimeLimit(None)));
+
+    players[1].score = 3;
+    assert_eq!(
+        mode.ending(16, &players),
+        Some(Ending::TimeLimit(Some(Winner::Player(1))))
+    );
+}
+
+#[test]
+fn last_standing_is_won_by_the_only_one_with_lives_left() {
+    let mode = mode("lives = 1\nlast_standing = true");
+    let mut players = players(3, &mode);
+
+    players[0].lives = Some(0);
+    assert_eq!(mode.ending(0, &players), None);
+
+    players[2].lives = Some(0);
+    assert_eq!(
+        mode.ending(0, &players),
+        Some(Ending::LastStanding(Some(Winner::Player(1))))
+    );
+
+    players[1].lives = Some(0);
+    assert_eq!(mode.ending(0, &players), Some(Ending::LastStanding(None)));
+}
+
+#[test]
+fn last_standing_alone_is_over_once_out_of_lives() {
+    let mode = mode("lives = 2\nlast_standing = true");
+    let mut players = players(1, &mode);
+
+    assert_eq!(mode.ending(0, &players), None);
+
+    players[0].lives = Some(0);
+    assert_eq!(mode.ending(0, &players), Some(Ending::LastStanding(None)));
+}
+
+#[test]
+fn endings_name_the_winner() {
+    assert_eq!(
+        Ending::FragLimit(Winner::Player(1)).to_string(),
+        "player 1 won, the frag limit was reached"
+    );
+    assert_eq!(
+        Ending::TimeLimit(Some(Winner::Team(0))).to_string(),
+        "the red team won, the time is up"
+    );
+    assert_eq!(
+        Ending::LastStanding(None).to_string(),
+        "nobody won, only one was left standing"
+    );
+}
@@ -0,0 +1,6 @@
+mod manager;
+mod packet;
+
+#[cfg_attr(not(feature = "graphics"), allow(unused_imports))]
+pub use self::manager::NetManager;
+pub use self::packet::Rejection;
@@ -0,0 +1,717 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::ErrorKind,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+};
+
+use crate::{
+    bus::Bus,
+    checksum::Checksum,
+    commands::Command,
+    messages::{Message, NetMessage, NetRequestMessage, RequestMessage, Sender, WorldMessage},
+};
+
+use super::packet::{Packet, Rejection};
+
+#[cfg(test)]
+mod tests;
+
+pub struct NetManager {
+    // the address the host binds to and the joining players send to
+    address: SocketAddr,
+    status: Status,
+    socket: Option<UdpSocket>,
+    session: Option<Session>,
+    // only used by the host, the address and ship class of every player
+    // in the lobby ordered by pid, our own address is the first one
+    lobby: Vec<(SocketAddr, String)>,
+    max_players: u8,
+    map: String,
+    mode: String,
+    // the ship class we fly, the host is told about it when joining
+    ship: String,
+    peers: Vec<Peer>,
+    sent: BTreeMap<u64, Vec<Command>>,
+    // our own latest checksums, to compare with the ones the peers send us
+    checksums: BTreeMap<u64, Checksum>,
+    actions: VecDeque<Action>,
+    buf: Vec<u8>,
+}
+
+#[derive(PartialEq, Eq)]
+enum Status {
+    Offline,
+    Hosting,
+    Joining,
+    Joined,
+    Playing,
+}
+
+// everything needed to play with the other players,
+// ships and addrs contain the ship class and address of every player ordered by pid
+struct Session {
+    pid: u8,
+    seed: u64,
+    map: String,
+    mode: String,
+    ships: Vec<String>,
+    addrs: Vec<SocketAddr>,
+    // whether the game has asked for the session, the commands of the peers
+    // are held back until then or they'd arrive before it knows the players
+    taken: bool,
+}
+
+struct Peer {
+    pid: u8,
+    addr: SocketAddr,
+    // the first tick the peer has not yet received from us
+    ack: u64,
+    // the next tick we expect to receive from the peer
+    recv: Option<u64>,
+    // commands received but not yet handed to the game, only the ones
+    // handed over are acknowledged so the peer keeps resending the rest
+    held: BTreeMap<u64, Vec<Command>>,
+    // whether the peer has begun sending us commands,
+    // the host will keep sending the start packet until it has
+    synced: bool,
+    // checksums we have received but not yet calculated ourselves
+    checksums: BTreeMap<u64, Checksum>,
+    // we only report the first tick we diverged from the peer
+    desynced: bool,
+}
+
+// the order of the actions matters here (host, disconnect, join...),
+// so we use a queue instead of a set
+enum Action {
+    Host {
+        max_players: u8,
+        map: String,
+        mode: String,
+        ship: String,
+    },
+    Join {
+        ship: String,
+    },
+    Start,
+    GetSession,
+    Disconnect,
+    SendCommands(u64, Vec<Command>),
+    SendChecksum(Checksum),
+}
+
+impl NetManager {
+    // how many of our own checksums we remember, the peers are never
+    // more than a couple of ticks ahead or behind of us
+    const CHECKSUM_TICKS: usize = 64;
+
+    pub fn new(address: SocketAddr) -> Self {
+        NetManager {
+            address,
+            status: Status::Offline,
+            socket: None,
+            session: None,
+            lobby: Vec::new(),
+            max_players: 0,
+            map: String::new(),
+            mode: String::new(),
+            ship: String::new(),
+            peers: Vec::new(),
+            sent: BTreeMap::new(),
+            checksums: BTreeMap::new(),
+            actions: VecDeque::new(),
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, bus: &mut Bus) {
+        self.action(bus);
+
+        if self.socket.is_none() {
+            return;
+        }
+
+        self.receive(bus);
+        self.deliver(bus);
+        self.verify(bus);
+        self.transmit();
+    }
+
+    pub fn message(&mut self, _sender: &Sender, msg: &Message) {
+        // we only care about net requests and the checksums of our world
+        let req = match msg {
+            Message::Request(RequestMessage::Net(msg)) => msg,
+            Message::World(WorldMessage::ChecksumCalculated(checksum)) => {
+                self.actions
+                    .push_back(Action::SendChecksum(checksum.clone()));
+                return;
+            }
+            _ => return,
+        };
+
+        match req {
+            NetRequestMessage::Host {
+                max_players,
+                map,
+                mode,
+                ship,
+            } => self.actions.push_back(Action::Host {
+                max_players: *max_players,
+                map: map.clone(),
+                mode: mode.clone(),
+                ship: ship.clone(),
+            }),
+            NetRequestMessage::Join { ship } => {
+                self.actions.push_back(Action::Join { ship: ship.clone() })
+            }
+            NetRequestMessage::Start => self.actions.push_back(Action::Start),
+            NetRequestMessage::GetSession => self.actions.push_back(Action::GetSession),
+            NetRequestMessage::Disconnect => self.actions.push_back(Action::Disconnect),
+            NetRequestMessage::SendCommands { tick, cmds } => self
+                .actions
+                .push_back(Action::SendCommands(*tick, cmds.clone())),
+        }
+    }
+
+    fn action(&mut self, bus: &mut Bus) {
+        while let Some(action) = self.actions.pop_front() {
+            match action {
+                Action::Host {
+                    max_players,
+                    map,
+                    mode,
+                    ship,
+                } => {
+                    self.disconnect();
+
+                    if let Err(e) = self.bind(self.address) {
+                        bus.send(Message::Net(NetMessage::Failed(e.to_string())));
+                        continue;
+                    }
+
+                    self.status = Status::Hosting;
+                    self.max_players = max_players;
+                    self.map = map;
+                    self.mode = mode;
+                    self.lobby.push((self.address, ship));
+
+                    bus.send(Message::Net(NetMessage::LobbyUpdated {
+                        pid: 0,
+                        players: 1,
+                    }));
+                }
+                Action::Join { ship } => {
+                    self.disconnect();
+
+                    // any port will do when joining, the host will tell everyone about it
+                    let any = match self.address {
+                        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+                        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+                    };
+
+                    if let Err(e) = self.bind(any) {
+                        bus.send(Message::Net(NetMessage::Failed(e.to_string())));
+                        continue;
+                    }
+
+                    self.status = Status::Joining;
+                    self.ship = ship;
+                }
+                Action::Start => {
+                    // only the host can start the game
+                    if self.status != Status::Hosting {
+                        continue;
+                    }
+
+                    let (addrs, ships): (Vec<_>, Vec<_>) = self.lobby.iter().cloned().unzip();
+
+                    // everyone is told about everyone else in a single packet,
+                    // a lobby that doesn't fit in one can't be started
+                    Packet::Start {
+                        pid: 0,
+                        seed: 0,
+                        map: self.map.clone(),
+                        mode: self.mode.clone(),
+                        ships: ships.clone(),
+                        addrs: addrs.clone(),
+                    }
+                    .encode(&mut self.buf);
+
+                    if self.buf.len() > Packet::MAX_SIZE {
+                        bus.send(Message::Net(NetMessage::Failed(format!(
+                            "the lobby takes {} bytes to start, at most {} fit in a packet",
+                            self.buf.len(),
+                            Packet::MAX_SIZE
+                        ))));
+                        continue;
+                    }
+
+                    self.lobby.clear();
+
+                    let session = Session {
+                        pid: 0,
+                        seed: fastrand::u64(0..1024),
+                        map: self.map.clone(),
+                        mode: self.mode.clone(),
+                        ships,
+                        addrs,
+                        taken: false,
+                    };
+
+                    self.start(session);
+
+                    bus.send(Message::Net(NetMessage::Started));
+                }
+                Action::GetSession => {
+                    let (pid, players, seed, map, mode, ships) = match &mut self.session {
+                        Some(session) => {
+                            session.taken = true;
+
+                            (
+                                session.pid,
+                                session.addrs.len() as u8,
+                                session.seed,
+                                Some(session.map.clone()),
+                                Some(session.mode.clone()),
+                                Some(session.ships.clone()),
+                            )
+                        }
+                        // no session means we're playing by ourselves
+                        None => (0, 1, fastrand::u64(0..1024), None, None, None),
+                    };
+
+                    bus.send(Message::Net(NetMessage::SessionGet {
+                        pid,
+                        players,
+                        seed,
+                        map,
+                        mode,
+                        ships,
+                    }));
+                }
+                Action::Disconnect => {
+                    // let the host know we're leaving, it's fine if this gets lost,
+                    // the host will simply not be able to start the game with us
+                    if self.status == Status::Joined {
+                        self.send(self.address, &Packet::Leave);
+                    }
+
+                    self.disconnect();
+
+                    bus.send(Message::Net(NetMessage::Disconnected));
+                }
+                Action::SendCommands(tick, cmds) => {
+                    // nobody to send to when playing by ourselves
+                    if self.status != Status::Playing {
+                        continue;
+                    }
+
+                    // the peers would wait for a tick that never arrives
+                    if cmds.len() > Packet::MAX_COMMANDS {
+                        self.disconnect();
+
+                        bus.send(Message::Net(NetMessage::Failed(format!(
+                            "{} commands for tick {}, at most {} can be sent",
+                            cmds.len(),
+                            tick,
+                            Packet::MAX_COMMANDS
+                        ))));
+                        continue;
+                    }
+
+                    self.sent.insert(tick, cmds);
+                }
+                Action::SendChecksum(checksum) => {
+                    if self.status != Status::Playing {
+                        continue;
+                    }
+
+                    self.checksums.insert(checksum.tick, checksum);
+
+                    while self.checksums.len() > NetManager::CHECKSUM_TICKS {
+                        self.checksums.pop_first();
+                    }
+                }
+            }
+        }
+    }
+
+    fn receive(&mut self, bus: &mut Bus) {
+        let mut buf = [0; Packet::MAX_SIZE];
+
+        loop {
+            let socket = match &self.socket {
+                Some(socket) => socket,
+                None => return,
+            };
+
+            let (len, addr) = match socket.recv_from(&mut buf) {
+                Ok(recv) => recv,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                // some platforms report unreachable peers here, the peer
+                // might just not be up yet so keep on reading
+                Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+                Err(e) => {
+                    println!("WARN: NET: failed to receive: {}", e);
+                    break;
+                }
+            };
+
+            let packet = match Packet::decode(&buf[..len]) {
+                Some(packet) => packet,
+                None => continue,
+            };
+
+            match (&self.status, packet) {
+                (Status::Hosting, Packet::Join { ship }) => {
+                    if self.lobby.iter().any(|x| x.0 == addr) {
+                        continue;
+                    }
+
+                    if self.lobby.len() >= self.max_players as usize {
+                        self.send(addr, &Packet::Rejected(Rejection::Full));
+                        continue;
+                    }
+
+                    // the ship class is looked up by everyone once the game starts
+                    self.lobby.push((addr, ship));
+
+                    bus.send(Message::Net(NetMessage::LobbyUpdated {
+                        pid: 0,
+                        players: self.lobby.len() as u8,
+                    }));
+                }
+                (Status::Hosting, Packet::Leave) => {
+                    // the pids of everyone after the leaving player will shift,
+                    // they will be told about it in the next welcome
+                    match self.lobby.iter().skip(1).position(|x| x.0 == addr) {
+                        Some(idx) => self.lobby.remove(idx + 1),
+                        None => continue,
+                    };
+
+                    bus.send(Message::Net(NetMessage::LobbyUpdated {
+                        pid: 0,
+                        players: self.lobby.len() as u8,
+                    }));
+                }
+                (Status::Playing, Packet::Join { .. }) => {
+                    // players that have not yet received the start packet will
+                    // keep on trying to join, those should not be rejected
+                    if self.peers.iter().any(|x| x.addr == addr) {
+                        continue;
+                    }
+
+                    if self.session.as_ref().is_some_and(|x| x.pid == 0) {
+                        self.send(addr, &Packet::Rejected(Rejection::Started));
+                    }
+                }
+                (Status::Joining | Status::Joined, Packet::Welcome { pid, players }) => {
+                    if addr != self.address {
+                        continue;
+                    }
+
+                    self.status = Status::Joined;
+
+                    bus.send(Message::Net(NetMessage::LobbyUpdated { pid, players }));
+                }
+                (Status::Joining | Status::Joined, Packet::Rejected(rejection)) => {
+                    if addr != self.address {
+                        continue;
+                    }
+
+                    self.disconnect();
+
+                    bus.send(Message::Net(NetMessage::Rejected(rejection)));
+                }
+                (
+                    Status::Joining | Status::Joined,
+                    Packet::Start {
+                        pid,
+                        seed,
+                        map,
+                        mode,
+                        ships,
+                        mut addrs,
+                    },
+                ) => {
+                    if addr != self.address || pid as usize >= addrs.len() {
+                        continue;
+                    }
+
+                    // the host only knows the address it's bound to,
+                    // we know the one we reach it at
+                    addrs[0] = self.address;
+
+                    self.start(Session {
+                        pid,
+                        seed,
+                        map,
+                        mode,
+                        ships,
+                        addrs,
+                        taken: false,
+                    });
+
+                    bus.send(Message::Net(NetMessage::Started));
+                }
+                (
+                    Status::Playing,
+                    Packet::Commands {
+                        pid,
+                        ack,
+                        ticks,
+                        checksums,
+                    },
+                ) => {
+                    let peer = match self
+                        .peers
+                        .iter_mut()
+                        .find(|x| x.pid == pid && x.addr == addr)
+                    {
+                        Some(peer) => peer,
+                        None => continue,
+                    };
+
+                    peer.ack = peer.ack.max(ack);
+                    peer.synced = true;
+
+                    // the ticks are resent until acknowledged,
+                    // so anything before the one we expect next is a duplicate
+                    for (tick, cmds) in ticks {
+                        if peer.recv.is_some_and(|x| tick < x) {
+                            continue;
+                        }
+
+                        peer.held.entry(tick).or_insert(cmds);
+                    }
+
+                    for checksum in checksums {
+                        peer.checksums.insert(checksum.tick, checksum);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // forget about everything every peer has received
+        if let Some(ack) = self.peers.iter().map(|x| x.ack).min() {
+            self.sent = self.sent.split_off(&ack);
+        }
+    }
+
+    // hands the held commands to the game in order once it has taken the session
+    fn deliver(&mut self, bus: &mut Bus) {
+        if !self.session.as_ref().is_some_and(|x| x.taken) {
+            return;
+        }
+
+        for peer in self.peers.iter_mut() {
+            while let Some(entry) = peer.held.first_entry() {
+                let tick = *entry.key();
+
+                // wait for the ones missing in between to be resent
+                if tick != peer.recv.unwrap_or(tick) {
+                    break;
+                }
+
+                peer.recv = Some(tick + 1);
+
+                bus.send(Message::Net(NetMessage::CommandsReceived {
+                    pid: peer.pid,
+                    tick,
+                    cmds: entry.remove(),
+                }));
+            }
+        }
+    }
+
+    fn verify(&mut self, bus: &mut Bus) {
+        for peer in self.peers.iter_mut() {
+            // compare every tick we both have a checksum for, oldest first
+            let ticks: Vec<u64> = peer
+                .checksums
+                .keys()
+                .filter(|x| self.checksums.contains_key(x))
+                .copied()
+                .collect();
+
+            for tick in ticks {
+                let (ours, theirs) = match (self.checksums.get(&tick), peer.checksums.remove(&tick))
+                {
+                    (Some(ours), Some(theirs)) => (ours, theirs),
+                    _ => continue,
+                };
+
+                let divergence = match ours.diverges(&theirs) {
+                    Some(divergence) => divergence,
+                    None => continue,
+                };
+
+                if !peer.desynced {
+                    peer.desynced = true;
+
+                    bus.send(Message::Net(NetMessage::Desynced {
+                        pid: peer.pid,
+                        tick,
+                        divergence,
+                    }));
+                }
+            }
+
+            // anything older than what we remember can never be compared
+            if let Some(oldest) = self.checksums.keys().next() {
+                peer.checksums = peer.checksums.split_off(oldest);
+            }
+        }
+    }
+
+    fn transmit(&mut self) {
+        // we send to everyone every tick, even if there's nothing new,
+        // this way lost packets are resent and acks always get through
+        match self.status {
+            Status::Offline => (),
+            Status::Hosting => {
+                let players = self.lobby.len() as u8;
+
+                for (pid, (addr, _)) in self.lobby.clone().into_iter().enumerate().skip(1) {
+                    self.send(
+                        addr,
+                        &Packet::Welcome {
+                            pid: pid as u8,
+                            players,
+                        },
+                    );
+                }
+            }
+            Status::Joining | Status::Joined => {
+                self.send(
+                    self.address,
+                    &Packet::Join {
+                        ship: self.ship.clone(),
+                    },
+                );
+            }
+            Status::Playing => {
+                let (pid, seed, map, mode, ships, addrs) = match &self.session {
+                    Some(session) => (
+                        session.pid,
+                        session.seed,
+                        session.map.clone(),
+                        session.mode.clone(),
+                        session.ships.clone(),
+                        session.addrs.clone(),
+                    ),
+                    None => return,
+                };
+
+                let checksums: Vec<Checksum> = self
+                    .checksums
+                    .values()
+                    .rev()
+                    .take(Packet::MAX_CHECKSUMS)
+                    .rev()
+                    .cloned()
+                    .collect();
+
+                let mut packets = Vec::new();
+
+                for peer in self.peers.iter() {
+                    if pid == 0 && !peer.synced {
+                        packets.push((
+                            peer.addr,
+                            Packet::Start {
+                                pid: peer.pid,
+                                seed,
+                                map: map.clone(),
+                                mode: mode.clone(),
+                                ships: ships.clone(),
+                                addrs: addrs.clone(),
+                            },
+                        ));
+                    }
+
+                    packets.push((
+                        peer.addr,
+                        Packet::Commands {
+                            pid,
+                            ack: peer.recv.unwrap_or(0),
+                            ticks: self
+                                .sent
+                                .range(peer.ack..)
+                                .take(Packet::MAX_TICKS)
+                                .map(|(tick, cmds)| (*tick, cmds.clone()))
+                                .collect(),
+                            checksums: checksums.clone(),
+                        },
+                    ));
+                }
+
+                for (addr, packet) in packets {
+                    self.send(addr, &packet);
+                }
+            }
+        }
+    }
+
+    fn start(&mut self, session: Session) {
+        self.status = Status::Playing;
+        self.peers = session
+            .addrs
+            .iter()
+            .enumerate()
+            .filter(|(pid, _)| *pid != session.pid as usize)
+            .map(|(pid, addr)| Peer {
+                pid: pid as u8,
+                addr: *addr,
+                ack: 0,
+                recv: None,
+                held: BTreeMap::new(),
+                synced: false,
+                checksums: BTreeMap::new(),
+                desynced: false,
+            })
+            .collect();
+        self.session = Some(session);
+    }
+
+    fn send(&mut self, addr: SocketAddr, packet: &Packet) {
+        let socket = match &self.socket {
+            Some(socket) => socket,
+            None => return,
+        };
+
+        packet.encode(&mut self.buf);
+
+        // the commands are cut to fit and a lobby too big to start is refused,
+        // dropping anything else would leave the peer waiting for it forever
+        assert!(
+            self.buf.len() <= Packet::MAX_SIZE,
+            "{} bytes for {}, at most {} fit in a packet",
+            self.buf.len(),
+            addr,
+            Packet::MAX_SIZE
+        );
+
+        if let Err(e) = socket.send_to(&self.buf, addr) {
+            println!("WARN: NET: failed to send to {}: {}", addr, e);
+        }
+    }
+
+    fn bind(&mut self, addr: SocketAddr) -> std::io::Result<()> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+
+        self.socket = Some(socket);
+
+        Ok(())
+    }
+
+    fn disconnect(&mut self) {
+        self.status = Status::Offline;
+        self.socket = None;
+        self.session = None;
+        self.lobby.clear();
+        self.peers.clear();
+        self.sent.clear();
+        self.checksums.clear();
+    }
+}
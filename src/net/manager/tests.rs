@@ -0,0 +1,436 @@
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    bus::Bus,
+    commands::Command,
+    messages::{Message, NetMessage, NetRequestMessage, RequestMessage, Sender},
+};
+
+use super::{NetManager, Packet, Rejection, Session, Status};
+
+// two players already in a game that took the session, talking over loopback sockets
+fn pair() -> (NetManager, NetManager) {
+    let mut nets = [0, 1].map(|_| {
+        let mut net = NetManager::new(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)));
+        net.bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .unwrap();
+        net
+    });
+
+    let addrs: Vec<SocketAddr> = nets
+        .iter()
+        .map(|x| x.socket.as_ref().unwrap().local_addr().unwrap())
+        .collect();
+
+    for (pid, net) in nets.iter_mut().enumerate() {
+        net.start(Session {
+            pid: pid as u8,
+            seed: 0,
+            map: String::from("arena"),
+            mode: String::from("deathmatch"),
+            ships: vec![String::from("fighter"); 2],
+            addrs: addrs.clone(),
+            taken: false,
+        });
+
+        request(net, NetRequestMessage::GetSession);
+    }
+
+    let [a, b] = nets;
+    (a, b)
+}
+
+// a host on a port nobody else is using right now
+fn host(max_players: u8) -> NetManager {
+    let address = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|x| x.local_addr())
+        .unwrap();
+
+    let mut net = NetManager::new(address);
+
+    request(
+        &mut net,
+        NetRequestMessage::Host {
+            max_players,
+            map: String::from("arena"),
+            mode: String::from("deathmatch"),
+            ship: String::from("fighter"),
+        },
+    );
+
+    net
+}
+
+fn join(host: &NetManager, ship: &str) -> NetManager {
+    let mut net = NetManager::new(host.address);
+
+    request(
+        &mut net,
+        NetRequestMessage::Join {
+            ship: String::from(ship),
+        },
+    );
+
+    net
+}
+
+fn request(net: &mut NetManager, req: NetRequestMessage) {
+    net.message(&Sender::None, &Message::Request(RequestMessage::Net(req)));
+}
+
+fn send(net: &mut NetManager, tick: u64, cmds: Vec<Command>) {
+    request(net, NetRequestMessage::SendCommands { tick, cmds });
+}
+
+fn update(net: &mut NetManager) -> Vec<NetMessage> {
+    // loopback delivers right away, but give it a moment anyway
+    thread::sleep(Duration::from_millis(5));
+
+    let mut bus = Bus::new();
+    net.update(&mut bus);
+
+    let mut msgs = Vec::new();
+
+    while let Some((_, msg)) = bus.pop() {
+        if let Message::Net(msg) = msg {
+            msgs.push(msg);
+        }
+    }
+
+    msgs
+}
+
+// the ticks of the commands received in this update
+fn received(net: &mut NetManager) -> Vec<u64> {
+    update(net)
+        .into_iter()
+        .filter_map(|x| match x {
+            NetMessage::CommandsReceived { tick, .. } => Some(tick),
+            _ => None,
+        })
+        .collect()
+}
+
+fn lobby(msgs: &[NetMessage]) -> Option<(u8, u8)> {
+    msgs.iter().rev().find_map(|x| match x {
+        NetMessage::LobbyUpdated { pid, players } => Some((*pid, *players)),
+        _ => None,
+    })
+}
+
+fn rejection(msgs: &[NetMessage]) -> Option<Rejection> {
+    msgs.iter().find_map(|x| match x {
+        NetMessage::Rejected(rejection) => Some(*rejection),
+        _ => None,
+    })
+}
+
+fn started(msgs: &[NetMessage]) -> bool {
+    msgs.iter().any(|x| matches!(x, NetMessage::Started))
+}
+
+// throws away everything that arrived, as if it got lost on the way
+fn lose(net: &mut NetManager) {
+    thread::sleep(Duration::from_millis(5));
+
+    let socket = net.socket.as_ref().unwrap();
+    let mut buf = [0; Packet::MAX_SIZE];
+
+    loop {
+        match socket.recv_from(&mut buf) {
+            Ok(_) => continue,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => panic!("failed to receive: {}", e),
+        }
+    }
+}
+
+#[test]
+fn commands_are_received_in_order_and_acknowledged() {
+    let (mut a, mut b) = pair();
+
+    for tick in 0..3 {
+        send(&mut a, tick, vec![Command::Accelerate]);
+    }
+
+    update(&mut a);
+    assert_eq!(received(&mut b), vec![0, 1, 2]);
+
+    // a keeps resending until b's ack comes back
+    assert_eq!(a.sent.len(), 3);
+    assert_eq!(received(&mut a), Vec::<u64>::new());
+    assert!(a.sent.is_empty());
+
+    // anything resent in between is not received twice
+    assert_eq!(received(&mut b), Vec::<u64>::new());
+}
+
+#[test]
+fn lost_commands_are_resent() {
+    let (mut a, mut b) = pair();
+
+    send(&mut a, 0, vec![Command::Shoot]);
+    update(&mut a);
+    lose(&mut b);
+
+    send(&mut a, 1, vec![Command::Nop]);
+    update(&mut a);
+    assert_eq!(received(&mut b), vec![0, 1]);
+}
+
+#[test]
+fn missing_commands_stall_until_the_peer_catches_up() {
+    let (mut a, mut b) = pair();
+    let ticks = Packet::MAX_TICKS as u64 + 8;
+
+    // everything b sends gets lost while we wait for it
+    for tick in 0..ticks {
+        send(&mut b, tick, vec![Command::RotateLeft]);
+        update(&mut b);
+        lose(&mut a);
+        assert_eq!(received(&mut a), Vec::<u64>::new());
+    }
+
+    // once through, only as much as fits in one packet arrives at once,
+    // the rest follows after we acknowledged it
+    update(&mut b);
+    assert_eq!(
+        received(&mut a),
+        (0..Packet::MAX_TICKS as u64).collect::<Vec<_>>()
+    );
+    update(&mut b);
+    assert_eq!(
+        received(&mut a),
+        (Packet::MAX_TICKS as u64..ticks).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn commands_too_big_for_one_packet_are_split() {
+    let (mut a, mut b) = pair();
+
+    for tick in 0..Packet::MAX_TICKS as u64 {
+        send(&mut a, tick, vec![Command::Shoot; 100]);
+    }
+
+    // only so many ticks of a hundred commands fit at once,
+    // the rest follows as soon as they're acknowledged
+    let mut ticks = Vec::new();
+
+    for _ in 0..Packet::MAX_TICKS {
+        update(&mut a);
+        let received = received(&mut b);
+        assert!(received.len() < Packet::MAX_TICKS);
+        ticks.extend(received);
+    }
+
+    assert_eq!(ticks, (0..Packet::MAX_TICKS as u64).collect::<Vec<_>>());
+}
+
+#[test]
+fn too_many_commands_to_send_fail_the_game() {
+    let (mut a, _) = pair();
+
+    send(&mut a, 0, vec![Command::Nop; Packet::MAX_COMMANDS + 1]);
+
+    assert!(update(&mut a)
+        .iter()
+        .any(|x| matches!(x, NetMessage::Failed(_))));
+    assert!(a.status == Status::Offline);
+}
+
+#[test]
+fn joining_players_are_welcomed_with_their_pid() {
+    let mut host = host(4);
+    assert_eq!(lobby(&update(&mut host)), Some((0, 1)));
+
+    let mut first = join(&host, "scout");
+    let mut second = join(&host, "gunship");
+    update(&mut first);
+    update(&mut second);
+
+    assert_eq!(lobby(&update(&mut host)), Some((0, 3)));
+    assert_eq!(lobby(&update(&mut first)), Some((1, 3)));
+    assert_eq!(lobby(&update(&mut second)), Some((2, 3)));
+    assert!(first.status == Status::Joined && second.status == Status::Joined);
+
+    // everyone after a leaving player moves up a pid
+    request(&mut first, NetRequestMessage::Disconnect);
+    update(&mut first);
+
+    assert_eq!(lobby(&update(&mut host)), Some((0, 2)));
+    assert_eq!(lobby(&update(&mut second)), Some((1, 2)));
+}
+
+#[test]
+fn joining_a_full_lobby_is_rejected() {
+    let mut host = host(2);
+    let mut first = join(&host, "scout");
+    update(&mut host);
+    update(&mut first);
+    update(&mut host);
+    assert_eq!(lobby(&update(&mut first)), Some((1, 2)));
+
+    let mut second = join(&host, "scout");
+    update(&mut second);
+    update(&mut host);
+
+    assert_eq!(rejection(&update(&mut second)), Some(Rejection::Full));
+    assert!(second.status == Status::Offline);
+}
+
+#[test]
+fn joining_a_started_game_is_rejected() {
+    let mut host = host(4);
+    let mut first = join(&host, "scout");
+    update(&mut host);
+    update(&mut first);
+    update(&mut host);
+    update(&mut first);
+
+    request(&mut host, NetRequestMessage::Start);
+    assert!(started(&update(&mut host)));
+
+    let mut late = join(&host, "scout");
+    update(&mut late);
+    update(&mut host);
+
+    assert_eq!(rejection(&update(&mut late)), Some(Rejection::Started));
+    assert!(late.status == Status::Offline);
+
+    // the players that made it in are not rejected while they wait for the start
+    assert_eq!(rejection(&update(&mut first)), None);
+}
+
+#[test]
+fn a_lobby_too_big_for_one_packet_is_not_started() {
+    let mut host = host(8);
+    let ship = "x".repeat(255);
+    let mut players: Vec<NetManager> = (0..7).map(|_| join(&host, &ship)).collect();
+
+    for _ in 0..2 {
+        update(&mut host);
+
+        for player in players.iter_mut() {
+            update(player);
+        }
+    }
+
+    assert_eq!(host.lobby.len(), 8);
+    request(&mut host, NetRequestMessage::Start);
+
+    let msgs = update(&mut host);
+    assert!(!started(&msgs));
+    assert!(msgs.iter().any(|x| matches!(x, NetMessage::Failed(_))));
+    assert!(host.status == Status::Hosting);
+}
+
+#[test]
+fn everyone_starts_the_same_session() {
+    let mut host = host(4);
+    let mut players = [join(&host, "scout"), join(&host, "gunship")];
+
+    for _ in 0..2 {
+        update(&mut host);
+
+        for player in players.iter_mut() {
+            update(player);
+        }
+    }
+
+    request(&mut host, NetRequestMessage::Start);
+    assert!(started(&update(&mut host)));
+
+    for player in players.iter_mut() {
+        assert!(started(&update(player)));
+    }
+
+    let mut sessions = Vec::new();
+
+    for net in std::iter::once(&mut host).chain(players.iter_mut()) {
+        request(net, NetRequestMessage::GetSession);
+
+        sessions.extend(update(net).into_iter().filter_map(|x| match x {
+            NetMessage::SessionGet {
+                pid,
+                players,
+                seed,
+                map,
+                mode,
+                ships,
+            } => Some((pid, players, seed, map, mode, ships)),
+            _ => None,
+        }));
+    }
+
+    let ships = vec![
+        String::from("fighter"),
+        String::from("scout"),
+        String::from("gunship"),
+    ];
+
+    assert_eq!(sessions.len(), 3);
+
+    for (pid, session) in sessions.iter().enumerate() {
+        assert_eq!(session.0, pid as u8);
+        assert_eq!(session.1, 3);
+        assert_eq!(session.2, sessions[0].2);
+        assert_eq!(session.3.as_deref(), Some("arena"));
+        assert_eq!(session.4.as_deref(), Some("deathmatch"));
+        assert_eq!(session.5.as_ref(), Some(&ships));
+    }
+}
+
+#[test]
+fn commands_are_held_until_the_session_is_taken() {
+    let mut host = host(2);
+    let mut player = join(&host, "scout");
+    update(&mut host);
+    update(&mut player);
+    update(&mut host);
+
+    request(&mut host, NetRequestMessage::Start);
+
+    for tick in 0..3 {
+        send(&mut host, tick, vec![Command::Accelerate]);
+    }
+
+    assert!(started(&update(&mut host)));
+
+    // the commands arrive right along with the start,
+    // but the game is still busy getting there
+    let msgs = update(&mut player);
+    assert!(started(&msgs));
+    assert!(!msgs
+        .iter()
+        .any(|x| matches!(x, NetMessage::CommandsReceived { .. })));
+
+    for _ in 0..2 {
+        update(&mut host);
+        assert_eq!(received(&mut player), Vec::<u64>::new());
+    }
+
+    // nothing was acknowledged so the host still has all of it
+    update(&mut host);
+    assert_eq!(host.sent.len(), 3);
+
+    request(&mut player, NetRequestMessage::GetSession);
+
+    let msgs = update(&mut player);
+    assert!(matches!(msgs[0], NetMessage::SessionGet { pid: 1, .. }));
+    assert_eq!(
+        msgs[1..]
+            .iter()
+            .filter_map(|x| match x {
+                NetMessage::CommandsReceived { pid: 0, tick, .. } => Some(*tick),
+                _ => None,
+            })
+            .collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+}
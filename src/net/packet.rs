@@ -0,0 +1,292 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::{bytes::Reader, checksum::Checksum, commands::Command};
+
+// every packet starts with the magic bytes and the protocol version,
+// anything else that ends up on our port is simply ignored
+const MAGIC: [u8; 2] = *b"FJ";
+const VERSION: u8 = 8;
+
+const KIND_COMMANDS: u8 = 0;
+const KIND_JOIN: u8 = 1;
+const KIND_LEAVE: u8 = 2;
+const KIND_WELCOME: u8 = 3;
+const KIND_REJECTED: u8 = 4;
+const KIND_START: u8 = 5;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Packet {
+    // all commands for the ticks the receiver has not yet acknowledged,
+    // ack is the first tick the sender has not yet received from the receiver,
+    // the latest checksums of the sender are resent a couple of times instead
+    Commands {
+        pid: u8,
+        ack: u64,
+        ticks: Vec<(u64, Vec<Command>)>,
+        checksums: Vec<Checksum>,
+    },
+    // sent to the host until we get welcomed or rejected,
+    // with the ship class we want to fly
+    Join {
+        ship: String,
+    },
+    Leave,
+    // sent by the host to everyone in the lobby, every tick
+    Welcome {
+        pid: u8,
+        players: u8,
+    },
+    Rejected(Rejection),
+    // sent by the host until the player starts sending commands,
+    // contains the map, the mode, and the ship class and address of every player ordered by pid
+    Start {
+        pid: u8,
+        seed: u64,
+        map: String,
+        mode: String,
+        ships: Vec<String>,
+        addrs: Vec<SocketAddr>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rejection {
+    Full,
+    Started,
+}
+
+impl Packet {
+    // the largest packet we send or receive, anything bigger risks getting
+    // fragmented or dropped along the way
+    pub const MAX_SIZE: usize = 1500;
+    // the most ticks we send in one packet, fewer if their commands
+    // don't fit in MAX_SIZE, should be plenty since we only resend
+    // what has not been acknowledged
+    pub const MAX_TICKS: usize = 32;
+    // a tick with more commands than this can't be sent at all
+    pub const MAX_COMMANDS: usize = u8::MAX as usize;
+    // losing a checksum now and then is fine, as long as most of them arrive,
+    // the ones that don't fit in MAX_SIZE are left out as well
+    pub const MAX_CHECKSUMS: usize = 4;
+
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        buf.extend_from_slice(&MAGIC);
+        buf.push(VERSION);
+
+        match self {
+            Packet::Commands {
+                pid,
+                ack,
+                ticks,
+                checksums,
+            } => {
+                buf.push(KIND_COMMANDS);
+                buf.push(*pid);
+                buf.extend_from_slice(&ack.to_le_bytes());
+                let count = buf.len();
+                buf.push(0);
+
+                // the ticks that don't fit are sent once these are acknowledged,
+                // with room left for the amount of checksums
+                for (tick, cmds) in ticks.iter().take(Packet::MAX_TICKS) {
+                    let len = match u8::try_from(cmds.len()) {
+                        Ok(len) => len,
+                        Err(_) => break,
+                    };
+
+                    if buf.len() + 8 + 1 + cmds.len() + 1 > Packet::MAX_SIZE {
+                        break;
+                    }
+
+                    buf.extend_from_slice(&tick.to_le_bytes());
+                    buf.push(len);
+                    buf.extend(cmds.iter().map(|x| x.to_u8()));
+                    buf[count] += 1;
+                }
+
+                let count = buf.len();
+                buf.push(0);
+
+                for checksum in checksums.iter().take(Packet::MAX_CHECKSUMS) {
+                    let len = buf.len();
+                    checksum.encode(buf);
+
+                    if buf.len() > Packet::MAX_SIZE {
+                        buf.truncate(len);
+                        break;
+                    }
+
+                    buf[count] += 1;
+                }
+            }
+            Packet::Join { ship } => {
+                buf.push(KIND_JOIN);
+                encode_string(buf, ship);
+            }
+            Packet::Leave => buf.push(KIND_LEAVE),
+            Packet::Welcome { pid, players } => {
+                buf.push(KIND_WELCOME);
+                buf.push(*pid);
+                buf.push(*players);
+            }
+            Packet::Rejected(rejection) => {
+                buf.push(KIND_REJECTED);
+                buf.push(match rejection {
+                    Rejection::Full => 0,
+                    Rejection::Started => 1,
+                });
+            }
+            Packet::Start {
+                pid,
+                seed,
+                map,
+                mode,
+                ships,
+                addrs,
+            } => {
+                buf.push(KIND_START);
+                buf.push(*pid);
+                buf.extend_from_slice(&seed.to_le_bytes());
+                encode_string(buf, map);
+                encode_string(buf, mode);
+                // there's one ship for every address
+                buf.push(addrs.len() as u8);
+
+                for ship in ships.iter().take(addrs.len()) {
+                    encode_string(buf, ship);
+                }
+
+                for addr in addrs {
+                    match addr.ip() {
+                        IpAddr::V4(ip) => {
+                            buf.push(4);
+                            buf.extend_from_slice(&ip.octets());
+                        }
+                        IpAddr::V6(ip) => {
+                            buf.push(6);
+                            buf.extend_from_slice(&ip.octets());
+                        }
+                    }
+
+                    buf.extend_from_slice(&addr.port().to_le_bytes());
+                }
+            }
+        }
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Packet> {
+        let mut reader = Reader::new(buf);
+
+        if reader.bytes(2)? != MAGIC || reader.u8()? != VERSION {
+            return None;
+        }
+
+        match reader.u8()? {
+            KIND_COMMANDS => {
+                let pid = reader.u8()?;
+                let ack = reader.u64()?;
+                let len = reader.u8()?;
+                let mut ticks = Vec::with_capacity(len as usize);
+
+                for _ in 0..len {
+                    let tick = reader.u64()?;
+                    let len = reader.u8()?;
+                    let mut cmds = Vec::with_capacity(len as usize);
+
+                    for _ in 0..len {
+                        cmds.push(Command::from_u8(reader.u8()?)?);
+                    }
+
+                    ticks.push((tick, cmds));
+                }
+
+                let len = reader.u8()?;
+                let mut checksums = Vec::with_capacity(len as usize);
+
+                for _ in 0..len {
+                    checksums.push(Checksum::decode(&mut reader)?);
+                }
+
+                Some(Packet::Commands {
+                    pid,
+                    ack,
+                    ticks,
+                    checksums,
+                })
+            }
+            KIND_JOIN => Some(Packet::Join {
+                ship: decode_string(&mut reader)?,
+            }),
+            KIND_LEAVE => Some(Packet::Leave),
+            KIND_WELCOME => Some(Packet::Welcome {
+                pid: reader.u8()?,
+                players: reader.u8()?,
+            }),
+            KIND_REJECTED => match reader.u8()? {
+                0 => Some(Packet::Rejected(Rejection::Full)),
+                1 => Some(Packet::Rejected(Rejection::Started)),
+                _ => None,
+            },
+            KIND_START => {
+                let pid = reader.u8()?;
+                let seed = reader.u64()?;
+                let map = decode_string(&mut reader)?;
+                let mode = decode_string(&mut reader)?;
+                let len = reader.u8()?;
+                let mut ships = Vec::with_capacity(len as usize);
+
+                for _ in 0..len {
+                    ships.push(decode_string(&mut reader)?);
+                }
+
+                let mut addrs = Vec::with_capacity(len as usize);
+
+                for _ in 0..len {
+                    let ip = match reader.u8()? {
+                        4 => {
+                            IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(reader.bytes(4)?).ok()?))
+                        }
+                        6 => IpAddr::V6(Ipv6Addr::from(
+                            <[u8; 16]>::try_from(reader.bytes(16)?).ok()?,
+                        )),
+                        _ => return None,
+                    };
+
+                    addrs.push(SocketAddr::new(ip, reader.u16()?));
+                }
+
+                Some(Packet::Start {
+                    pid,
+                    seed,
+                    map,
+                    mode,
+                    ships,
+                    addrs,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+// at most 255 bytes, anything longer is cut off at the last whole character
+fn encode_string(buf: &mut Vec<u8>, value: &str) {
+    let mut len = value.len().min(u8::MAX as usize);
+
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    let bytes = &value.as_bytes()[..len];
+    buf.push(bytes.len() as u8);
+    buf.extend_from_slice(bytes);
+}
+
+fn decode_string(reader: &mut Reader) -> Option<String> {
+    let len = reader.u8()?;
+    String::from_utf8(reader.bytes(len as usize)?.to_vec()).ok()
+}
@@ -0,0 +1,250 @@
+use std::net::SocketAddr;
+
+use crate::{
+    checksum::{Checksum, Group},
+    commands::Command,
+};
+
+use super::{Packet, Rejection};
+
+fn checksum(tick: u64, players: usize) -> Checksum {
+    Checksum {
+        tick,
+        world: tick * 7,
+        players: (0..players as u64).map(|x| x + tick).collect(),
+        projectiles: Group::default(),
+        pickups: Group::default(),
+        exhausts: Group::default(),
+        explosions: Group::default(),
+        stars: Group {
+            count: 3,
+            sum: tick,
+            weighted: tick * 2,
+        },
+    }
+}
+
+fn round_trip(packet: &Packet) -> Option<Packet> {
+    let mut buf = Vec::new();
+    packet.encode(&mut buf);
+    Packet::decode(&buf)
+}
+
+#[test]
+fn every_kind_survives_a_round_trip() {
+    let packets = [
+        Packet::Commands {
+            pid: 3,
+            ack: 1234,
+            ticks: vec![
+                (10, vec![Command::Nop]),
+                (11, vec![]),
+                (
+                    12,
+                    vec![Command::RotateLeft, Command::Shoot, Command::SwitchWeapon],
+                ),
+            ],
+            checksums: vec![checksum(9, 2), checksum(10, 2)],
+        },
+        Packet::Join {
+            ship: String::from("fighter"),
+        },
+        Packet::Leave,
+        Packet::Welcome { pid: 2, players: 4 },
+        Packet::Rejected(Rejection::Full),
+        Packet::Rejected(Rejection::Started),
+        Packet::Start {
+            pid: 1,
+            seed: u64::MAX,
+            map: String::from("arena"),
+            mode: String::from("deathmatch"),
+            ships: vec![String::from("fighter"), String::from("scout")],
+            addrs: vec![
+                "127.0.0.1:4000".parse().unwrap(),
+                "[::1]:4001".parse().unwrap(),
+            ],
+        },
+    ];
+
+    for packet in packets {
+        assert_eq!(round_trip(&packet), Some(packet));
+    }
+}
+
+#[test]
+fn foreign_and_truncated_packets_are_ignored() {
+    let mut buf = Vec::new();
+    Packet::Welcome { pid: 1, players: 2 }.encode(&mut buf);
+
+    assert!(Packet::decode(&buf).is_some());
+
+    for len in 0..buf.len() {
+        assert_eq!(Packet::decode(&buf[..len]), None);
+    }
+
+    let mut magic = buf.clone();
+    magic[0] = b'X';
+    assert_eq!(Packet::decode(&magic), None);
+
+    let mut version = buf.clone();
+    version[2] += 1;
+    assert_eq!(Packet::decode(&version), None);
+
+    let mut kind = buf.clone();
+    kind[3] = u8::MAX;
+    assert_eq!(Packet::decode(&kind), None);
+}
+
+#[test]
+fn long_names_are_cut_at_a_character() {
+    // 'ö' takes two bytes, so byte 255 falls in the middle of one
+    let ship = "ö".repeat(200);
+
+    let decoded = match round_trip(&Packet::Join { ship: ship.clone() }) {
+        Some(Packet::Join { ship }) => ship,
+        packet => panic!("expected a join packet, got {:?}", packet),
+    };
+
+    assert_eq!(decoded.len(), 254);
+    assert!(ship.starts_with(&decoded));
+}
+
+#[test]
+fn a_full_window_fits_in_one_packet() {
+    let all = vec![
+        Command::Nop,
+        Command::RotateLeft,
+        Command::RotateRight,
+        Command::Accelerate,
+        Command::Decelerate,
+        Command::Shoot,
+        Command::Explode,
+        Command::SwitchWeapon,
+    ];
+
+    let mut buf = Vec::new();
+
+    Packet::Commands {
+        pid: 0,
+        ack: 0,
+        ticks: (0..Packet::MAX_TICKS as u64)
+            .map(|x| (x, all.clone()))
+            .collect(),
+        checksums: (0..Packet::MAX_CHECKSUMS as u64)
+            .map(|x| checksum(x, 8))
+            .collect(),
+    }
+    .encode(&mut buf);
+
+    assert!(buf.len() <= Packet::MAX_SIZE);
+
+    match Packet::decode(&buf) {
+        Some(Packet::Commands {
+            ticks, checksums, ..
+        }) => {
+            assert_eq!(ticks.len(), Packet::MAX_TICKS);
+            assert_eq!(checksums.len(), Packet::MAX_CHECKSUMS);
+        }
+        packet => panic!("expected a commands packet, got {:?}", packet),
+    }
+}
+
+#[test]
+fn checksums_that_do_not_fit_are_left_out() {
+    // with a hash for every one of a hundred players only one checksum fits
+    let checksums: Vec<Checksum> = (0..Packet::MAX_CHECKSUMS as u64)
+        .map(|x| checksum(x, 100))
+        .collect();
+
+    let mut buf = Vec::new();
+
+    Packet::Commands {
+        pid: 0,
+        ack: 0,
+        ticks: vec![(0, vec![Command::Shoot])],
+        checksums: checksums.clone(),
+    }
+    .encode(&mut buf);
+
+    assert!(buf.len() <= Packet::MAX_SIZE);
+
+    // the oldest ones are kept, the newer ones are resent in the next packets anyway
+    match Packet::decode(&buf) {
+        Some(Packet::Commands {
+            checksums: decoded, ..
+        }) => assert_eq!(decoded, checksums[..1]),
+        packet => panic!("expected a commands packet, got {:?}", packet),
+    }
+}
+
+#[test]
+fn ticks_that_do_not_fit_are_left_out() {
+    // a hundred commands take up 109 bytes a tick, only 13 of them fit
+    let ticks: Vec<(u64, Vec<Command>)> = (0..Packet::MAX_TICKS as u64)
+        .map(|x| (x, vec![Command::Shoot; 100]))
+        .collect();
+
+    let mut buf = Vec::new();
+
+    Packet::Commands {
+        pid: 0,
+        ack: 0,
+        ticks: ticks.clone(),
+        checksums: Vec::new(),
+    }
+    .encode(&mut buf);
+
+    assert!(buf.len() <= Packet::MAX_SIZE);
+
+    // the rest follows once these are acknowledged
+    match Packet::decode(&buf) {
+        Some(Packet::Commands { ticks: decoded, .. }) => assert_eq!(decoded, ticks[..13]),
+        packet => panic!("expected a commands packet, got {:?}", packet),
+    }
+
+    // too many commands to count can't be sent at all,
+    // and neither can anything after them
+    Packet::Commands {
+        pid: 0,
+        ack: 0,
+        ticks: vec![
+            (0, vec![Command::Nop]),
+            (1, vec![Command::Nop; Packet::MAX_COMMANDS + 1]),
+            (2, vec![Command::Nop]),
+        ],
+        checksums: Vec::new(),
+    }
+    .encode(&mut buf);
+
+    match Packet::decode(&buf) {
+        Some(Packet::Commands { ticks, .. }) => assert_eq!(ticks, vec![(0, vec![Command::Nop])]),
+        packet => panic!("expected a commands packet, got {:?}", packet),
+    }
+}
+
+#[test]
+fn addresses_line_up_with_ships() {
+    let addrs: Vec<SocketAddr> = vec!["10.0.0.1:1".parse().unwrap()];
+
+    // extra ships without an address are not sent
+    let packet = Packet::Start {
+        pid: 0,
+        seed: 0,
+        map: String::new(),
+        mode: String::new(),
+        ships: vec![String::from("a"), String::from("b")],
+        addrs: addrs.clone(),
+    };
+
+    match round_trip(&packet) {
+        Some(Packet::Start {
+            ships,
+            addrs: decoded,
+            ..
+        }) => {
+            assert_eq!(ships, vec![String::from("a")]);
+            assert_eq!(decoded, addrs);
+        }
+        packet => panic!("expected a start packet, got {:?}", packet),
+    }
+}
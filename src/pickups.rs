@@ -0,0 +1,83 @@
+use std::ops::Range;
+
+use crate::{components::render::RenderColor, math::Flint};
+
+// lying around at the pickup points of the map until a ship flies over it,
+// the ship is given what it's named after, most of it only for a while
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pickup {
+    Health,
+    Shield,
+    Weapon,
+    Speed,
+}
+
+// what a pickup does to the ship that collected it, until it wears off
+#[derive(Clone)]
+pub struct Effect {
+    pub pickup: Pickup,
+    // ticks left until it wears off
+    pub ticks: i32,
+}
+
+impl Pickup {
+    pub const ALL: [Pickup; 4] = [
+        Pickup::Health,
+        Pickup::Shield,
+        Pickup::Weapon,
+        Pickup::Speed,
+    ];
+    // ticks until the next one shows up at a pickup point, drawn from the rng
+    pub const DELAY: Range<i32> = 200..400;
+    // the square it lies in, a ship only has to touch it
+    pub const SIZE: i32 = 10;
+    // life given back, but never more than the class has
+    pub const HEALTH: i32 = 25;
+
+    pub fn from_name(name: &str) -> Option<Pickup> {
+        match name {
+            "health" => Some(Pickup::Health),
+            "shield" => Some(Pickup::Shield),
+            "weapon" => Some(Pickup::Weapon),
+            "speed" => Some(Pickup::Speed),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pickup::Health => "health",
+            Pickup::Shield => "shield",
+            Pickup::Weapon => "weapon",
+            Pickup::Speed => "speed",
+        }
+    }
+
+    // ticks the effect lasts, zero for the ones given right away
+    pub fn duration(&self) -> i32 {
+        match self {
+            Pickup::Health => 0,
+            // nothing hurts while shielded
+            Pickup::Shield => 160,
+            // every weapon cools down twice as fast, and is reloaded
+            Pickup::Weapon => 320,
+            // flying faster and getting there quicker
+            Pickup::Speed => 240,
+        }
+    }
+
+    // how much faster the ship is while boosted
+    pub fn speed_boost() -> Flint {
+        Flint::from_num(1.5)
+    }
+
+    pub fn color(&self) -> RenderColor {
+        match self {
+            Pickup::Health => RenderColor::LIMEGREEN,
+            Pickup::Shield => RenderColor::LIGHTSKYBLUE,
+            Pickup::Weapon => RenderColor::ORANGE,
+            Pickup::Speed => RenderColor::VIOLET,
+        }
+    }
+}
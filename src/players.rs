@@ -0,0 +1,58 @@
+use crate::{arena::Handle, commands::Command, ships::ShipClass};
+
+// whoever is playing, kept apart from the ship they are flying,
+// so that they are still around while dead or without any ship at all
+#[derive(Clone)]
+pub struct Player {
+    pub pid: usize,
+    pub name: String,
+    // a kill scores one, a suicide costs one
+    pub score: i32,
+    pub kills: u32,
+    pub deaths: u32,
+    // having hurt someone that was killed by another player
+    pub assists: u32,
+    // none for as many lives as needed
+    pub lives: Option<u32>,
+    // none when playing for oneself
+    pub team: Option<usize>,
+    // what every ship the player is given is like
+    pub class: ShipClass,
+    // none while dead or out of lives
+    pub ship: Option<Handle>,
+    // the commands given this tick, whether there is a ship to carry them out or not
+    pub input: Vec<Command>,
+}
+
+impl Player {
+    pub fn new(pid: usize, class: ShipClass) -> Self {
+        Player {
+            pid,
+            name: format!("player {}", pid),
+            score: 0,
+            kills: 0,
+            deaths: 0,
+            assists: 0,
+            lives: None,
+            team: None,
+            class,
+            ship: None,
+            input: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    pub fn is_alive(&self) -> bool {
+        self.ship.is_some()
+    }
+
+    // someone else in the same team, never when playing for oneself
+    pub fn is_teammate(&self, other: &Player) -> bool {
+        self.pid != other.pid && self.team.is_some() && self.team == other.team
+    }
+
+    // nothing left to respawn with, can only watch the others
+    pub fn is_spectating(&self) -> bool {
+        self.ship.is_none() && self.lives == Some(0)
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    bytes::Reader,
+    checksum::{Checksum, Divergence},
+    commands::Command,
+};
+
+#[cfg(test)]
+mod tests;
+
+// everything needed to play a match again, since the world is deterministic
+// the seed, map, mode, players, their ships, and the commands for every tick are enough,
+// the checksums of every tick are kept to make sure it plays out the same way
+#[derive(Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub pid: u8,
+    pub players: u8,
+    pub map: String,
+    pub mode: String,
+    // the ship class of every player, ordered by pid
+    pub ships: Vec<String>,
+    pub ticks: Vec<Vec<Vec<Command>>>,
+    pub checksums: Vec<Checksum>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    #[cfg(feature = "graphics")]
+    NotFound,
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    Corrupt,
+    // the lengths are stored in a single byte, anything longer can't be recorded
+    TooLong(&'static str),
+}
+
+impl Replay {
+    // bump this whenever the format changes, or whenever the simulation changes
+    // in a way that makes old replays play out differently
+    pub const VERSION: u16 = 21;
+    pub const DIRECTORY: &'static str = "replays";
+    const EXTENSION: &'static str = "fjr";
+    const MAGIC: [u8; 4] = *b"FJRP";
+
+    pub fn new(
+        seed: u64,
+        pid: u8,
+        map: String,
+        mode: String,
+        ships: Vec<String>,
+    ) -> Result<Self, ReplayError> {
+        let players =
+            u8::try_from(ships.len()).map_err(|_| ReplayError::TooLong("list of ships"))?;
+
+        if map.len() > u8::MAX as usize {
+            return Err(ReplayError::TooLong("map name"));
+        }
+
+        if mode.len() > u8::MAX as usize {
+            return Err(ReplayError::TooLong("mode name"));
+        }
+
+        if ships.iter().any(|x| x.len() > u8::MAX as usize) {
+            return Err(ReplayError::TooLong("ship name"));
+        }
+
+        Ok(Replay {
+            seed,
+            pid,
+            players,
+            map,
+            mode,
+            ships,
+            ticks: Vec::new(),
+            checksums: Vec::new(),
+        })
+    }
+
+    pub fn record(&mut self, cmds: &[Vec<Command>]) -> Result<(), ReplayError> {
+        if cmds.iter().any(|x| x.len() > u8::MAX as usize) {
+            return Err(ReplayError::TooLong("list of commands"));
+        }
+
+        // the first couple of ticks have no commands at all,
+        // we store them as empty commands for every player instead
+        let mut tick = vec![Vec::new(); self.players as usize];
+
+        for (pid, cmds) in cmds.iter().enumerate().take(tick.len()) {
+            tick[pid].extend_from_slice(cmds);
+        }
+
+        self.ticks.push(tick);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "graphics")]
+    pub fn record_checksum(&mut self, checksum: Checksum) {
+        self.checksums.push(checksum);
+    }
+
+    // compares the checksum with the one recorded for the same tick,
+    // ticks without a recorded checksum are assumed to be fine
+    pub fn verify(&self, checksum: &Checksum) -> Option<Divergence> {
+        // the checksums are recorded in order
+        let idx = self
+            .checksums
+            .binary_search_by_key(&checksum.tick, |x| x.tick)
+            .ok()?;
+
+        self.checksums[idx].diverges(checksum)
+    }
+
+    // every length was checked when recording, so nothing gets cut off here
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&Replay::MAGIC);
+        buf.extend_from_slice(&Replay::VERSION.to_le_bytes());
+        buf.extend_from_slice(&self.seed.to_le_bytes());
+        buf.push(self.pid);
+        buf.push(self.players);
+        buf.push(self.map.len() as u8);
+        buf.extend_from_slice(self.map.as_bytes());
+        buf.push(self.mode.len() as u8);
+        buf.extend_from_slice(self.mode.as_bytes());
+
+        for ship in self.ships.iter() {
+            buf.push(ship.len() as u8);
+            buf.extend_from_slice(ship.as_bytes());
+        }
+
+        buf.extend_from_slice(&(self.ticks.len() as u64).to_le_bytes());
+
+        for tick in self.ticks.iter() {
+            for cmds in tick.iter() {
+                buf.push(cmds.len() as u8);
+                buf.extend(cmds.iter().map(|x| x.to_u8()));
+            }
+        }
+
+        buf.extend_from_slice(&(self.checksums.len() as u64).to_le_bytes());
+
+        for checksum in self.checksums.iter() {
+            checksum.encode(&mut buf);
+        }
+
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Replay, ReplayError> {
+        let mut reader = Reader::new(buf);
+
+        if reader.bytes(4) != Some(&Replay::MAGIC) {
+            return Err(ReplayError::InvalidMagic);
+        }
+
+        let version = reader.u16().ok_or(ReplayError::Corrupt)?;
+
+        if version != Replay::VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        Replay::decode_body(&mut reader).ok_or(ReplayError::Corrupt)
+    }
+
+    pub fn save(&self) -> Result<PathBuf, ReplayError> {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or_default();
+
+        let path = Path::new(Replay::DIRECTORY).join(format!("{}.{}", secs, Replay::EXTENSION));
+
+        fs::create_dir_all(Replay::DIRECTORY)?;
+        fs::write(&path, self.encode())?;
+
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, ReplayError> {
+        Replay::decode(&fs::read(path)?)
+    }
+
+    #[cfg(feature = "graphics")]
+    pub fn latest() -> Result<Replay, ReplayError> {
+        // the file names are timestamps, so the latest is the greatest
+        let path = fs::read_dir(Replay::DIRECTORY)?
+            .filter_map(|x| x.ok().map(|x| x.path()))
+            .filter(|x| x.extension().is_some_and(|x| x == Replay::EXTENSION))
+            .max()
+            .ok_or(ReplayError::NotFound)?;
+
+        Replay::load(&path)
+    }
+
+    fn decode_body(reader: &mut Reader) -> Option<Replay> {
+        let seed = reader.u64()?;
+        let pid = reader.u8()?;
+        let players = reader.u8()?;
+        let len = reader.u8()?;
+        let map = String::from_utf8(reader.bytes(len as usize)?.to_vec()).ok()?;
+        let len = reader.u8()?;
+        let mode = String::from_utf8(reader.bytes(len as usize)?.to_vec()).ok()?;
+        let mut ships = Vec::with_capacity(players as usize);
+
+        for _ in 0..players {
+            let len = reader.u8()?;
+            ships.push(String::from_utf8(reader.bytes(len as usize)?.to_vec()).ok()?);
+        }
+
+        let len = reader.u64()?;
+        let mut ticks = Vec::new();
+
+        for _ in 0..len {
+            let mut tick = Vec::with_capacity(players as usize);
+
+            for _ in 0..players {
+                let len = reader.u8()?;
+                let mut cmds = Vec::with_capacity(len as usize);
+
+                for _ in 0..len {
+                    cmds.push(Command::from_u8(reader.u8()?)?);
+                }
+
+                tick.push(cmds);
+            }
+
+            ticks.push(tick);
+        }
+
+        let len = reader.u64()?;
+        let mut checksums = Vec::new();
+
+        for _ in 0..len {
+            checksums.push(Checksum::decode(reader)?);
+        }
+
+        // anything left means we didn't read what was written
+        if !reader.is_empty() {
+            return None;
+        }
+
+        Some(Replay {
+            seed,
+            pid,
+            players,
+            map,
+            mode,
+            ships,
+            ticks,
+            checksums,
+        })
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(value: io::Error) -> Self {
+        ReplayError::Io(value)
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            #[cfg(feature = "graphics")]
+            ReplayError::NotFound => write!(f, "no replays found"),
+            ReplayError::InvalidMagic => write!(f, "not a replay"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "replay version {} is not supported, expected {}",
+                version,
+                Replay::VERSION
+            ),
+            ReplayError::Corrupt => write!(f, "replay is corrupt"),
+            ReplayError::TooLong(what) => write!(f, "{} is too long to fit in a replay", what),
+        }
+    }
+}
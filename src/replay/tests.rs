@@ -0,0 +1,131 @@
+use crate::{
+    checksum::{Checksum, Group},
+    commands::Command,
+};
+
+use super::{Replay, ReplayError};
+
+fn replay() -> Replay {
+    let mut replay = Replay::new(
+        1337,
+        1,
+        String::from("arena"),
+        String::from("teams"),
+        vec![String::from("fighter"), String::from("scout")],
+    )
+    .unwrap();
+
+    replay.record(&[]).unwrap();
+    replay
+        .record(&[vec![Command::Accelerate, Command::Shoot], vec![]])
+        .unwrap();
+    replay
+        .record(&[vec![], vec![Command::SwitchWeapon]])
+        .unwrap();
+
+    for tick in 0..3 {
+        replay.checksums.push(Checksum {
+            tick,
+            world: tick * 3,
+            players: vec![tick, tick + 1],
+            projectiles: Group::default(),
+            pickups: Group::default(),
+            exhausts: Group::default(),
+            explosions: Group::default(),
+            stars: Group {
+                count: 3,
+                sum: tick,
+                weighted: tick * 2,
+            },
+        });
+    }
+
+    replay
+}
+
+#[test]
+fn replays_survive_a_round_trip() {
+    let replay = replay();
+
+    // ticks without commands are recorded for every player
+    assert_eq!(replay.ticks[0], vec![Vec::new(), Vec::new()]);
+    assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+}
+
+#[test]
+fn other_files_are_not_replays() {
+    let mut buf = replay().encode();
+    buf[0] = b'X';
+
+    assert!(matches!(
+        Replay::decode(&buf),
+        Err(ReplayError::InvalidMagic)
+    ));
+    assert!(matches!(
+        Replay::decode(&[]),
+        Err(ReplayError::InvalidMagic)
+    ));
+}
+
+#[test]
+fn other_versions_are_not_supported() {
+    let mut buf = replay().encode();
+    buf[4..6].copy_from_slice(&(Replay::VERSION + 1).to_le_bytes());
+
+    assert!(matches!(
+        Replay::decode(&buf),
+        Err(ReplayError::UnsupportedVersion(version)) if version == Replay::VERSION + 1
+    ));
+}
+
+#[test]
+fn truncated_or_trailing_bytes_are_corrupt() {
+    let buf = replay().encode();
+
+    for len in 6..buf.len() {
+        assert!(matches!(
+            Replay::decode(&buf[..len]),
+            Err(ReplayError::Corrupt)
+        ));
+    }
+
+    let mut trailing = buf.clone();
+    trailing.push(0);
+
+    assert!(matches!(
+        Replay::decode(&trailing),
+        Err(ReplayError::Corrupt)
+    ));
+}
+
+#[test]
+fn lengths_that_do_not_fit_are_rejected() {
+    let new = |map: &str, ships: Vec<String>| {
+        Replay::new(0, 0, String::from(map), String::from("deathmatch"), ships)
+    };
+
+    // 'ö' takes two bytes, so this is exactly as long as a name can be
+    let longest = "ö".repeat(127) + "a";
+    let replay = new(&longest, vec![longest.clone()]).unwrap();
+    assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+
+    let longer = longest.clone() + "a";
+
+    assert!(matches!(new(&longer, vec![]), Err(ReplayError::TooLong(_))));
+    assert!(matches!(
+        new("arena", vec![longer]),
+        Err(ReplayError::TooLong(_))
+    ));
+    assert!(matches!(
+        new("arena", vec![String::from("scout"); 256]),
+        Err(ReplayError::TooLong(_))
+    ));
+
+    let mut replay = new("arena", vec![String::from("scout")]).unwrap();
+
+    assert!(matches!(
+        replay.record(&[vec![Command::Nop; 256]]),
+        Err(ReplayError::TooLong(_))
+    ));
+    assert!(replay.ticks.is_empty());
+}
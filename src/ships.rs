@@ -0,0 +1,286 @@
+use std::{fmt, fs, io};
+
+use toml::Value;
+
+use crate::{
+    components::logic::Motion,
+    map::{self, MapError},
+    math::Flint,
+    weapons::Weapon,
+};
+
+#[cfg(test)]
+mod tests;
+
+// the ship classes are read from ships.toml, like the maps everyone playing
+// must have the same file, a player picks one by name before the match starts
+//
+//     [[classes]]
+//     name = "fighter"
+//     width = 26
+//     height = 31
+//     max_speed = 8
+//     acceleration = 0.2
+//     rotation_speed = 0.08
+//     deceleration = 0.06
+//     life = 50
+//     shield = 20
+//     weapons = ["cannon", "missile"]
+pub struct ShipClasses {
+    pub classes: Vec<ShipClass>,
+}
+
+#[derive(Clone)]
+pub struct ShipClass {
+    pub name: String,
+    pub width: Flint,
+    pub height: Flint,
+    // standing still, with the stats of the class
+    pub motion: Motion,
+    pub life: Flint,
+    pub shield: Flint,
+    pub weapons: Vec<Weapon>,
+}
+
+#[derive(Debug)]
+pub enum ShipError {
+    Io(io::Error),
+    Parse { line: usize, reason: String },
+    Invalid(String),
+}
+
+impl ShipClasses {
+    pub const PATH: &'static str = "ships.toml";
+
+    const MAX_CLASSES: usize = u8::MAX as usize;
+    const MAX_NAME: usize = 32;
+    // the ships are pushed apart and collided by their size, anything much smaller
+    // or larger than what we have now has never been tried
+    const SIZES: (i32, i32) = (8, 64);
+    const MAX_SPEED: i32 = 16;
+    const MAX_LIFE: i32 = 1000;
+
+    pub fn load() -> Result<ShipClasses, ShipError> {
+        ShipClasses::parse(&fs::read_to_string(ShipClasses::PATH)?)
+    }
+
+    pub fn parse(content: &str) -> Result<ShipClasses, ShipError> {
+        let value = map::parse_toml(content)?;
+
+        let classes = match value.get("classes") {
+            Some(classes) => map::parse_array(classes, "classes")?
+                .iter()
+                .enumerate()
+                .map(|(idx, x)| parse_class(x, &format!("classes[{}]", idx)))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+
+        if classes.is_empty() || classes.len() > ShipClasses::MAX_CLASSES {
+            return Err(invalid(&format!(
+                "there must be 1 to {} classes, not {}",
+                ShipClasses::MAX_CLASSES,
+                classes.len()
+            )));
+        }
+
+        for (idx, class) in classes.iter().enumerate() {
+            if classes[..idx].iter().any(|x| x.name == class.name) {
+                return Err(invalid(&format!(
+                    "classes[{}].name {} is already taken",
+                    idx, class.name
+                )));
+            }
+        }
+
+        Ok(ShipClasses { classes })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ShipClass> {
+        self.classes.iter().find(|x| x.name == name)
+    }
+
+    // the class of every player ordered by pid, unknown names are an error since
+    // everyone must fly the same ships, anyone without a name gets the first class
+    pub fn pick(&self, names: &[String], players: usize) -> Result<Vec<ShipClass>, String> {
+        (0..players)
+            .map(|pid| match names.get(pid) {
+                Some(name) => self
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| format!("there is no ship class named {}", name)),
+                None => Ok(self.classes[0].clone()),
+            })
+            .collect()
+    }
+}
+
+impl From<io::Error> for ShipError {
+    fn from(value: io::Error) -> Self {
+        ShipError::Io(value)
+    }
+}
+
+impl From<MapError> for ShipError {
+    fn from(value: MapError) -> Self {
+        match value {
+            MapError::Io(e) => ShipError::Io(e),
+            MapError::Parse { line, reason } => ShipError::Parse { line, reason },
+            e => ShipError::Invalid(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ShipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShipError::Io(e) => write!(f, "{}", e),
+            ShipError::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+            ShipError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+fn parse_class(value: &Value, path: &str) -> Result<ShipClass, ShipError> {
+    // the name is sent over the network and stored in replays
+    let name = match map::get(value, "name", path)?.as_str() {
+        Some(name)
+            if !name.is_empty()
+                && name.len() <= ShipClasses::MAX_NAME
+                && name
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-') =>
+        {
+            name.to_owned()
+        }
+        _ => {
+            return Err(invalid(&format!(
+                "{}.name must be 1 to {} letters, digits, - or _",
+                path,
+                ShipClasses::MAX_NAME
+            )))
+        }
+    };
+
+    let number =
+        |key: &str| map::parse_number(map::get(value, key, path)?, &format!("{}.{}", path, key));
+
+    let (min, max) = ShipClasses::SIZES;
+    let width = number("width")?;
+    let height = number("height")?;
+
+    if [width, height].iter().any(|x| *x < min || *x > max) {
+        return Err(invalid(&format!(
+            "{}.width and {}.height must be between {} and {}",
+            path, path, min, max
+        )));
+    }
+
+    let motion = Motion {
+        speed: Flint::ZERO,
+        max_speed: number("max_speed")?,
+        acceleration: number("acceleration")?,
+        rotation_speed: number("rotation_speed")?,
+        deceleration: number("deceleration")?,
+    };
+
+    if motion.max_speed <= Flint::ZERO || motion.max_speed > ShipClasses::MAX_SPEED {
+        return Err(invalid(&format!(
+            "{}.max_speed must be greater than 0 and at most {}",
+            path,
+            ShipClasses::MAX_SPEED
+        )));
+    }
+
+    if motion.acceleration <= Flint::ZERO || motion.acceleration > motion.max_speed {
+        return Err(invalid(&format!(
+            "{}.acceleration must be greater than 0 and at most max_speed",
+            path
+        )));
+    }
+
+    // half a turn every tick is more than anyone can steer
+    if motion.rotation_speed <= Flint::ZERO || motion.rotation_speed > Flint::PI {
+        return Err(invalid(&format!(
+            "{}.rotation_speed must be greater than 0 and at most {}",
+            path,
+            Flint::PI
+        )));
+    }
+
+    if motion.deceleration < Flint::ZERO || motion.deceleration > motion.acceleration {
+        return Err(invalid(&format!(
+            "{}.deceleration must be between 0 and acceleration",
+            path
+        )));
+    }
+
+    let life = number("life")?;
+
+    if life <= Flint::ZERO || life > ShipClasses::MAX_LIFE {
+        return Err(invalid(&format!(
+            "{}.life must be greater than 0 and at most {}",
+            path,
+            ShipClasses::MAX_LIFE
+        )));
+    }
+
+    // taken before the life, none without
+    let shield = match value.get("shield") {
+        Some(shield) => map::parse_number(shield, &format!("{}.shield", path))?,
+        None => Flint::ZERO,
+    };
+
+    if shield < Flint::ZERO || shield > ShipClasses::MAX_LIFE {
+        return Err(invalid(&format!(
+            "{}.shield must be between 0 and {}",
+            path,
+            ShipClasses::MAX_LIFE
+        )));
+    }
+
+    let weapons_path = format!("{}.weapons", path);
+
+    let weapons = match value.get("weapons") {
+        Some(weapons) => map::parse_array(weapons, &weapons_path)?
+            .iter()
+            .enumerate()
+            .map(|(idx, x)| {
+                x.as_str().and_then(Weapon::from_name).ok_or_else(|| {
+                    invalid(&format!(
+                        "{}[{}] must be cannon, spread, missile or beam",
+                        weapons_path, idx
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        // unarmed, e.g. to simply fly around
+        None => Vec::new(),
+    };
+
+    // switching weapons goes through them in order, once each
+    for (idx, weapon) in weapons.iter().enumerate() {
+        if weapons[..idx].contains(weapon) {
+            return Err(invalid(&format!(
+                "{}[{}] {} is already listed",
+                weapons_path,
+                idx,
+                weapon.name()
+            )));
+        }
+    }
+
+    Ok(ShipClass {
+        name,
+        width,
+        height,
+        motion,
+        life,
+        shield,
+        weapons,
+    })
+}
+
+fn invalid(reason: &str) -> ShipError {
+    ShipError::Invalid(reason.to_owned())
+}
@@ -0,0 +1,239 @@
+use crate::{math::Flint, weapons::Weapon};
+
+use super::{ShipClasses, ShipError};
+
+const FIGHTER: &[(&str, &str)] = &[
+    ("name", "\"fighter\""),
+    ("width", "26"),
+    ("height", "31"),
+    ("max_speed", "8"),
+    ("acceleration", "0.2"),
+    ("rotation_speed", "0.08"),
+    ("deceleration", "0.06"),
+    ("life", "50"),
+    ("shield", "20"),
+    ("weapons", "[\"cannon\", \"missile\"]"),
+];
+
+// the fighter with the key set to the value, or left out without a value
+fn class(key: &str, value: Option<&str>) -> String {
+    let mut content = String::from("[[classes]]\n");
+
+    for (k, v) in FIGHTER {
+        match (*k == key, value) {
+            (false, _) => content.push_str(&format!("{} = {}\n", k, v)),
+            (true, Some(value)) => content.push_str(&format!("{} = {}\n", k, value)),
+            (true, None) => (),
+        }
+    }
+
+    content
+}
+
+fn fighter() -> String {
+    class("", None)
+}
+
+fn error(content: &str) -> String {
+    match ShipClasses::parse(content) {
+        Ok(_) => panic!("parsed invalid ship classes:\n{}", content),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn bundled_classes_are_valid() {
+    let classes = ShipClasses::load().expect("the ship classes");
+
+    assert!(classes.get("fighter").is_some());
+}
+
+#[test]
+fn a_class_is_read() {
+    let classes = ShipClasses::parse(&fighter()).unwrap();
+    let fighter = &classes.classes[0];
+
+    assert_eq!(fighter.name, "fighter");
+    assert_eq!(
+        (fighter.width, fighter.height),
+        (Flint::from_num(26), Flint::from_num(31))
+    );
+    assert_eq!(fighter.motion.speed, Flint::ZERO);
+    assert_eq!(fighter.motion.max_speed, Flint::from_num(8));
+    assert_eq!(fighter.motion.acceleration, Flint::from_num(0.2));
+    assert_eq!(fighter.motion.rotation_speed, Flint::from_num(0.08));
+    assert_eq!(fighter.motion.deceleration, Flint::from_num(0.06));
+    assert_eq!(fighter.life, Flint::from_num(50));
+    assert_eq!(fighter.shield, Flint::from_num(20));
+    assert_eq!(fighter.weapons, vec![Weapon::Cannon, Weapon::Missile]);
+}
+
+#[test]
+fn shield_and_weapons_are_optional() {
+    let classes = ShipClasses::parse(&class("shield", None)).unwrap();
+    assert_eq!(classes.classes[0].shield, Flint::ZERO);
+
+    let classes = ShipClasses::parse(&class("weapons", None)).unwrap();
+    assert!(classes.classes[0].weapons.is_empty());
+}
+
+#[test]
+fn missing_fields_are_rejected() {
+    for key in [
+        "name",
+        "width",
+        "height",
+        "max_speed",
+        "acceleration",
+        "rotation_speed",
+        "deceleration",
+        "life",
+    ] {
+        assert_eq!(
+            error(&class(key, None)),
+            format!("missing classes[0].{}", key)
+        );
+    }
+
+    assert_eq!(error(""), "there must be 1 to 255 classes, not 0");
+    assert_eq!(error("classes = 1"), "classes must be an array");
+}
+
+#[test]
+fn bad_fields_are_rejected() {
+    let cases = [
+        (
+            "name",
+            "\"\"",
+            "classes[0].name must be 1 to 32 letters, digits, - or _",
+        ),
+        (
+            "name",
+            "\"space ship\"",
+            "classes[0].name must be 1 to 32 letters, digits, - or _",
+        ),
+        (
+            "name",
+            "3",
+            "classes[0].name must be 1 to 32 letters, digits, - or _",
+        ),
+        ("width", "\"wide\"", "classes[0].width must be a number"),
+        (
+            "width",
+            "4",
+            "classes[0].width and classes[0].height must be between 8 and 64",
+        ),
+        (
+            "height",
+            "65",
+            "classes[0].width and classes[0].height must be between 8 and 64",
+        ),
+        (
+            "max_speed",
+            "0",
+            "classes[0].max_speed must be greater than 0 and at most 16",
+        ),
+        (
+            "max_speed",
+            "17",
+            "classes[0].max_speed must be greater than 0 and at most 16",
+        ),
+        (
+            "acceleration",
+            "9",
+            "classes[0].acceleration must be greater than 0 and at most max_speed",
+        ),
+        (
+            "rotation_speed",
+            "-0.1",
+            "classes[0].rotation_speed must be greater than 0",
+        ),
+        (
+            "rotation_speed",
+            "4",
+            "classes[0].rotation_speed must be greater than 0",
+        ),
+        (
+            "deceleration",
+            "0.5",
+            "classes[0].deceleration must be between 0 and acceleration",
+        ),
+        (
+            "life",
+            "0",
+            "classes[0].life must be greater than 0 and at most 1000",
+        ),
+        (
+            "shield",
+            "-1",
+            "classes[0].shield must be between 0 and 1000",
+        ),
+        (
+            "weapons",
+            "\"cannon\"",
+            "classes[0].weapons must be an array",
+        ),
+        (
+            "weapons",
+            "[\"laser\"]",
+            "classes[0].weapons[0] must be cannon, spread, missile or beam",
+        ),
+        (
+            "weapons",
+            "[\"beam\", \"beam\"]",
+            "classes[0].weapons[1] beam is already listed",
+        ),
+    ];
+
+    for (key, value, expected) in cases {
+        let error = error(&class(key, Some(value)));
+
+        assert!(
+            error.starts_with(expected),
+            "{} = {}: expected \"{}\", got \"{}\"",
+            key,
+            value,
+            expected,
+            error
+        );
+    }
+}
+
+#[test]
+fn names_are_unique() {
+    let content = fighter() + &class("width", Some("20"));
+
+    assert_eq!(error(&content), "classes[1].name fighter is already taken");
+}
+
+#[test]
+fn syntax_errors_point_out_the_line() {
+    match ShipClasses::parse("[[classes]]\nname = \"fighter\nwidth = 26\n") {
+        Err(ShipError::Parse { line, .. }) => assert_eq!(line, 2),
+        Err(e) => panic!("expected a syntax error, got {}", e),
+        Ok(_) => panic!("parsed ship classes with a syntax error"),
+    }
+}
+
+#[test]
+fn unknown_classes_are_not_picked() {
+    let content = fighter() + &class("name", Some("\"scout\""));
+    let classes = ShipClasses::parse(&content).unwrap();
+
+    let picked = classes
+        .pick(&[String::from("scout")], 3)
+        .unwrap()
+        .into_iter()
+        .map(|x| x.name)
+        .collect::<Vec<_>>();
+
+    // anyone without a name gets the first class
+    assert_eq!(picked, vec!["scout", "fighter", "fighter"]);
+
+    assert_eq!(
+        classes
+            .pick(&[String::from("fighter"), String::from("cruiser")], 2)
+            .err(),
+        Some(String::from("there is no ship class named cruiser"))
+    );
+}
@@ -1,13 +1,20 @@
 use fastrand::Rng;
 
 use crate::{
-    components::logic::{Body, Motion},
+    arena::Handle,
+    components::logic::{Body, Health, Motion},
     components::{
         logic::Shape,
-        render::{RenderColor, RenderRectangle, RenderTriangle, RenderVector2, Renderable},
+        render::{
+            RenderColor, RenderPolygon, RenderRectangle, RenderTriangle, RenderVector2, Renderable,
+        },
     },
-    entities::{Particle, Projectile, Star, Triship},
-    math::{Flint, FlintRectangle, FlintTriangle, FlintVec2},
+    entities::{Entities, Kind, Star},
+    math::{Directions, Flint, FlintPolygon, FlintRectangle, FlintTriangle, FlintVec2},
+    modes::Team,
+    pickups::Pickup,
+    ships::ShipClass,
+    weapons::{Loadout, Weapon},
 };
 
 pub struct Spawner;
@@ -17,48 +24,64 @@ impl Spawner {
         Self {}
     }
 
-    pub fn spawn_triship(&self, centroid: FlintVec2, direction: FlintVec2) -> Triship {
+    pub fn spawn_triship(
+        &self,
+        entities: &mut Entities,
+        class: &ShipClass,
+        centroid: FlintVec2,
+        direction: FlintVec2,
+        pid: usize,
+    ) -> Handle {
         let shape = Shape {
-            shape: FlintTriangle::from_centroid(centroid, Flint::from_num(26), Flint::from_num(31)),
+            shape: FlintTriangle::from_centroid(centroid, class.width, class.height),
             direction,
         };
 
         let body = Body::<FlintTriangle>::new(shape);
 
+        // told apart by the color of the team, when in one
+        let color = match entities.players.get(pid).and_then(|x| x.team) {
+            Some(team) => Team::color(team),
+            None => RenderColor::DIMGRAY,
+        };
+
         let render = Renderable::<RenderTriangle>::new(
-            RenderColor::DIMGRAY,
+            color,
             body.live.shape.into(),
             direction.radians().to_num(),
         );
 
-        let motion = Motion {
-            speed: Flint::from_num(0),
-            max_speed: Flint::from_num(8),
-            acceleration: Flint::from_num(0.2),
-            rotation_speed: Flint::from_num(0.08),
-            // rotation_speed: Flint::from_num(0.02),
-        };
-
-        Triship {
-            body,
-            motion,
-            render,
-            dead: false,
-            life: Flint::from_num(50),
-        }
+        let handle = entities.spawn(Kind::Triship);
+
+        entities.triangles.insert(handle, body);
+        entities.motions.insert(handle, class.motion.clone());
+        entities.render_triangles.insert(handle, render);
+        entities
+            .healths
+            .insert(handle, Health::new(class.life, class.shield));
+        entities.effects.insert(handle, Vec::new());
+        entities.owners.insert(handle, pid);
+        entities
+            .loadouts
+            .insert(handle, Loadout::new(&class.weapons));
+
+        handle
     }
 
     pub fn spawn_projectile(
         &self,
+        entities: &mut Entities,
+        weapon: Weapon,
         centroid: FlintVec2,
         direction: FlintVec2,
         // render_centroid: RenderVector2,
         relative_speed: Flint,
         pid: usize,
-    ) -> Projectile {
-        // TODO: should be height 2 and width 1?
+    ) -> Handle {
+        let stats = weapon.stats();
+
         let shape = Shape {
-            shape: FlintRectangle::from_centroid(centroid, Flint::from_num(2), Flint::from_num(1)),
+            shape: FlintRectangle::from_centroid(centroid, stats.length, stats.width),
             direction,
         };
 
@@ -71,34 +94,97 @@ impl Spawner {
         // rec.y = render_centroid.y;
 
         let render = Renderable::<RenderRectangle>::new(
-            RenderColor::LIGHTYELLOW,
+            stats.color,
             rec,
             body.live.direction.radians().to_num(),
         );
 
-        let speed = Flint::from_num(14);
-
         let motion = Motion {
             // projectile will travel at base speed relative to entity that fired it,
-            // if entity speed is 5 then the speed of the projectile will be 14 + 5
-            speed: speed + relative_speed,
+            // if entity speed is 5 then the speed of a cannon projectile will be 14 + 5
+            speed: stats.speed + relative_speed,
             max_speed: Flint::MAX,
-            acceleration: speed,
+            acceleration: stats.speed,
             rotation_speed: Flint::ZERO,
+            deceleration: Flint::ZERO,
         };
 
-        Projectile {
-            body,
-            motion,
-            render,
-            dead: false,
-            pid,
-            dmg: Flint::from_num(1),
+        let handle = entities.spawn(Kind::Projectile);
+
+        entities.rectangles.insert(handle, body);
+        entities.motions.insert(handle, motion);
+        entities.render_rectangles.insert(handle, render);
+        entities.owners.insert(handle, pid);
+        entities.damages.insert(handle, stats.damage);
+        entities.lifetimes.insert(handle, stats.lifetime);
+
+        if stats.turn_rate > Flint::ZERO {
+            entities.homings.insert(handle, stats.turn_rate);
         }
+
+        handle
+    }
+
+    pub fn spawn_obstacle(&self, entities: &mut Entities, points: &[FlintVec2]) -> Handle {
+        let shape = Shape {
+            shape: FlintPolygon {
+                points: points.to_vec(),
+            },
+            direction: Directions::EAST,
+        };
+
+        let body = Body::<FlintPolygon>::new(shape);
+
+        let render = Renderable::<RenderPolygon>::new(
+            RenderColor::LIGHTGRAY,
+            (&body.live.shape).into(),
+            body.live.direction.radians().to_num(),
+        );
+
+        let handle = entities.spawn(Kind::Obstacle);
+
+        entities.polygons.insert(handle, body);
+        entities.render_polygons.insert(handle, render);
+
+        handle
     }
 
+    pub fn spawn_pickup(
+        &self,
+        entities: &mut Entities,
+        pickup: Pickup,
+        centroid: FlintVec2,
+        idx: usize,
+    ) -> Handle {
+        let size = Flint::from_num(Pickup::SIZE);
+
+        let shape = Shape {
+            shape: FlintRectangle::from_centroid(centroid, size, size),
+            direction: Directions::EAST,
+        };
+
+        let body = Body::<FlintRectangle>::new(shape);
+
+        let render = Renderable::<RenderRectangle>::new(
+            pickup.color(),
+            body.live.shape.into(),
+            body.live.direction.radians().to_num(),
+        );
+
+        let handle = entities.spawn(Kind::Pickup);
+
+        entities.rectangles.insert(handle, body);
+        entities.render_rectangles.insert(handle, render);
+        entities.pickups.insert(handle, (pickup, idx));
+
+        handle
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn_particle(
         &self,
+        entities: &mut Entities,
+        kind: Kind,
         centroid: FlintVec2,
         render_centroid: RenderVector2,
         direction: FlintVec2,
@@ -107,7 +193,7 @@ impl Spawner {
         lifetime: i32,
         color: RenderColor,
         amount: u8, // TODO: naming...
-    ) -> Particle {
+    ) -> Handle {
         let shape = Shape {
             shape: centroid,
             direction,
@@ -125,31 +211,34 @@ impl Spawner {
             max_speed: Flint::MAX,
             acceleration: Flint::ZERO,
             rotation_speed: Flint::ZERO,
+            deceleration: Flint::ZERO,
         };
 
         let render =
             Renderable::<RenderVector2>::new(color, render_centroid, direction.radians().to_num());
 
-        Particle {
-            body,
-            motion,
-            lifetime,
-            render,
-            dead: false,
-            amount,
-        }
+        let handle = entities.spawn(kind);
+
+        entities.points.insert(handle, body);
+        entities.motions.insert(handle, motion);
+        entities.render_points.insert(handle, render);
+        entities.lifetimes.insert(handle, lifetime);
+        entities.fades.insert(handle, amount);
+
+        handle
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn_exhaust_particles(
         &self,
+        entities: &mut Entities,
         centroid: FlintVec2,
         render_centroid: RenderVector2,
         direction: FlintVec2,
         min_speed: Flint,
         relative_speed: Flint,
         rng: &mut Rng,
-    ) -> Vec<Particle> {
-        let mut particles = Vec::new();
+    ) {
         // 18 particles
 
         // 0 1 2 3 4
@@ -170,10 +259,10 @@ impl Spawner {
 
         let neg = -Flint::from_num(v.len() / 2);
 
-        for i in 0..v.len() {
+        for (i, count) in v.into_iter().enumerate() {
             let idx = Flint::from_num(i);
 
-            for j in 0..v[i] {
+            for j in 0..count {
                 let pos = FlintVec2::new(neg + idx, Flint::ZERO).rotated(rad90, zero);
                 let c = centroid + pos;
                 let rc = render_centroid - RenderVector2::new(pos.x.to_num(), pos.y.to_num());
@@ -181,7 +270,10 @@ impl Spawner {
                 let lifetime = rng.i32(2..6) + j;
                 let color = RenderColor::LIGHTSKYBLUE;
                 let amount = 0;
-                let p = self.spawn_particle(
+
+                self.spawn_particle(
+                    entities,
+                    Kind::Exhaust,
                     c,
                     rc,
                     direction,
@@ -191,14 +283,11 @@ impl Spawner {
                     color,
                     amount,
                 );
-
-                particles.push(p);
             }
         }
-
-        particles
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn_star(
         &self,
         centroid: FlintVec2,
@@ -239,12 +328,11 @@ impl Spawner {
 
     pub fn spawn_explosion_particles(
         &self,
+        entities: &mut Entities,
         centroid: FlintVec2,
         amount: u8,
         rng: &mut Rng,
-    ) -> Vec<Particle> {
-        let mut explosion = Vec::new();
-
+    ) {
         for _ in 0..amount {
             let degrees = rng.i32(0..360);
             let radians = degrees * (Flint::PI / 180);
@@ -262,7 +350,10 @@ impl Spawner {
             );
             let amount = rng.u8(24..56);
             let lifetime = (color.a / amount) as i32;
-            let p = self.spawn_particle(
+
+            self.spawn_particle(
+                entities,
+                Kind::Explosion,
                 centroid,
                 rc,
                 direction,
@@ -272,10 +363,6 @@ impl Spawner {
                 color,
                 amount,
             );
-
-            explosion.push(p);
         }
-
-        explosion
     }
 }
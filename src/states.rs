@@ -1,14 +1,25 @@
+#[cfg(feature = "graphics")]
 mod game;
+#[cfg(feature = "graphics")]
 mod manager;
+#[cfg(feature = "graphics")]
 mod menu;
+#[cfg(feature = "graphics")]
+mod replay;
 
+#[cfg(feature = "graphics")]
 pub use self::game::GameState;
+#[cfg(feature = "graphics")]
 pub use self::manager::StateManager;
+#[cfg(feature = "graphics")]
 pub use self::menu::MenuState;
+#[cfg(feature = "graphics")]
+pub use self::replay::ReplayState;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 pub enum State {
     None,
     Menu,
     Game,
+    Replay,
 }
@@ -1,4 +1,7 @@
-use std::collections::{BTreeSet, HashMap};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+};
 
 use raylib::prelude::*;
 
@@ -6,12 +9,17 @@ use crate::{
     bus::Bus,
     commands::Command,
     engine::Engine,
-    math::{Directions, Flint, FlintVec2},
+    map::Map,
     messages::{
-        EngineMessage, EngineRequestMessage, Message, RequestMessage, Sender, StateRequestMessage,
+        EngineMessage, EngineRequestMessage, LogicMessage, Message, NetMessage, NetRequestMessage,
+        RequestMessage, Sender, StateRequestMessage, WorldMessage,
     },
     misc::RaylibRenderHandle,
-    world::{Map, Spawn, World},
+    modes::{Ending, Modes, Team},
+    players::Player,
+    replay::Replay,
+    ships::ShipClasses,
+    world::World,
 };
 
 use super::State;
@@ -27,13 +35,35 @@ pub struct GameState {
     cmds: Vec<Command>,
     rcmds: HashMap<u64, ReceivedCommands>,
     empty: Vec<Vec<Command>>,
+    replay: Option<Replay>,
+    // the map, mode and ship class we picked ourselves, unless the session decides otherwise
+    map: String,
+    mode: String,
+    ship: String,
+    error: Option<String>,
+    desync: Option<String>,
+    // what just happened to us, like a pickup or a hit, and the tick it did
+    notice: Option<(String, Color, u64)>,
+    // the world stops once the match is over, showing the scoreboard instead
+    ending: Option<Ending>,
     debug: bool,
     paused: bool,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 enum Action {
-    Initialize { pid: u8, players: u8, seed: u64 },
+    Initialize {
+        pid: u8,
+        players: u8,
+        seed: u64,
+        map: Option<String>,
+        mode: Option<String>,
+        ships: Option<Vec<String>>,
+    },
+    GetMap,
+    GetMode,
+    GetShip,
+    GetSession,
     GotoMenu,
     Command(Command),
     GetDebug,
@@ -48,46 +78,11 @@ struct ReceivedCommands {
 }
 
 impl GameState {
+    // at 16 tps a delay of 3 ticks will be (1000 ms / 16) * 3 = 187.5 ms,
+    // which is the time our commands have to reach the other players
     const DELAY_TICKS: u64 = 3;
-
-    pub fn simulate_recv_cmds(&mut self) {
-        // TODO: this is temporary until we can get the networking implemented,
-        // this will simulate a delay of 3 ticks, which at 16 tps will be
-        // (1000 ms / 16) * 3 = 187.5 ms
-
-        let rcmds = self
-            .rcmds
-            .entry(self.tick + GameState::DELAY_TICKS)
-            .or_insert_with(|| ReceivedCommands {
-                ready: false,
-                received: 0,
-                commands: vec![Vec::new(); self.players as usize],
-            });
-
-        let mut rotated_left = false;
-        let mut rotated_right = false;
-
-        for i in 0..self.players {
-            let cmds = &mut rcmds.commands[i as usize];
-
-            if i == self.pid {
-                for cmd in self.cmds.drain(..) {
-                    cmds.push(cmd);
-                }
-            } else if !rotated_left {
-                rotated_left = true;
-                cmds.push(Command::RotateLeft);
-            } else if !rotated_right {
-                rotated_right = true;
-                cmds.push(Command::RotateRight);
-            } else {
-                cmds.push(Command::Nop);
-            }
-
-            rcmds.received += 1;
-            rcmds.ready = rcmds.received == self.players;
-        }
-    }
+    // how long it says what just happened to us
+    const NOTICE_TICKS: u64 = 32;
 
     pub fn new() -> Self {
         GameState {
@@ -101,6 +96,14 @@ impl GameState {
             cmds: Vec::new(),
             rcmds: HashMap::new(),
             empty: Vec::new(),
+            replay: None,
+            map: String::new(),
+            mode: String::new(),
+            ship: String::new(),
+            error: None,
+            desync: None,
+            notice: None,
+            ending: None,
             debug: false,
             paused: false,
         }
@@ -108,21 +111,24 @@ impl GameState {
 
     pub fn init(&mut self) {
         self.actions.insert(Action::GetDebug);
+        self.actions.insert(Action::GetMap);
+        self.actions.insert(Action::GetMode);
+        self.actions.insert(Action::GetShip);
 
-        // TODO: we need to get the pid, pids, and seed
-        // this should be fetched from somewhere,
-        // when networking is implemented
-        self.actions.insert(Action::Initialize {
-            pid: fastrand::u8(0..4),
-            players: 4,
-            seed: fastrand::u64(0..1024),
-        });
+        // the pid, players, seed, map, mode, and ships are decided by the session,
+        // or by ourselves if we're playing alone
+        self.actions.insert(Action::GetSession);
     }
 
     pub fn exit(&mut self) {
         self.world.exit();
         self.actions.clear();
         self.cmds.clear();
+        self.replay = None;
+        self.error = None;
+        self.desync = None;
+        self.notice = None;
+        self.ending = None;
         self.rcmds.clear();
         self.pid = 0;
         self.players = 0;
@@ -137,6 +143,15 @@ impl GameState {
             self.actions.insert(Action::GotoMenu);
         }
 
+        // nothing left to do but to look at the scoreboard
+        if self.ending.is_some() {
+            if rh.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                self.actions.insert(Action::GotoMenu);
+            }
+
+            return;
+        }
+
         if rh.is_key_pressed(KeyboardKey::KEY_D) {
             self.actions.insert(Action::ToggleDebug);
         }
@@ -144,7 +159,6 @@ impl GameState {
             self.actions.insert(Action::TogglePause);
         }
 
-        // TODO: make sure we don't insert duplicate commands
         if self.paused {
             return;
         }
@@ -169,6 +183,10 @@ impl GameState {
             self.actions.insert(Action::Command(Command::Shoot));
         }
 
+        if rh.is_key_pressed(KeyboardKey::KEY_Q) {
+            self.actions.insert(Action::Command(Command::SwitchWeapon));
+        }
+
         if rh.is_key_pressed(KeyboardKey::KEY_LEFT_CONTROL) {
             self.actions.insert(Action::Command(Command::Explode));
         }
@@ -181,11 +199,16 @@ impl GameState {
     pub fn update(&mut self, bus: &mut Bus) {
         self.action(bus);
 
-        if !self.init || self.paused {
+        // everyone playing stops at the same tick, since they all agree on when it's over
+        if !self.init || self.paused || self.ending.is_some() {
             return;
         }
 
-        self.simulate_recv_cmds();
+        // if we were stalling last tick, our commands for this tick
+        // have already been sent
+        if !self.stalling {
+            self.send_cmds(bus);
+        }
 
         let cmds = match self.rcmds.get_mut(&self.tick) {
             // once we have received all commands and ready is set,
@@ -202,27 +225,185 @@ impl GameState {
             }
         };
 
-        self.world.update(&cmds, bus.with_sender(Sender::World));
+        self.world.update(cmds, bus.with_sender(Sender::World));
+
+        if let Some(Err(e)) = self.replay.as_mut().map(|x| x.record(cmds)) {
+            println!("WARN: GAME: no longer recording the replay: {}", e);
+            self.replay = None;
+        }
+
+        self.rcmds.remove(&self.tick);
         self.tick += 1;
 
-        self.cmds.clear();
+        self.stalling = false;
     }
 
     pub fn message(&mut self, sender: &Sender, msg: &Message) {
-        // TODO: once we get all commands for the current tick,
-        // set stalling to false
-
         self.world.message(sender, msg);
 
         match msg {
             Message::Engine(EngineMessage::DebugGet(debug) | EngineMessage::DebugSet(debug)) => {
                 self.debug = *debug;
             }
-            _ => return,
+            Message::Engine(EngineMessage::MapGet(map)) => {
+                self.map = map.clone();
+            }
+            Message::Engine(EngineMessage::ModeGet(mode)) => {
+                self.mode = mode.clone();
+            }
+            Message::Engine(EngineMessage::ShipGet(ship)) => {
+                self.ship = ship.clone();
+            }
+            Message::Net(NetMessage::SessionGet {
+                pid,
+                players,
+                seed,
+                map,
+                mode,
+                ships,
+            }) => {
+                // commands from the other players might arrive before we have initialized,
+                // so we need to know how many players there are right away
+                self.pid = *pid;
+                self.players = *players;
+
+                self.actions.insert(Action::Initialize {
+                    pid: *pid,
+                    players: *players,
+                    seed: *seed,
+                    map: map.clone(),
+                    mode: mode.clone(),
+                    ships: ships.clone(),
+                });
+            }
+            Message::Net(NetMessage::CommandsReceived { pid, tick, cmds }) => {
+                self.recv_cmds(*pid, *tick, cmds);
+            }
+            Message::Net(NetMessage::Failed(reason)) => {
+                self.error = Some(format!("Network failed: {}", reason));
+            }
+            Message::World(WorldMessage::ChecksumCalculated(checksum)) => {
+                if let Some(replay) = self.replay.as_mut() {
+                    replay.record_checksum(checksum.clone());
+                }
+            }
+            Message::World(WorldMessage::MatchOver(ending)) => {
+                println!("INFO: GAME: {}", ending);
+
+                self.ending = Some(*ending);
+            }
+            Message::Logic(LogicMessage::PickupCollected { pid, pickup, .. })
+                if *pid == self.pid as usize =>
+            {
+                let text = format!("picked up {}", pickup.name());
+                self.notice = Some((text, Color::from(pickup.color()), self.tick));
+            }
+            Message::Logic(LogicMessage::Damaged {
+                pid,
+                attacker,
+                damage,
+            }) => {
+                let pid = *pid as u8;
+                let attacker = attacker.map(|x| x as u8);
+
+                // only what we took and what we dealt
+                let notice = if pid == self.pid {
+                    match attacker {
+                        Some(attacker) if attacker != pid => {
+                            format!("hit by player {} for {}", attacker, damage)
+                        }
+                        _ => format!("crashed for {}", damage),
+                    }
+                } else if attacker == Some(self.pid) {
+                    format!("hit player {} for {}", pid, damage)
+                } else {
+                    return;
+                };
+
+                self.notice = Some((notice, Color::WHITESMOKE, self.tick));
+            }
+            Message::Logic(LogicMessage::Killed {
+                pid,
+                killer,
+                assists,
+            }) => {
+                let pid = *pid as u8;
+                let killer = killer.map(|x| x as u8);
+
+                let notice = if pid == self.pid {
+                    match killer {
+                        Some(killer) => format!("killed by player {}", killer),
+                        None => String::from("died by yourself"),
+                    }
+                } else if killer == Some(self.pid) {
+                    format!("killed player {}", pid)
+                } else if assists.contains(&(self.pid as usize)) {
+                    format!("assisted killing player {}", pid)
+                } else {
+                    return;
+                };
+
+                self.notice = Some((notice, Color::WHITESMOKE, self.tick));
+            }
+            Message::Net(NetMessage::Desynced {
+                pid,
+                tick,
+                divergence,
+            }) => {
+                let desync = format!(
+                    "desynced from player {} at tick {}, {} diverged",
+                    pid, tick, divergence
+                );
+
+                println!("WARN: GAME: {}", desync);
+
+                // the first desync is the interesting one
+                self.desync.get_or_insert(desync);
+            }
+            _ => (),
         }
     }
 
+    fn send_cmds(&mut self, bus: &mut Bus) {
+        // our own commands will be executed DELAY_TICKS from now,
+        // the same tick the other players will execute them
+        let tick = self.tick + GameState::DELAY_TICKS;
+        let cmds: Vec<Command> = self.cmds.drain(..).collect();
+
+        self.recv_cmds(self.pid, tick, &cmds);
+
+        bus.send(Message::Request(RequestMessage::Net(
+            NetRequestMessage::SendCommands { tick, cmds },
+        )));
+    }
+
+    fn recv_cmds(&mut self, pid: u8, tick: u64, cmds: &[Command]) {
+        if pid >= self.players {
+            return;
+        }
+
+        let rcmds = self.rcmds.entry(tick).or_insert_with(|| ReceivedCommands {
+            ready: false,
+            received: 0,
+            commands: vec![Vec::new(); self.players as usize],
+        });
+
+        rcmds.commands[pid as usize].extend_from_slice(cmds);
+        rcmds.received += 1;
+        rcmds.ready = rcmds.received == self.players;
+    }
+
     pub fn draw(&mut self, rrh: &mut RaylibRenderHandle, delta: f32) {
+        if let Some(error) = &self.error {
+            rrh.draw_text(
+                error,
+                Engine::WIDTH / 2 - raylib::text::measure_text(error, 10) / 2,
+                Engine::HEIGHT / 2,
+                10,
+                Engine::DEBUG_TEXT_COLOR,
+            );
+        }
+
         if !self.init {
             return;
         }
@@ -251,71 +432,205 @@ impl GameState {
                 Color::WHITESMOKE,
             );
         }
+
+        if let Some((text, color, tick)) = &self.notice {
+            if self.tick < tick + GameState::NOTICE_TICKS {
+                rrh.draw_text(
+                    text,
+                    Engine::WIDTH / 2 - raylib::text::measure_text(text, 10) / 2,
+                    Engine::HEIGHT / 2 - 40,
+                    10,
+                    *color,
+                );
+            }
+        }
+
+        if let Some(ending) = &self.ending {
+            self.draw_scoreboard(rrh, ending);
+        }
+
+        if let Some(desync) = &self.desync {
+            rrh.draw_text(
+                desync,
+                Engine::WIDTH / 2 - raylib::text::measure_text(desync, 10) / 2,
+                4,
+                10,
+                Color::RED,
+            );
+        }
+    }
+
+    // everyone ordered by score, then by kills, and by pid when that's the same too,
+    // when playing in teams they are put together under their team, the best team first
+    fn draw_scoreboard(&self, rrh: &mut RaylibRenderHandle, ending: &Ending) {
+        let all = &self.world.entities().players;
+        let mut players: Vec<&Player> = all.iter().collect();
+        players.sort_by_key(|x| {
+            let team = x.team.map(|team| (Reverse(Team::score(team, all)), team));
+            (team, Reverse(x.score), Reverse(x.kills), x.pid)
+        });
+
+        let mut lines = vec![ending.to_string(), String::new()];
+
+        for (idx, x) in players.iter().enumerate() {
+            if let Some(team) = x
+                .team
+                .filter(|_| idx == 0 || players[idx - 1].team != x.team)
+            {
+                lines.push(format!(
+                    "{} team   score {}",
+                    Team::name(team),
+                    Team::score(team, all)
+                ));
+            }
+
+            lines.push(format!(
+                "{}   score {}   {} kills   {} deaths   {} assists",
+                x.name, x.score, x.kills, x.deaths, x.assists
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("enter - back to menu"));
+
+        let top = Engine::HEIGHT / 2 - lines.len() as i32 * 12 / 2;
+
+        rrh.draw_rectangle(
+            0,
+            0,
+            Engine::WIDTH,
+            Engine::HEIGHT,
+            Color::new(0, 0, 0, 160),
+        );
+
+        for (idx, line) in lines.iter().enumerate() {
+            rrh.draw_text(
+                line,
+                Engine::WIDTH / 2 - raylib::text::measure_text(line, 10) / 2,
+                top + idx as i32 * 12,
+                10,
+                Color::WHITESMOKE,
+            );
+        }
     }
 
     fn action(&mut self, bus: &mut Bus) {
         while let Some(action) = self.actions.pop_first() {
             match action {
-                Action::Initialize { pid, players, seed } => {
-                    // TODO: map should be configurable
-                    let width = Flint::from_num(800);
-                    let height = Flint::from_num(600);
-                    let map = Map {
-                        // four spawn points for this map
-                        spawns: vec![
-                            // top left
-                            Spawn {
-                                point: FlintVec2::new(Flint::from_num(100), Flint::from_num(100)),
-                                direction: Directions::WEST,
-                            },
-                            // top right
-                            Spawn {
-                                point: FlintVec2::new(
-                                    width - Flint::from_num(100),
-                                    Flint::from_num(100),
-                                ),
-                                direction: Directions::SOUTH,
-                            },
-                            // bottom left
-                            Spawn {
-                                point: FlintVec2::new(
-                                    Flint::from_num(100),
-                                    height - Flint::from_num(100),
-                                ),
-                                direction: Directions::EAST,
-                            },
-                            // bottom right
-                            Spawn {
-                                point: FlintVec2::new(
-                                    width - Flint::from_num(100),
-                                    height - Flint::from_num(100),
-                                ),
-                                direction: Directions::NORTH,
-                            },
-                        ],
-                        width,
-                        height,
-                        width_i32: width.to_num(),
-                        height_i32: height.to_num(),
-                        width_f32: width.to_num(),
-                        height_f32: height.to_num(),
+                Action::Initialize {
+                    pid,
+                    players,
+                    seed,
+                    map,
+                    mode,
+                    ships,
+                } => {
+                    // the map, mode and ship have been picked by the time the session arrives,
+                    // they were all requested at the same time
+                    let name = map.unwrap_or_else(|| self.map.clone());
+
+                    let map = match Map::load(&name) {
+                        Ok(map) => map,
+                        Err(e) => {
+                            self.error = Some(format!("Failed to load map {}: {}", name, e));
+                            continue;
+                        }
+                    };
+
+                    if players > map.max_players() {
+                        self.error = Some(format!(
+                            "Map {} has room for {} players, not {}",
+                            name,
+                            map.max_players(),
+                            players
+                        ));
+                        continue;
+                    }
+
+                    let ships = ships.unwrap_or_else(|| vec![self.ship.clone()]);
+
+                    let classes = match ShipClasses::load()
+                        .map_err(|e| e.to_string())
+                        .and_then(|x| x.pick(&ships, players as usize))
+                    {
+                        Ok(classes) => classes,
+                        Err(e) => {
+                            self.error = Some(format!("Failed to load ship classes: {}", e));
+                            continue;
+                        }
                     };
 
-                    self.world.init(pid as usize, players as usize, seed, map);
+                    let mode = mode.unwrap_or_else(|| self.mode.clone());
+
+                    let mode = match Modes::load()
+                        .map_err(|e| e.to_string())
+                        .and_then(|x| x.pick(&mode))
+                    {
+                        Ok(mode) => mode,
+                        Err(e) => {
+                            self.error = Some(format!("Failed to load modes: {}", e));
+                            continue;
+                        }
+                    };
+
+                    // not being able to record is no reason to not play
+                    self.replay =
+                        match Replay::new(seed, pid, map.name.clone(), mode.name.clone(), ships) {
+                            Ok(replay) => Some(replay),
+                            Err(e) => {
+                                println!("WARN: GAME: not recording a replay: {}", e);
+                                None
+                            }
+                        };
+                    self.world.init(pid as usize, classes, seed, map, mode);
 
                     self.pid = pid;
                     self.players = players;
 
                     self.init = true;
                 }
+                Action::GetMap => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::GetMap,
+                    )));
+                }
+                Action::GetMode => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::GetMode,
+                    )));
+                }
+                Action::GetShip => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::GetShip,
+                    )));
+                }
+                Action::GetSession => {
+                    bus.send(Message::Request(RequestMessage::Net(
+                        NetRequestMessage::GetSession,
+                    )));
+                }
                 Action::GotoMenu => {
+                    if let Some(replay) = self.replay.take() {
+                        match replay.save() {
+                            Ok(path) => println!("Saved replay to {}", path.display()),
+                            Err(e) => println!("Failed to save replay: {}", e),
+                        }
+                    }
+
+                    bus.send(Message::Request(RequestMessage::Net(
+                        NetRequestMessage::Disconnect,
+                    )));
+
                     bus.send(Message::Request(RequestMessage::State(
                         StateRequestMessage::SetState(State::Menu),
                     )));
                 }
                 Action::Command(cmd) => {
-                    // TODO: these should be sent via net
-                    self.cmds.push(cmd);
+                    // while stalling the same keys keep being held for the same tick,
+                    // it still only happens once
+                    if !self.cmds.contains(&cmd) {
+                        self.cmds.push(cmd);
+                    }
                 }
                 Action::GetDebug => {
                     bus.send(Message::Request(RequestMessage::Engine(
@@ -5,11 +5,11 @@ use raylib::prelude::*;
 use crate::{
     bus::Bus,
     engine::Engine,
-    messages::{Message, RequestMessage, Sender, StateMessage, StateRequestMessage},
+    messages::{Message, RequestMessage, Sender, StateRequestMessage},
     misc::RaylibRenderHandle,
 };
 
-use super::{GameState, MenuState, State};
+use super::{GameState, MenuState, ReplayState, State};
 
 pub struct StateManager {
     current: State,
@@ -22,6 +22,7 @@ pub struct StateManager {
 struct States {
     menu: MenuState,
     game: GameState,
+    replay: ReplayState,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
@@ -39,6 +40,7 @@ impl StateManager {
             states: States {
                 menu: MenuState::new(),
                 game: GameState::new(),
+                replay: ReplayState::new(),
             },
             actions: BTreeSet::new(),
             debug_text: text.to_owned(),
@@ -51,16 +53,18 @@ impl StateManager {
             State::None => (),
             State::Menu => self.states.menu.input(rh),
             State::Game => self.states.game.input(rh),
+            State::Replay => self.states.replay.input(rh),
         }
     }
 
     pub fn update(&mut self, bus: &mut Bus) {
-        self.action(bus);
+        self.action();
 
         match self.current {
             State::None => (),
             State::Menu => self.states.menu.update(bus.with_sender(Sender::Menu)),
             State::Game => self.states.game.update(bus.with_sender(Sender::Game)),
+            State::Replay => self.states.replay.update(bus.with_sender(Sender::Replay)),
         }
     }
 
@@ -69,6 +73,7 @@ impl StateManager {
             State::None => (),
             State::Menu => self.states.menu.message(sender, msg),
             State::Game => self.states.game.message(sender, msg),
+            State::Replay => self.states.replay.message(sender, msg),
         }
 
         // we only care about state requests
@@ -89,6 +94,7 @@ impl StateManager {
             State::None => (),
             State::Menu => self.states.menu.draw(rrh, delta),
             State::Game => self.states.game.draw(rrh, delta),
+            State::Replay => self.states.replay.draw(rrh, delta),
         }
 
         // TODO: debug
@@ -104,7 +110,7 @@ impl StateManager {
         }
     }
 
-    fn action(&mut self, bus: &mut Bus) {
+    fn action(&mut self) {
         while let Some(action) = self.actions.pop_first() {
             match action {
                 Action::SetState(state) => {
@@ -112,6 +118,7 @@ impl StateManager {
                         State::None => (),
                         State::Menu => self.states.menu.exit(),
                         State::Game => self.states.game.exit(),
+                        State::Replay => self.states.replay.exit(),
                     }
 
                     self.current = state;
@@ -122,9 +129,8 @@ impl StateManager {
                         State::None => (),
                         State::Menu => self.states.menu.init(),
                         State::Game => self.states.game.init(),
+                        State::Replay => self.states.replay.init(),
                     }
-
-                    bus.send(Message::State(StateMessage::StateSet(state)));
                 }
             }
         }
@@ -4,10 +4,16 @@ use raylib::prelude::*;
 
 use crate::{
     bus::Bus,
+    engine::Engine,
+    map::Map,
     messages::{
-        EngineMessage, EngineRequestMessage, Message, RequestMessage, Sender, StateRequestMessage,
+        EngineMessage, EngineRequestMessage, Message, NetMessage, NetRequestMessage,
+        RequestMessage, Sender, StateRequestMessage,
     },
     misc::RaylibRenderHandle,
+    modes::Modes,
+    net::Rejection,
+    ships::ShipClasses,
 };
 
 use super::State;
@@ -15,13 +21,43 @@ use super::State;
 pub struct MenuState {
     actions: BTreeSet<Action>,
     debug: bool,
+    lobby: Lobby,
+    // every map in the maps directory, and the one currently picked
+    maps: Vec<String>,
+    map: String,
+    // every mode in the order of the file, and the one currently picked
+    modes: Vec<String>,
+    mode: String,
+    // every ship class in the order of the file, and the one currently picked
+    ships: Vec<String>,
+    ship: String,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 enum Action {
     GotoGame,
+    GotoReplay,
+    Start,
+    Host,
+    Join,
+    Leave,
     GetDebug,
     ToggleDebug,
+    GetMap,
+    NextMap,
+    GetMode,
+    NextMode,
+    GetShip,
+    NextShip,
+}
+
+enum Lobby {
+    None,
+    Hosting { players: u8 },
+    Joining,
+    Joined { pid: u8, players: u8 },
+    Rejected(Rejection),
+    Failed(String),
 }
 
 impl MenuState {
@@ -29,11 +65,37 @@ impl MenuState {
         MenuState {
             actions: BTreeSet::new(),
             debug: false,
+            lobby: Lobby::None,
+            maps: Vec::new(),
+            map: String::new(),
+            modes: Vec::new(),
+            mode: String::new(),
+            ships: Vec::new(),
+            ship: String::new(),
         }
     }
 
     pub fn init(&mut self) {
         self.actions.insert(Action::GetDebug);
+        self.actions.insert(Action::GetMap);
+        self.actions.insert(Action::GetMode);
+        self.actions.insert(Action::GetShip);
+        self.lobby = Lobby::None;
+        self.maps = Map::list();
+        self.modes = match Modes::load() {
+            Ok(modes) => modes.modes.into_iter().map(|x| x.name).collect(),
+            Err(e) => {
+                self.lobby = Lobby::Failed(format!("modes, {}", e));
+                Vec::new()
+            }
+        };
+        self.ships = match ShipClasses::load() {
+            Ok(ships) => ships.classes.into_iter().map(|x| x.name).collect(),
+            Err(e) => {
+                self.lobby = Lobby::Failed(format!("ship classes, {}", e));
+                Vec::new()
+            }
+        };
     }
 
     pub fn exit(&mut self) {
@@ -42,12 +104,40 @@ impl MenuState {
 
     pub fn input(&mut self, rh: &RaylibHandle) {
         if rh.is_key_pressed(KeyboardKey::KEY_S) {
-            self.actions.insert(Action::GotoGame);
+            self.actions.insert(Action::Start);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_R) {
+            self.actions.insert(Action::GotoReplay);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_H) {
+            self.actions.insert(Action::Host);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_J) {
+            self.actions.insert(Action::Join);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_L) {
+            self.actions.insert(Action::Leave);
         }
 
         if rh.is_key_pressed(KeyboardKey::KEY_D) {
             self.actions.insert(Action::ToggleDebug);
         }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_M) {
+            self.actions.insert(Action::NextMap);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_G) {
+            self.actions.insert(Action::NextMode);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_C) {
+            self.actions.insert(Action::NextShip);
+        }
     }
 
     pub fn update(&mut self, bus: &mut Bus) {
@@ -59,11 +149,85 @@ impl MenuState {
             Message::Engine(EngineMessage::DebugSet(debug) | EngineMessage::DebugGet(debug)) => {
                 self.debug = *debug;
             }
-            _ => return,
+            Message::Engine(EngineMessage::MapSet(map) | EngineMessage::MapGet(map)) => {
+                self.map = map.clone();
+            }
+            Message::Engine(EngineMessage::ModeSet(mode) | EngineMessage::ModeGet(mode)) => {
+                self.mode = mode.clone();
+            }
+            Message::Engine(EngineMessage::ShipSet(ship) | EngineMessage::ShipGet(ship)) => {
+                self.ship = ship.clone();
+            }
+            Message::Net(NetMessage::LobbyUpdated { pid, players }) => {
+                self.lobby = match pid {
+                    0 => Lobby::Hosting { players: *players },
+                    _ => Lobby::Joined {
+                        pid: *pid,
+                        players: *players,
+                    },
+                };
+            }
+            Message::Net(NetMessage::Rejected(rejection)) => {
+                self.lobby = Lobby::Rejected(*rejection);
+            }
+            Message::Net(NetMessage::Failed(reason)) => {
+                self.lobby = Lobby::Failed(reason.to_owned());
+            }
+            Message::Net(NetMessage::Started) => {
+                self.actions.insert(Action::GotoGame);
+            }
+            _ => (),
         }
     }
 
-    pub fn draw(&mut self, _rrh: &mut RaylibRenderHandle, _delta: f32) {}
+    pub fn draw(&mut self, rrh: &mut RaylibRenderHandle, _delta: f32) {
+        let status = match &self.lobby {
+            Lobby::None => String::from("s - play alone, h - host, j - join, r - last replay"),
+            Lobby::Hosting { players } => {
+                format!("hosting, {} players, s - start, l - leave", players)
+            }
+            Lobby::Joining => String::from("joining, l - leave"),
+            Lobby::Joined { pid, players } => {
+                format!("joined as {}, {} players, l - leave", pid, players)
+            }
+            Lobby::Rejected(Rejection::Full) => String::from("rejected, the game is full"),
+            Lobby::Rejected(Rejection::Started) => String::from("rejected, the game has started"),
+            Lobby::Failed(reason) => format!("failed, {}", reason),
+        };
+
+        rrh.draw_text(
+            &status,
+            Engine::WIDTH / 2 - raylib::text::measure_text(&status, 10) / 2,
+            Engine::HEIGHT / 2,
+            10,
+            Engine::DEBUG_TEXT_COLOR,
+        );
+
+        // the map, the mode and the ship can only be changed before we're in a lobby
+        let texts = match &self.lobby {
+            Lobby::Hosting { .. } | Lobby::Joining | Lobby::Joined { .. } => [
+                format!("map {}, mode {}", self.map, self.mode),
+                format!("ship {}", self.ship),
+            ],
+            _ => [
+                format!(
+                    "map {}, m - next map, mode {}, g - next mode",
+                    self.map, self.mode
+                ),
+                format!("ship {}, c - next ship", self.ship),
+            ],
+        };
+
+        for (idx, text) in texts.iter().enumerate() {
+            rrh.draw_text(
+                text,
+                Engine::WIDTH / 2 - raylib::text::measure_text(text, 10) / 2,
+                Engine::HEIGHT / 2 + 14 + idx as i32 * 12,
+                10,
+                Engine::DEBUG_TEXT_COLOR,
+            );
+        }
+    }
 
     fn action(&mut self, bus: &mut Bus) {
         while let Some(action) = self.actions.pop_first() {
@@ -73,6 +237,58 @@ impl MenuState {
                         StateRequestMessage::SetState(State::Game),
                     )));
                 }
+                Action::GotoReplay => {
+                    bus.send(Message::Request(RequestMessage::State(
+                        StateRequestMessage::SetState(State::Replay),
+                    )));
+                }
+                Action::Start => match self.lobby {
+                    Lobby::Hosting { .. } => {
+                        bus.send(Message::Request(RequestMessage::Net(
+                            NetRequestMessage::Start,
+                        )));
+                    }
+                    // the host decides when to start
+                    Lobby::Joining | Lobby::Joined { .. } => (),
+                    _ => {
+                        self.actions.insert(Action::GotoGame);
+                    }
+                },
+                Action::Host => {
+                    // we can't have more players than there are spawns in the map
+                    let max_players = match Map::load(&self.map) {
+                        Ok(map) => map.max_players(),
+                        Err(e) => {
+                            self.lobby = Lobby::Failed(format!("map {}, {}", self.map, e));
+                            continue;
+                        }
+                    };
+
+                    bus.send(Message::Request(RequestMessage::Net(
+                        NetRequestMessage::Host {
+                            max_players,
+                            map: self.map.clone(),
+                            mode: self.mode.clone(),
+                            ship: self.ship.clone(),
+                        },
+                    )));
+                }
+                Action::Join => {
+                    self.lobby = Lobby::Joining;
+
+                    bus.send(Message::Request(RequestMessage::Net(
+                        NetRequestMessage::Join {
+                            ship: self.ship.clone(),
+                        },
+                    )));
+                }
+                Action::Leave => {
+                    self.lobby = Lobby::None;
+
+                    bus.send(Message::Request(RequestMessage::Net(
+                        NetRequestMessage::Disconnect,
+                    )));
+                }
                 Action::GetDebug => {
                     bus.send(Message::Request(RequestMessage::Engine(
                         EngineRequestMessage::GetDebug,
@@ -83,6 +299,81 @@ impl MenuState {
                         EngineRequestMessage::SetDebug(!self.debug),
                     )));
                 }
+                Action::GetMap => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::GetMap,
+                    )));
+                }
+                Action::NextMap => {
+                    if let Lobby::Hosting { .. } | Lobby::Joining | Lobby::Joined { .. } =
+                        self.lobby
+                    {
+                        continue;
+                    }
+
+                    // the maps are sorted, so the next one is the first one after ours
+                    let map = match self.maps.iter().find(|x| **x > self.map) {
+                        Some(map) => map,
+                        None => match self.maps.first() {
+                            Some(map) => map,
+                            None => continue,
+                        },
+                    };
+
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::SetMap(map.clone()),
+                    )));
+                }
+                Action::GetMode => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::GetMode,
+                    )));
+                }
+                Action::NextMode => {
+                    if let Lobby::Hosting { .. } | Lobby::Joining | Lobby::Joined { .. } =
+                        self.lobby
+                    {
+                        continue;
+                    }
+
+                    // in the order of the file, starting over after the last one
+                    let mode = match self.modes.iter().position(|x| *x == self.mode) {
+                        Some(idx) => &self.modes[(idx + 1) % self.modes.len()],
+                        None => match self.modes.first() {
+                            Some(mode) => mode,
+                            None => continue,
+                        },
+                    };
+
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::SetMode(mode.clone()),
+                    )));
+                }
+                Action::GetShip => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::GetShip,
+                    )));
+                }
+                Action::NextShip => {
+                    if let Lobby::Hosting { .. } | Lobby::Joining | Lobby::Joined { .. } =
+                        self.lobby
+                    {
+                        continue;
+                    }
+
+                    // in the order of the file, starting over after the last one
+                    let ship = match self.ships.iter().position(|x| *x == self.ship) {
+                        Some(idx) => &self.ships[(idx + 1) % self.ships.len()],
+                        None => match self.ships.first() {
+                            Some(ship) => ship,
+                            None => continue,
+                        },
+                    };
+
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::SetShip(ship.clone()),
+                    )));
+                }
             }
         }
     }
@@ -0,0 +1,349 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use raylib::prelude::*;
+
+use crate::{
+    bus::Bus,
+    checksum::Checksum,
+    engine::Engine,
+    map::Map,
+    messages::{
+        EngineMessage, EngineRequestMessage, Message, RequestMessage, Sender, StateRequestMessage,
+        WorldMessage,
+    },
+    misc::RaylibRenderHandle,
+    modes::Modes,
+    replay::Replay,
+    ships::ShipClasses,
+    world::{Snapshot, World},
+};
+
+use super::State;
+
+pub struct ReplayState {
+    actions: BTreeSet<Action>,
+    world: World,
+    replay: Option<Replay>,
+    snapshots: BTreeMap<usize, Snapshot>,
+    tick: usize,
+    error: Option<String>,
+    desync: Option<String>,
+    debug: bool,
+    paused: bool,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Action {
+    Load,
+    GotoMenu,
+    GetDebug,
+    ToggleDebug,
+    TogglePause,
+    Step,
+    Rewind,
+    FastForward,
+}
+
+impl ReplayState {
+    // a snapshot every 4 seconds at 16 tps, seeking will at most
+    // have to simulate this many ticks
+    const SNAPSHOT_TICKS: usize = 64;
+    // rewinding moves back this many ticks every tick,
+    // minus the one tick we move forward when not paused
+    const REWIND_TICKS: usize = 3;
+    const FAST_FORWARD_TICKS: usize = 3;
+
+    pub fn new() -> Self {
+        ReplayState {
+            actions: BTreeSet::new(),
+            world: World::new(),
+            replay: None,
+            snapshots: BTreeMap::new(),
+            tick: 0,
+            error: None,
+            desync: None,
+            debug: false,
+            paused: false,
+        }
+    }
+
+    pub fn init(&mut self) {
+        self.actions.insert(Action::GetDebug);
+        self.actions.insert(Action::Load);
+    }
+
+    pub fn exit(&mut self) {
+        self.world.exit();
+        self.actions.clear();
+        self.replay = None;
+        self.snapshots.clear();
+        self.tick = 0;
+        self.error = None;
+        self.desync = None;
+        self.paused = false;
+    }
+
+    pub fn input(&mut self, rh: &RaylibHandle) {
+        if rh.is_key_pressed(KeyboardKey::KEY_E) {
+            self.actions.insert(Action::GotoMenu);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_D) {
+            self.actions.insert(Action::ToggleDebug);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_P) {
+            self.actions.insert(Action::TogglePause);
+        }
+
+        if rh.is_key_pressed(KeyboardKey::KEY_N) {
+            self.actions.insert(Action::Step);
+        }
+
+        if rh.is_key_down(KeyboardKey::KEY_LEFT) {
+            self.actions.insert(Action::Rewind);
+        }
+
+        if rh.is_key_down(KeyboardKey::KEY_RIGHT) {
+            self.actions.insert(Action::FastForward);
+        }
+    }
+
+    pub fn update(&mut self, bus: &mut Bus) {
+        self.action(bus);
+
+        if self.paused {
+            return;
+        }
+
+        self.step(bus);
+    }
+
+    pub fn message(&mut self, sender: &Sender, msg: &Message) {
+        self.world.message(sender, msg);
+
+        match msg {
+            Message::Engine(EngineMessage::DebugGet(debug) | EngineMessage::DebugSet(debug)) => {
+                self.debug = *debug;
+            }
+            Message::World(WorldMessage::ChecksumCalculated(checksum)) => {
+                if let Some(replay) = &self.replay {
+                    ReplayState::verify(replay, &mut self.desync, checksum);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn draw(&mut self, rrh: &mut RaylibRenderHandle, delta: f32) {
+        let replay = match (&self.replay, &self.error) {
+            (Some(replay), _) => replay,
+            (None, Some(error)) => {
+                rrh.draw_text(
+                    error,
+                    Engine::WIDTH / 2 - raylib::text::measure_text(error, 10) / 2,
+                    Engine::HEIGHT / 2,
+                    10,
+                    Engine::DEBUG_TEXT_COLOR,
+                );
+                return;
+            }
+            _ => return,
+        };
+
+        let delta = if self.paused || self.tick >= replay.ticks.len() {
+            1.0
+        } else {
+            delta
+        };
+
+        self.world.draw(rrh, self.debug, delta);
+
+        let text = format!("{} / {} ticks", self.tick, replay.ticks.len());
+        rrh.draw_text(
+            &text,
+            Engine::WIDTH - raylib::text::measure_text(&text, 10) - 4,
+            14,
+            10,
+            Color::WHITESMOKE,
+        );
+
+        if let Some(desync) = &self.desync {
+            rrh.draw_text(
+                desync,
+                Engine::WIDTH / 2 - raylib::text::measure_text(desync, 10) / 2,
+                4,
+                10,
+                Color::RED,
+            );
+        }
+
+        let text = if self.paused {
+            "paused, p - play, n - step, left - rewind, right - fast forward"
+        } else {
+            "p - pause, left - rewind, right - fast forward"
+        };
+        rrh.draw_text(
+            text,
+            Engine::WIDTH / 2 - raylib::text::measure_text(text, 10) / 2,
+            Engine::HEIGHT - 14,
+            10,
+            Color::WHITESMOKE,
+        );
+    }
+
+    fn step(&mut self, bus: &mut Bus) {
+        let replay = match &self.replay {
+            Some(replay) => replay,
+            None => return,
+        };
+
+        // once we run out of ticks we simply stay on the last one
+        let cmds = match replay.ticks.get(self.tick) {
+            Some(cmds) => cmds,
+            None => return,
+        };
+
+        // the messages from the previous tick have been delivered by now,
+        // so this is a safe time to take a snapshot
+        if self.tick.is_multiple_of(ReplayState::SNAPSHOT_TICKS) {
+            self.snapshots
+                .entry(self.tick)
+                .or_insert_with(|| self.world.snapshot());
+        }
+
+        self.world.update(cmds, bus.with_sender(Sender::World));
+        self.tick += 1;
+    }
+
+    fn seek(&mut self, target: usize) {
+        let replay = match &self.replay {
+            Some(replay) => replay,
+            None => return,
+        };
+
+        let target = target.min(replay.ticks.len());
+
+        // restore the closest snapshot before the target,
+        // unless we're already closer ourselves
+        if let Some((tick, snapshot)) = self.snapshots.range(..=target).next_back() {
+            if target < self.tick || *tick > self.tick {
+                self.world.restore(snapshot);
+                self.tick = *tick;
+            }
+        }
+
+        // then simulate forward until we reach the target
+        while self.tick < target {
+            if self.tick.is_multiple_of(ReplayState::SNAPSHOT_TICKS) {
+                self.snapshots
+                    .entry(self.tick)
+                    .or_insert_with(|| self.world.snapshot());
+            }
+
+            self.world.simulate(&replay.ticks[self.tick]);
+            self.tick += 1;
+
+            // simulating handles the messages itself, so we have to check it here
+            ReplayState::verify(replay, &mut self.desync, &self.world.checksum());
+        }
+    }
+
+    fn verify(replay: &Replay, desync: &mut Option<String>, checksum: &Checksum) {
+        // the first desync is the interesting one, everything after will differ as well
+        if desync.is_some() {
+            return;
+        }
+
+        if let Some(divergence) = replay.verify(checksum) {
+            *desync = Some(format!(
+                "desynced from the replay at tick {}, {} diverged",
+                checksum.tick, divergence
+            ));
+        }
+    }
+
+    fn action(&mut self, bus: &mut Bus) {
+        while let Some(action) = self.actions.pop_first() {
+            match action {
+                Action::Load => {
+                    let replay = match Replay::latest() {
+                        Ok(replay) => replay,
+                        Err(e) => {
+                            self.error = Some(format!("Failed to load replay: {}", e));
+                            continue;
+                        }
+                    };
+
+                    let map = match Map::load(&replay.map) {
+                        Ok(map) => map,
+                        Err(e) => {
+                            self.error = Some(format!("Failed to load map {}: {}", replay.map, e));
+                            continue;
+                        }
+                    };
+
+                    let classes = match ShipClasses::load()
+                        .map_err(|e| e.to_string())
+                        .and_then(|x| x.pick(&replay.ships, replay.players as usize))
+                    {
+                        Ok(classes) => classes,
+                        Err(e) => {
+                            self.error = Some(format!("Failed to load ship classes: {}", e));
+                            continue;
+                        }
+                    };
+
+                    let mode = match Modes::load()
+                        .map_err(|e| e.to_string())
+                        .and_then(|x| x.pick(&replay.mode))
+                    {
+                        Ok(mode) => mode,
+                        Err(e) => {
+                            self.error = Some(format!("Failed to load modes: {}", e));
+                            continue;
+                        }
+                    };
+
+                    self.world
+                        .init(replay.pid as usize, classes, replay.seed, map, mode);
+
+                    // we always have a snapshot of the first tick to rewind to
+                    self.snapshots.insert(0, self.world.snapshot());
+                    self.replay = Some(replay);
+                }
+                Action::GotoMenu => {
+                    bus.send(Message::Request(RequestMessage::State(
+                        StateRequestMessage::SetState(State::Menu),
+                    )));
+                }
+                Action::GetDebug => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::GetDebug,
+                    )));
+                }
+                Action::ToggleDebug => {
+                    bus.send(Message::Request(RequestMessage::Engine(
+                        EngineRequestMessage::SetDebug(!self.debug),
+                    )));
+                }
+                Action::TogglePause => {
+                    self.paused = !self.paused;
+                }
+                Action::Step => {
+                    // stepping is done the same way as seeking, this way every update
+                    // caused by an action has had its messages handled before the next one
+                    if self.paused {
+                        self.seek(self.tick + 1);
+                    }
+                }
+                Action::Rewind => {
+                    self.seek(self.tick.saturating_sub(ReplayState::REWIND_TICKS));
+                }
+                Action::FastForward => {
+                    self.seek(self.tick + ReplayState::FAST_FORWARD_TICKS);
+                }
+            }
+        }
+    }
+}
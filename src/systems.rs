@@ -1,5 +1,7 @@
 mod logic;
+#[cfg(feature = "graphics")]
 mod render;
 
 pub use logic::LogicSystem;
+#[cfg(feature = "graphics")]
 pub use render::RenderSystem;
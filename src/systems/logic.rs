@@ -1,28 +1,49 @@
-use std::collections::VecDeque;
+use std::{cmp::Reverse, collections::VecDeque};
 
 use fastrand::Rng;
 
 use crate::{
+    arena::{Components, Handle},
     bus::Bus,
-    collisions,
+    collisions::{self, Bounds, Grid, Impact},
     commands::Command,
-    components::logic::{Body, Counter, Miscellaneous, Motion},
-    entities::{Entities, EntityTypeIndex},
-    math::{Flint, FlintRectangle, FlintTriangle, FlintVec2},
+    components::logic::{Body, Counter, Miscellaneous, Motion, Movable},
+    components::render::{RenderBody, Renderable, Translatable},
+    entities::{Entities, EntityHandle, Kind},
+    map::{Edge, Map},
+    math::{Flint, FlintRectangle, FlintVec2},
     messages::{LogicMessage, Message, Sender},
+    modes::{FriendlyFire, Mode},
+    pickups::{Effect, Pickup},
+    players::Player,
     spawner::Spawner,
-    world::Map,
 };
 
+#[derive(Clone)]
 pub struct LogicSystem {
     tasks: VecDeque<Task>,
-    deaths: Vec<EntityTypeIndex>,
-    deceleration: Flint,
+    deaths: Vec<EntityHandle>,
+    // the damage taken for every unit of speed when crashing into something
+    crash_damage: Flint,
+    // anything slower than this only stops, so pushing against something doesn't hurt
+    crash_speed: Flint,
+    // the gap left between crashed ships and what they crashed into
+    contact_margin: Flint,
+    // what teammates do to each other, how long it takes to respawn
+    // and how long the respawned ship is protected, given by the mode
+    friendly_fire: FriendlyFire,
+    respawn_delay: i32,
+    spawn_protection: i32,
+    // the broad phase, rebuilt every tick
+    triships: Grid,
+    obstacles: Grid,
+    candidates: Vec<usize>,
 }
 
+#[derive(Clone)]
 enum Task {
-    HandleCollision(EntityTypeIndex, EntityTypeIndex),
-    HandleDeath(EntityTypeIndex),
+    HandleCollision(EntityHandle, EntityHandle),
+    HandleDeath(EntityHandle),
 }
 
 impl LogicSystem {
@@ -30,10 +51,24 @@ impl LogicSystem {
         LogicSystem {
             tasks: VecDeque::new(),
             deaths: Vec::new(),
-            deceleration: Flint::from_num(0.06),
+            crash_damage: Flint::from_num(2),
+            crash_speed: Flint::from_num(1),
+            contact_margin: Flint::from_num(0.1),
+            friendly_fire: FriendlyFire::Off,
+            respawn_delay: 0,
+            spawn_protection: 0,
+            triships: Grid::new(),
+            obstacles: Grid::new(),
+            candidates: Vec::new(),
         }
     }
 
+    pub fn init(&mut self, mode: &Mode) {
+        self.friendly_fire = mode.friendly_fire;
+        self.respawn_delay = mode.respawn_delay;
+        self.spawn_protection = mode.spawn_protection;
+    }
+
     pub fn message(&mut self, _sender: &Sender, msg: &Message) {
         let msg = match msg {
             Message::Logic(msg) => msg,
@@ -41,13 +76,18 @@ impl LogicSystem {
         };
 
         match msg {
-            LogicMessage::Death(eti) => self.tasks.push_back(Task::HandleDeath(*eti)),
+            LogicMessage::Death(handle) => self.tasks.push_back(Task::HandleDeath(*handle)),
             LogicMessage::Collision(one, two) => {
                 self.tasks.push_back(Task::HandleCollision(*one, *two))
             }
+            // only there for whoever else wants to know, it has already been handled
+            LogicMessage::PickupCollected { .. }
+            | LogicMessage::Damaged { .. }
+            | LogicMessage::Killed { .. } => (),
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         map: &Map,
@@ -59,9 +99,9 @@ impl LogicSystem {
         bus: &mut Bus,
     ) {
         // tasks must be handled first,
-        // they are spawned from the previous tick's messages and might contain
-        // id references that change once the systems kick in
-        self.handle_tasks(spawner, entities, rng, misc);
+        // they are spawned from the previous tick's messages and refer to entities
+        // that might be removed once the systems kick in
+        self.handle_tasks(spawner, entities, rng, misc, bus);
 
         // -----------
         // --- PRE ---
@@ -69,7 +109,6 @@ impl LogicSystem {
 
         // LOGIC
         self.update_dead_removal(entities);
-        self.update_dead_marker(entities);
 
         // RENDER
         // update render past bodies,
@@ -82,13 +121,18 @@ impl LogicSystem {
 
         // LOGIC
         self.update_respawn(entities, map, spawner, misc);
+        self.update_pickups(entities, map, spawner, rng, misc);
         self.update_body_past(entities);
+        self.update_effects(entities);
+        self.update_healths(entities);
+        self.update_weapons(entities);
         self.update_commands(entities, spawner, rng, cmds);
+        self.update_homing(entities);
         self.update_motion(map, entities);
         self.update_lifetime(entities);
-        self.update_out_of_bounds(map, entities);
+        self.update_edges(map, entities, bus);
         self.update_counter_toggle(entities);
-        self.update_collision_detection(entities, spawner, rng, misc, bus);
+        self.update_collision_detection(map, entities, bus);
         self.update_color(entities);
         self.update_dead_detection(entities, bus);
 
@@ -108,11 +152,16 @@ impl LogicSystem {
         entities: &mut Entities,
         rng: &mut Rng,
         misc: &mut Miscellaneous,
+        bus: &mut Bus,
     ) {
         while let Some(task) = self.tasks.pop_front() {
             match task {
-                Task::HandleCollision(one, two) => self.handle_collision(entities, one, two),
-                Task::HandleDeath(eti) => self.handle_death(spawner, entities, rng, eti, misc),
+                Task::HandleCollision(one, two) => {
+                    self.handle_collision(spawner, entities, rng, one, two, bus)
+                }
+                Task::HandleDeath(handle) => {
+                    self.handle_death(spawner, entities, rng, handle, misc, bus)
+                }
             }
         }
     }
@@ -122,255 +171,658 @@ impl LogicSystem {
         spawner: &Spawner,
         entities: &mut Entities,
         rng: &mut Rng,
-        eti: EntityTypeIndex,
+        handle: EntityHandle,
         misc: &mut Miscellaneous,
+        bus: &mut Bus,
     ) {
-        self.deaths.push(eti);
+        self.deaths.push(handle);
+
+        match handle {
+            EntityHandle::Triship(handle) => {
+                let (body, pid) =
+                    match (entities.triangles.get(handle), entities.owners.get(handle)) {
+                        (Some(body), Some(pid)) => (body, *pid),
+                        _ => return,
+                    };
 
-        match eti {
-            EntityTypeIndex::Triship(idx) => {
                 // spawn three explosions, midway between centroid and each axis
-                let triship = &entities.players[idx];
-                let centroid = triship.body.live.shape.centroid();
+                let centroid = body.live.shape.centroid();
                 let two = Flint::from_num(2);
-                let rad = triship.body.live.direction.radians();
+                let rad = body.live.direction.radians();
                 let vec = vec![
-                    (centroid + triship.body.live.shape.v1.rotated(rad, centroid)) / two,
-                    (centroid + triship.body.live.shape.v2.rotated(rad, centroid)) / two,
-                    (centroid + triship.body.live.shape.v3.rotated(rad, centroid)) / two,
+                    (centroid + body.live.shape.v1.rotated(rad, centroid)) / two,
+                    (centroid + body.live.shape.v2.rotated(rad, centroid)) / two,
+                    (centroid + body.live.shape.v3.rotated(rad, centroid)) / two,
                 ];
 
                 for v in vec {
-                    let explosion = spawner.spawn_explosion_particles(v, 32, rng);
-                    entities.explosions.extend(explosion);
+                    spawner.spawn_explosion_particles(entities, v, 32, rng);
                 }
 
                 // spawn one big explosion in the centroid as well
-                let explosion = spawner.spawn_explosion_particles(centroid, 64, rng);
-                entities.explosions.extend(explosion);
+                spawner.spawn_explosion_particles(entities, centroid, 64, rng);
+
+                // the killing blow scores and every enemy that hurt it assists,
+                // crashing or hitting oneself costs a point instead, and so does killing a teammate
+                let players = &entities.players;
+                let (killer, assists) = match entities.healths.get(handle) {
+                    Some(health) => match health.attacker.filter(|x| *x != pid) {
+                        Some(killer) => (
+                            Some(killer),
+                            health
+                                .attackers
+                                .iter()
+                                .copied()
+                                .filter(|x| *x != pid && *x != killer)
+                                .filter(|x| !is_teammates(players, Some(pid), Some(*x)))
+                                .collect(),
+                        ),
+                        None => (None, Vec::new()),
+                    },
+                    None => (None, Vec::new()),
+                };
+
+                entities.players[pid].deaths += 1;
+
+                match killer {
+                    Some(killer) if is_teammates(&entities.players, Some(pid), Some(killer)) => {
+                        entities.players[killer].score -= 1
+                    }
+                    Some(killer) => {
+                        entities.players[killer].kills += 1;
+                        entities.players[killer].score += 1;
+                    }
+                    None => entities.players[pid].score -= 1,
+                }
+
+                for assist in assists.iter() {
+                    entities.players[*assist].assists += 1;
+                }
+
+                bus.send(Message::Logic(LogicMessage::Killed {
+                    pid,
+                    killer,
+                    assists,
+                }));
+
+                // out of lives, the player stays around without a ship
+                let player = &mut entities.players[pid];
+
+                if let Some(lives) = player.lives.as_mut() {
+                    *lives = lives.saturating_sub(1);
+
+                    if *lives == 0 {
+                        return;
+                    }
+                }
 
                 misc.player_death_counters.push((
-                    idx,
+                    pid,
                     Counter {
-                        value: 100, // stay dead for 100 ticks
+                        value: self.respawn_delay,
                     },
                 ));
             }
-            EntityTypeIndex::Projectile(idx) => {
-                // spawn one explosion
-                let projectile = &entities.projectiles[idx];
-                let explosion =
-                    spawner.spawn_explosion_particles(projectile.body.live.shape.point, 8, rng);
-
-                entities.explosions.extend(explosion);
+            EntityHandle::Projectile(handle) => {
+                let point = match entities.rectangles.get(handle) {
+                    Some(body) => body.live.shape.point,
+                    None => return,
+                };
+
+                // spawn one explosion, missiles go off with a bang,
+                // whether they hit something or ran out of fuel
+                let amount = match entities.homings.contains(handle) {
+                    true => 32,
+                    false => 8,
+                };
+
+                spawner.spawn_explosion_particles(entities, point, amount, rng);
             }
+            EntityHandle::Pickup(handle) => {
+                // the next one shows up at the same point a while later
+                if let Some((_, idx)) = entities.pickups.get(handle) {
+                    misc.map_pickup_counters.push((
+                        *idx,
+                        Counter {
+                            value: rng.i32(Pickup::DELAY),
+                        },
+                    ));
+                }
+            }
+            EntityHandle::Obstacle(_) => (),
         }
     }
 
     fn handle_collision(
         &mut self,
+        spawner: &Spawner,
         entities: &mut Entities,
-        one: EntityTypeIndex,
-        two: EntityTypeIndex,
+        rng: &mut Rng,
+        one: EntityHandle,
+        two: EntityHandle,
+        bus: &mut Bus,
     ) {
-        match (one, two) {
-            (EntityTypeIndex::Triship(t_idx), EntityTypeIndex::Projectile(p_idx))
-            | (EntityTypeIndex::Projectile(p_idx), EntityTypeIndex::Triship(t_idx)) => {
-                let triship = &mut entities.players[t_idx];
-                let projectile = &mut entities.projectiles[p_idx];
+        // either of them might have been removed since the collision was found,
+        // then there's nothing left to collide with
+        let sparks = match (one, two) {
+            (EntityHandle::Triship(t_handle), EntityHandle::Pickup(p_handle))
+            | (EntityHandle::Pickup(p_handle), EntityHandle::Triship(t_handle)) => {
+                // another ship got to it first, or the ship has just died
+                if entities.dead.contains(p_handle) || entities.dead.contains(t_handle) {
+                    return;
+                }
+
+                let (pickup, pid) = match (
+                    entities.pickups.get(p_handle),
+                    entities.owners.get(t_handle),
+                ) {
+                    (Some((pickup, _)), Some(pid)) => (*pickup, *pid),
+                    _ => return,
+                };
+
+                entities.dead.insert(p_handle, ());
+                apply_pickup(entities, t_handle, pid, pickup);
 
-                projectile.dead = true;
-                triship.life -= projectile.dmg;
+                bus.send(Message::Logic(LogicMessage::PickupCollected {
+                    handle: EntityHandle::Pickup(p_handle),
+                    pid,
+                    pickup,
+                }));
+
+                return;
+            }
+            (EntityHandle::Triship(t_handle), EntityHandle::Projectile(p_handle))
+            | (EntityHandle::Projectile(p_handle), EntityHandle::Triship(t_handle)) => {
+                // the projectile has already hit another ship, or the ship has just died,
+                // either way one projectile never hits more than once
+                if entities.dead.contains(p_handle) || entities.dead.contains(t_handle) {
+                    return;
+                }
+
+                let (body_t, health, body_p, damage) = match (
+                    entities.triangles.get_mut(t_handle),
+                    entities.healths.get_mut(t_handle),
+                    entities.rectangles.get_mut(p_handle),
+                    entities.damages.get(p_handle),
+                ) {
+                    (Some(body_t), Some(health), Some(body_p), Some(damage)) => {
+                        (body_t, health, body_p, damage)
+                    }
+                    _ => return,
+                };
+
+                entities.dead.insert(p_handle, ());
+                let attacker = entities.owners.get(p_handle).copied();
+                let target = entities.owners.get(t_handle).copied();
+                let damage = match is_teammates(&entities.players, attacker, target) {
+                    true => self.friendly_fire.scale(*damage),
+                    false => *damage,
+                };
+                let taken = health.hurt(damage, attacker);
 
                 // if we have a collision we must calculate where we collide,
                 // since the projectile includes past and live body to detect
-                // a collision we will begin with the past body and simply move
-                // it until we find the collision
-                projectile.body.live = projectile.body.past;
-                projectile.body.dirty = true;
+                // a collision we will begin with the past body and sweep it
+                // along the way it moved until it hits
+                apply_impact_rectangle(body_p, body_t.calc_axes());
 
-                let direction = projectile.body.live.direction;
-                let axes_p = projectile.body.calc_axes(false);
-                let axes_t = triship.body.calc_axes();
-                let speed = collisions::calculate_speed_to_collision(direction, axes_p, axes_t);
-                let velocity = direction * speed;
+                if health.life <= 0 {
+                    entities.dead.insert(t_handle, ());
+                }
 
-                projectile.body.live.shape.point += velocity;
+                send_damage(&entities.owners, t_handle, attacker, taken, bus);
 
-                if triship.life <= 0 {
-                    triship.dead = true;
+                return;
+            }
+            (EntityHandle::Projectile(p_handle), EntityHandle::Obstacle(o_handle))
+            | (EntityHandle::Obstacle(o_handle), EntityHandle::Projectile(p_handle)) => {
+                let (body_p, body_o) = match (
+                    entities.rectangles.get_mut(p_handle),
+                    entities.polygons.get_mut(o_handle),
+                ) {
+                    (Some(body_p), Some(body_o)) => (body_p, body_o),
+                    _ => return,
+                };
+
+                // a player in front of the obstacle has already been hit
+                if entities.dead.contains(p_handle) {
+                    return;
                 }
+
+                entities.dead.insert(p_handle, ());
+
+                // same as above, sweep the past body until it hits
+                apply_impact_rectangle(body_p, body_o.calc_axes());
+
+                return;
+            }
+            (EntityHandle::Triship(t_handle), EntityHandle::Obstacle(o_handle))
+            | (EntityHandle::Obstacle(o_handle), EntityHandle::Triship(t_handle)) => {
+                let (body_t, motion, health, body_o) = match (
+                    entities.triangles.get_mut(t_handle),
+                    entities.motions.get_mut(t_handle),
+                    entities.healths.get_mut(t_handle),
+                    entities.polygons.get_mut(o_handle),
+                ) {
+                    (Some(body_t), Some(motion), Some(health), Some(body_o)) => {
+                        (body_t, motion, health, body_o)
+                    }
+                    _ => return,
+                };
+
+                // the live bodies still overlap, that's where the sparks fly
+                let axes_t = body_t.calc_axes();
+                let axes_o = body_o.calc_axes();
+                let mut sparks =
+                    collisions::manifold(axes_t, axes_o).map_or(Vec::new(), |x| x.points);
+
+                // the collision was found right after the triship moved,
+                // so the past body is where it was before it crashed,
+                // sweep it from there to where it touches the obstacle
+                let velocity = body_t.live.shape.centroid() - body_t.past.shape.centroid();
+
+                body_t.live = body_t.past;
+                body_t.dirty = true;
+
+                // nothing to do if it only rotated into the obstacle
+                let axes_t = body_t.calc_axes();
+                let axes_o = body_o.calc_axes();
+                let damage = match collisions::time_of_impact(axes_t, velocity, axes_o) {
+                    Some(impact) => {
+                        let push = self.calc_push(&impact, axes_t, axes_o);
+
+                        body_t.push(velocity * impact.fraction + push);
+
+                        // crashing head on hurts more than scraping along the side
+                        self.calc_crash_damage(velocity.dot(&impact.normal))
+                    }
+                    None => Flint::ZERO,
+                };
+
+                let taken = health.hurt(damage, None);
+                motion.speed = Flint::ZERO;
+
+                if health.life <= 0 {
+                    entities.dead.insert(t_handle, ());
+                }
+
+                send_damage(&entities.owners, t_handle, None, taken, bus);
+
+                if damage == Flint::ZERO {
+                    sparks.clear();
+                }
+
+                sparks
             }
-            _ => (),
+            (EntityHandle::Triship(one), EntityHandle::Triship(two)) => {
+                // one of them might have died from something else this tick
+                if entities.dead.contains(one) || entities.dead.contains(two) {
+                    return;
+                }
+
+                let (t1, t2, m1, m2, h1, h2) = match (
+                    entities.triangles.get2_mut(one, two),
+                    entities.motions.get2_mut(one, two),
+                    entities.healths.get2_mut(one, two),
+                ) {
+                    (Some((t1, t2)), Some((m1, m2)), Some((h1, h2))) => (t1, t2, m1, m2, h1, h2),
+                    _ => return,
+                };
+
+                let axes_1 = t1.calc_axes();
+                let axes_2 = t2.calc_axes();
+                let mut sparks =
+                    collisions::manifold(axes_1, axes_2).map_or(Vec::new(), |x| x.points);
+
+                // same as with obstacles, sweep them from where they were
+                // before they crashed, to where they touch each other
+                let v1 = t1.live.shape.centroid() - t1.past.shape.centroid();
+                let v2 = t2.live.shape.centroid() - t2.past.shape.centroid();
+
+                t1.live = t1.past;
+                t1.dirty = true;
+                t2.live = t2.past;
+                t2.dirty = true;
+
+                let axes_1 = t1.calc_axes();
+                let axes_2 = t2.calc_axes();
+                let damage = match collisions::time_of_impact(axes_1, v1 - v2, axes_2) {
+                    Some(impact) => {
+                        let push = self.calc_push(&impact, axes_1, axes_2) / Flint::from_num(2);
+
+                        t1.push(v1 * impact.fraction + push);
+                        t2.push(v2 * impact.fraction - push);
+
+                        // both take half of the damage, based on how fast they crashed into each other
+                        self.calc_crash_damage((v1 - v2).dot(&impact.normal)) / 2
+                    }
+                    None => Flint::ZERO,
+                };
+
+                // each of them was hurt by the other
+                let (pid_1, pid_2) = (
+                    entities.owners.get(one).copied(),
+                    entities.owners.get(two).copied(),
+                );
+                let damage = match is_teammates(&entities.players, pid_1, pid_2) {
+                    true => self.friendly_fire.scale(damage),
+                    false => damage,
+                };
+                let (taken_1, taken_2) = (h1.hurt(damage, pid_2), h2.hurt(damage, pid_1));
+
+                // bounce by trading the velocities, but since the ships can only move
+                // the way they are facing only the part along that direction is kept
+                m1.speed = clamp_speed(t1.live.direction.dot(&v2), m1);
+                m2.speed = clamp_speed(t2.live.direction.dot(&v1), m2);
+
+                let (dead_1, dead_2) = (h1.life <= 0, h2.life <= 0);
+
+                if dead_1 {
+                    entities.dead.insert(one, ());
+                }
+
+                if dead_2 {
+                    entities.dead.insert(two, ());
+                }
+
+                send_damage(&entities.owners, one, pid_2, taken_1, bus);
+                send_damage(&entities.owners, two, pid_1, taken_2, bus);
+
+                if damage == Flint::ZERO {
+                    sparks.clear();
+                }
+
+                sparks
+            }
+            _ => return,
+        };
+
+        for point in sparks {
+            spawner.spawn_explosion_particles(entities, point, 4, rng);
         }
     }
 
-    fn update_dead_marker(&mut self, entities: &mut Entities) {
-        while let Some(death) = self.deaths.pop() {
-            match death {
-                EntityTypeIndex::Triship(idx) => entities.players[idx].dead = true,
-                EntityTypeIndex::Projectile(idx) => entities.projectiles[idx].dead = true,
-            }
+    // speed is how fast it crashed straight into something, in either direction
+    fn calc_crash_damage(&self, speed: Flint) -> Flint {
+        if speed.abs() < self.crash_speed {
+            return Flint::ZERO;
         }
+
+        speed.abs() * self.crash_damage
+    }
+
+    // how far to push alpha out of beta once it has been moved to where they touch,
+    // they might already have overlapped to begin with if one of them respawned on top of the other
+    fn calc_push(
+        &self,
+        impact: &Impact,
+        shape_alpha: &[FlintVec2],
+        shape_beta: &[FlintVec2],
+    ) -> FlintVec2 {
+        let margin = impact.normal * self.contact_margin;
+
+        if impact.fraction > Flint::ZERO {
+            return margin;
+        }
+
+        collisions::manifold(shape_alpha, shape_beta).map_or(margin, |x| x.mtv() + margin)
     }
 
     fn update_dead_detection(&mut self, entities: &mut Entities, bus: &mut Bus) {
-        for (idx, e) in entities.projectiles.iter().enumerate() {
-            if e.dead {
-                bus.send(Message::Logic(LogicMessage::Death(
-                    EntityTypeIndex::Projectile(idx),
-                )));
+        for (handle, _) in entities.dead.iter() {
+            // particles have no deaths to handle, they are simply removed
+            let handle = match entities.kind(handle) {
+                Some(Kind::Triship) => EntityHandle::Triship(handle),
+                Some(Kind::Projectile) => EntityHandle::Projectile(handle),
+                Some(Kind::Pickup) => EntityHandle::Pickup(handle),
+                _ => continue,
+            };
+
+            bus.send(Message::Logic(LogicMessage::Death(handle)));
+        }
+    }
+
+    fn update_body_past(&self, entities: &mut Entities) {
+        apply_body_past(&mut entities.triangles);
+        apply_body_past(&mut entities.rectangles);
+        apply_body_past(&mut entities.points);
+    }
+
+    fn update_collision_detection(&mut self, map: &Map, entities: &mut Entities, bus: &mut Bus) {
+        // broad phase, put everything that can be hit in a grid,
+        // so that only what's close to each other has to be checked
+        self.triships.reset(map.width, map.height);
+        self.obstacles.reset(map.width, map.height);
+
+        // the grids are given the slots the handles point to
+        for (handle, body) in entities.triangles.iter_mut() {
+            // don't do anything if already dead
+            if entities.dead.contains(handle) {
+                continue;
             }
+
+            let bounds = Bounds::from_points(body.calc_axes());
+            self.triships.insert(handle.index(), &bounds);
+        }
+
+        for (handle, body) in entities.polygons.iter_mut() {
+            let bounds = Bounds::from_points(body.calc_axes());
+            self.obstacles.insert(handle.index(), &bounds);
         }
 
-        for (idx, e) in entities.players.iter().enumerate() {
-            if e.dead {
-                bus.send(Message::Logic(LogicMessage::Death(
-                    EntityTypeIndex::Triship(idx),
+        // projectile - player, whatever has a rectangle body and does damage
+        for (p_handle, body_p) in entities.rectangles.iter_mut() {
+            if entities.dead.contains(p_handle) || !entities.damages.contains(p_handle) {
+                continue;
+            }
+
+            let shape_alpha = body_p.calc_axes(true);
+            let bounds = Bounds::from_points(shape_alpha);
+
+            self.triships.query(&bounds, &mut self.candidates);
+
+            for &idx in self.candidates.iter() {
+                let t_handle = match entities.triangles.handle(idx) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
+
+                // let's not shoot ourselves, or our teammates unless they can be hurt
+                let (owner_p, owner_t) = (
+                    entities.owners.get(p_handle).copied(),
+                    entities.owners.get(t_handle).copied(),
+                );
+
+                if owner_p == owner_t
+                    || (self.friendly_fire == FriendlyFire::Off
+                        && is_teammates(&entities.players, owner_p, owner_t))
+                {
+                    continue;
+                }
+
+                let shape_beta = match entities.triangles.get_mut(t_handle) {
+                    Some(body_t) => body_t.calc_axes(),
+                    None => continue,
+                };
+
+                if !collisions::intersects(shape_alpha, shape_beta) {
+                    continue;
+                }
+
+                bus.send(Message::Logic(LogicMessage::Collision(
+                    EntityHandle::Projectile(p_handle),
+                    EntityHandle::Triship(t_handle),
                 )));
             }
         }
-    }
 
-    fn update_body_past(&self, entities: &mut Entities) {
-        entities
-            .players
-            .iter_mut()
-            // .filter(|x| !x.dead)
-            .for_each(|x| x.body.past = x.body.live);
+        // projectile - obstacle, after projectile - player so that
+        // a player in front of an obstacle is hit before the obstacle
+        for (p_handle, body_p) in entities.rectangles.iter_mut() {
+            if entities.dead.contains(p_handle) || !entities.damages.contains(p_handle) {
+                continue;
+            }
 
-        entities
-            .projectiles
-            .iter_mut()
-            // .filter(|x| !x.dead)
-            .for_each(|x| x.body.past = x.body.live);
-    }
+            let shape_alpha = body_p.calc_axes(true);
+            let bounds = Bounds::from_points(shape_alpha);
 
-    fn update_collision_detection(
-        &self,
-        entities: &mut Entities,
-        spawner: &Spawner,
-        rng: &mut Rng,
-        misc: &mut Miscellaneous,
-        bus: &mut Bus,
-    ) {
-        // TODO: fix quad or kd tree for collisions
+            self.obstacles.query(&bounds, &mut self.candidates);
+
+            for &idx in self.candidates.iter() {
+                let o_handle = match entities.polygons.handle(idx) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
+
+                let shape_beta = match entities.polygons.get_mut(o_handle) {
+                    Some(body_o) => body_o.calc_axes(),
+                    None => continue,
+                };
 
-        // projectile - player
-        for (proj_idx, projectile) in entities.projectiles.iter_mut().enumerate() {
-            for (pid, player) in entities.players.iter_mut().enumerate() {
-                // don't do anything if either are already dead
-                if player.dead || projectile.dead {
+                if !collisions::intersects(shape_alpha, shape_beta) {
                     continue;
                 }
 
-                // let's not shoot ourselves...
-                if projectile.pid == pid {
+                bus.send(Message::Logic(LogicMessage::Collision(
+                    EntityHandle::Projectile(p_handle),
+                    EntityHandle::Obstacle(o_handle),
+                )));
+
+                // the projectile is stopped by the first obstacle it hits
+                break;
+            }
+        }
+
+        // pickup - player, only one of the ships gets it when several reach it at once
+        for (handle, _) in entities.pickups.iter() {
+            if entities.dead.contains(handle) {
+                continue;
+            }
+
+            let shape_alpha = match entities.rectangles.get_mut(handle) {
+                Some(body) => body.calc_axes(false),
+                None => continue,
+            };
+
+            let bounds = Bounds::from_points(shape_alpha);
+
+            self.triships.query(&bounds, &mut self.candidates);
+
+            for &idx in self.candidates.iter() {
+                let t_handle = match entities.triangles.handle(idx) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
+
+                let shape_beta = match entities.triangles.get_mut(t_handle) {
+                    Some(body_t) => body_t.calc_axes(),
+                    None => continue,
+                };
+
+                if !collisions::intersects(shape_alpha, shape_beta) {
                     continue;
                 }
 
-                let shape_alpha = projectile.body.calc_axes(true);
-                let shape_beta = player.body.calc_axes();
+                bus.send(Message::Logic(LogicMessage::Collision(
+                    EntityHandle::Pickup(handle),
+                    EntityHandle::Triship(t_handle),
+                )));
+
+                break;
+            }
+        }
+
+        // player - obstacle
+        for (t_handle, body_t) in entities.triangles.iter_mut() {
+            if entities.dead.contains(t_handle) {
+                continue;
+            }
+
+            let shape_alpha = body_t.calc_axes();
+            let bounds = Bounds::from_points(shape_alpha);
+
+            self.obstacles.query(&bounds, &mut self.candidates);
+
+            for &idx in self.candidates.iter() {
+                let o_handle = match entities.polygons.handle(idx) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
+
+                let shape_beta = match entities.polygons.get_mut(o_handle) {
+                    Some(body_o) => body_o.calc_axes(),
+                    None => continue,
+                };
 
                 if !collisions::intersects(shape_alpha, shape_beta) {
                     continue;
                 }
 
                 bus.send(Message::Logic(LogicMessage::Collision(
-                    EntityTypeIndex::Projectile(proj_idx),
-                    EntityTypeIndex::Triship(pid),
+                    EntityHandle::Triship(t_handle),
+                    EntityHandle::Obstacle(o_handle),
                 )));
 
-                // projectile.dead = true;
-                // player.life -= projectile.dmg;
-
-                // if we have a collision we must calculate where we collide,
-                // since the projectile includes past and live body to detect
-                // a collision we will begin with the past body and move it
-                // until we find the collision
-
-                // projectile.body.live = projectile.body.past;
-                // projectile.body.dirty = true;
-
-                // let direction = projectile.body.live.direction;
-                // let shape_alpha = projectile.body.calc_axes(false);
-                // let speed = collisions::calculate_speed_to_collision(
-                //     direction,
-                //     // projectile.motion.speed,
-                //     shape_alpha,
-                //     shape_beta,
-                // );
-
-                // let velocity = direction * speed;
-                // projectile.body.live.shape.point += velocity;
-
-                // let explosion =
-                //     spawner.spawn_explosion_particles(projectile.body.live.shape.point, 8, rng);
-                // entities.explosions.extend(explosion);
-
-                // if player.life > 0 {
-                //     continue;
-                // }
-
-                // // player.dead = true;
-
-                // // spawn three explosions, midway between centroid and each axis
-                // let centroid = player.body.live.shape.centroid();
-
-                // let two = Flint::from_num(2);
-                // let rad = player.body.live.direction.radians();
-                // let vec = vec![
-                //     (centroid + player.body.live.shape.v1.rotated(rad, centroid)) / two,
-                //     (centroid + player.body.live.shape.v2.rotated(rad, centroid)) / two,
-                //     (centroid + player.body.live.shape.v3.rotated(rad, centroid)) / two,
-                //     // player.body.live.shape.v1.rotated(rad, centroid),
-                //     // player.body.live.shape.v2.rotated(rad, centroid),
-                //     // player.body.live.shape.v3.rotated(rad, centroid),
-                // ];
-
-                // for v in vec {
-                //     let explosion = spawner.spawn_explosion_particles(v, 32, rng);
-                //     entities.explosions.extend(explosion);
-                // }
-
-                // // spawn one big in the centroid as well
-                // let explosion = spawner.spawn_explosion_particles(centroid, 64, rng);
-                // entities.explosions.extend(explosion);
-
-                // misc.player_death_counters.push((
-                //     pid,
-                //     Counter {
-                //         value: 100, // stay dead for 100 ticks
-                //     },
-                // ));
+                // moving back once gets the player out of every obstacle
+                break;
             }
         }
 
         // player - player
-        // for i in 0..entities.players.len() - 1 {
-        //     let (left, right) = entities.players.split_at_mut(i + 1);
-        //     let p1 = &mut left[i];
+        for one in entities.triangles.handles() {
+            if entities.dead.contains(one) {
+                continue;
+            }
+
+            let bounds = match entities.triangles.get_mut(one) {
+                Some(body) => Bounds::from_points(body.calc_axes()),
+                None => continue,
+            };
+
+            self.triships.query(&bounds, &mut self.candidates);
 
-        //     for p2 in right.iter_mut() {
+            // every pair is only checked once, from the one in the lowest slot
+            for &idx in self.candidates.iter().filter(|&&idx| idx > one.index()) {
+                let two = match entities.triangles.handle(idx) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
 
-        //         p2.dead = false;
-        //         p1.dead = false;
-        //     }
-        // }
+                let (t1, t2) = match entities.triangles.get2_mut(one, two) {
+                    Some(bodies) => bodies,
+                    None => continue,
+                };
+
+                if !collisions::intersects(t1.calc_axes(), t2.calc_axes()) {
+                    continue;
+                }
+
+                bus.send(Message::Logic(LogicMessage::Collision(
+                    EntityHandle::Triship(one),
+                    EntityHandle::Triship(two),
+                )));
+            }
+        }
     }
 
     fn update_color(&self, entities: &mut Entities) {
-        // particles - exhausts
-        entities.exhausts.iter_mut().for_each(|x| {
-            apply_amount_incdec(&mut x.render.color.a, x.amount, false, true);
-            apply_amount_incdec(&mut x.render.color.g, x.amount, true, true);
-        });
+        // ships blink while nothing can hurt them
+        for (handle, health) in entities.healths.iter() {
+            if let Some(render) = entities.render_triangles.get_mut(handle) {
+                render.color.a = match health.invulnerable % 8 {
+                    1..=4 => 96,
+                    _ => 255,
+                };
+            }
+        }
 
-        // particles - explosions
-        entities.explosions.iter_mut().for_each(|x| {
-            apply_amount_incdec(&mut x.render.color.a, x.amount, false, true);
-            apply_amount_incdec(&mut x.render.color.g, x.amount, true, true);
-        });
+        // particles fade away
+        for (handle, amount) in entities.fades.iter() {
+            if let Some(render) = entities.render_points.get_mut(handle) {
+                apply_amount_incdec(&mut render.color.a, *amount, false, true);
+                apply_amount_incdec(&mut render.color.g, *amount, true, true);
+            }
+        }
 
         // stars
         entities
@@ -379,78 +831,69 @@ impl LogicSystem {
             .for_each(|x| apply_amount_incdec(&mut x.render.color.a, x.amount, x.toggle, false));
     }
 
-    fn update_motion(&self, _map: &Map, entities: &mut Entities) {
-        // players
-        entities
-            .players
-            .iter_mut()
-            .filter(|x| !x.dead)
-            .for_each(|x| {
-                apply_velocity_triangle(&mut x.body, &x.motion);
-                apply_deceleration(&mut x.motion, &self.deceleration);
-
-                let has_moved_v1 = x.body.past.shape.v1 != x.body.live.shape.v1;
-                let has_moved_v2 = x.body.past.shape.v2 != x.body.live.shape.v2;
-                let has_moved_v3 = x.body.past.shape.v3 != x.body.live.shape.v3;
-                let has_moved_dir = x.body.past.direction != x.body.live.direction;
+    fn update_homing(&self, entities: &mut Entities) {
+        for (handle, turn_rate) in entities.homings.iter() {
+            if entities.dead.contains(handle) {
+                continue;
+            }
 
-                x.body.dirty = has_moved_v1 || has_moved_v2 || has_moved_v3 || has_moved_dir;
-            });
+            let body = match entities.rectangles.get_mut(handle) {
+                Some(body) => body,
+                None => continue,
+            };
 
-        // projectiles
-        entities
-            .projectiles
-            .iter_mut()
-            .filter(|x| !x.dead)
-            .for_each(|x| {
-                apply_velocity_rectangle(&mut x.body, &x.motion);
-                // no deceleration on projectiles
+            let owner = entities.owners.get(handle).copied();
+            let centroid = body.live.shape.centroid();
 
-                let has_moved = x.body.past.shape.point != x.body.live.shape.point;
-                let has_moved_dir = x.body.past.direction != x.body.live.direction;
+            // the nearest ship that isn't our own or a teammate's, the first one found when as near,
+            // the ships are always gone through in the same order so it's the same for everyone
+            let mut target: Option<(FlintVec2, Flint)> = None;
 
-                x.body.dirty = has_moved || has_moved_dir;
-            });
+            for (t_handle, body_t) in entities.triangles.iter() {
+                let other = entities.owners.get(t_handle).copied();
 
-        // particles - exhausts
-        entities.exhausts.iter_mut().for_each(|x| {
-            apply_velocity_vector2(&mut x.body, &x.motion);
-            // no deceleration on particles
-        });
+                if entities.dead.contains(t_handle)
+                    || other == owner
+                    || is_teammates(&entities.players, owner, other)
+                {
+                    continue;
+                }
 
-        // particles - explosions
-        entities.explosions.iter_mut().for_each(|x| {
-            apply_velocity_vector2(&mut x.body, &x.motion);
-            // no deceleration on particles
-        });
-    }
+                let offset = body_t.live.shape.centroid() - centroid;
+                let distance = calc_distance_squared(offset);
 
-    fn update_render_past(&self, entities: &mut Entities) {
-        // players
-        entities
-            .players
-            .iter_mut()
-            // .filter(|x| !x.dead)
-            .for_each(|x| x.render.past = x.render.live);
+                if target.is_none_or(|(_, x)| distance < x) {
+                    target = Some((offset, distance));
+                }
+            }
 
-        // projectiles
-        entities
-            .projectiles
-            .iter_mut()
-            // .filter(|x| !x.dead)
-            .for_each(|x| x.render.past = x.render.live);
+            if let Some((offset, _)) = target {
+                body.live.direction = calc_turn(body.live.direction, offset, *turn_rate);
+                body.dirty = true;
+            }
+        }
+    }
 
-        // particles
-        entities
-            .exhausts
-            .iter_mut()
-            .for_each(|x| x.render.past = x.render.live);
+    fn update_motion(&self, _map: &Map, entities: &mut Entities) {
+        // obstacles never move
+        apply_motion(
+            &mut entities.triangles,
+            &mut entities.motions,
+            &entities.dead,
+        );
+        apply_motion(
+            &mut entities.rectangles,
+            &mut entities.motions,
+            &entities.dead,
+        );
+        apply_motion(&mut entities.points, &mut entities.motions, &entities.dead);
+    }
 
-        // particles - explosions
-        entities
-            .explosions
-            .iter_mut()
-            .for_each(|x| x.render.past = x.render.live);
+    fn update_render_past(&self, entities: &mut Entities) {
+        // obstacles never move
+        apply_render_past(&mut entities.render_triangles);
+        apply_render_past(&mut entities.render_rectangles);
+        apply_render_past(&mut entities.render_points);
 
         // stars
         entities
@@ -460,31 +903,10 @@ impl LogicSystem {
     }
 
     fn update_render_live(&self, entities: &mut Entities) {
-        // players
-        entities
-            .players
-            .iter_mut()
-            // .filter(|x| !x.dead)
-            .for_each(|x| x.render.live = (&x.body).into());
-
-        // projectiles
-        entities
-            .projectiles
-            .iter_mut()
-            // .filter(|x| !x.dead)
-            .for_each(|x| x.render.live = (&x.body).into());
-
-        // particles - exhausts
-        entities
-            .exhausts
-            .iter_mut()
-            .for_each(|x| x.render.live = (&x.body).into());
-
-        // particles - explosions
-        entities
-            .explosions
-            .iter_mut()
-            .for_each(|x| x.render.live = (&x.body).into());
+        // obstacles never move
+        apply_render_live(&mut entities.render_triangles, &entities.triangles);
+        apply_render_live(&mut entities.render_rectangles, &entities.rectangles);
+        apply_render_live(&mut entities.render_points, &entities.points);
 
         // stars
         entities
@@ -493,57 +915,166 @@ impl LogicSystem {
             .for_each(|x| x.render.live = (&x.body).into());
     }
 
-    fn update_out_of_bounds(&self, map: &Map, entities: &mut Entities) {
-        entities
-            .projectiles
-            .iter_mut()
-            .filter(|x| !x.dead)
-            .for_each(|x| {
-                if is_out_of_bounds_rectangle(&x.body, map) {
-                    x.dead = true;
+    fn update_edges(&self, map: &Map, entities: &mut Entities, bus: &mut Bus) {
+        match map.edge {
+            Edge::Walls => {
+                // triangles, the ships crash into the walls
+                for (handle, body) in entities.triangles.iter_mut() {
+                    if entities.dead.contains(handle) {
+                        continue;
+                    }
+
+                    let push = calc_push_inside(&Bounds::from_points(body.calc_axes()), map);
+
+                    if push == FlintVec2::new(Flint::ZERO, Flint::ZERO) {
+                        continue;
+                    }
+
+                    // just like crashing into an obstacle
+                    let velocity = body.live.shape.centroid() - body.past.shape.centroid();
+
+                    body.push(push);
+
+                    if let Some(motion) = entities.motions.get_mut(handle) {
+                        motion.speed = Flint::ZERO;
+                    }
+
+                    if let Some(health) = entities.healths.get_mut(handle) {
+                        let damage = self.calc_crash_damage(velocity.dot(&push.normalized()));
+                        let taken = health.hurt(damage, None);
+
+                        if health.life <= 0 {
+                            entities.dead.insert(handle, ());
+                        }
+
+                        send_damage(&entities.owners, handle, None, taken, bus);
+                    }
                 }
-            });
-    }
 
-    fn update_dead_removal(&mut self, entities: &mut Entities) {
-        // sort the deaths,
-        // this way we can use swap_remove without fucking up the indexes :)
-        self.deaths.sort();
+                // rectangles, the whole way they moved must be within the walls
+                for (handle, body) in entities.rectangles.iter_mut() {
+                    if entities.dead.contains(handle) {
+                        continue;
+                    }
 
-        while let Some(eti) = self.deaths.pop() {
-            match eti {
-                EntityTypeIndex::Triship(_) => {
-                    // TODO: as of right now, dead players should not be removed,
-                    // it messes with the indexes/pids,
-                    // probably need to rethink how this is handled
+                    if !is_within_map(&Bounds::from_points(body.calc_axes(true)), map) {
+                        entities.dead.insert(handle, ());
+                    }
                 }
-                EntityTypeIndex::Projectile(idx) => {
-                    entities.projectiles.swap_remove(idx);
+
+                // points
+                for (handle, body) in entities.points.iter() {
+                    if !is_within_map(&Bounds::from_points(&[body.live.shape]), map) {
+                        entities.dead.insert(handle, ());
+                    }
+                }
+            }
+            Edge::Wrap => {
+                apply_wrap(
+                    &mut entities.triangles,
+                    &mut entities.render_triangles,
+                    &entities.dead,
+                    map,
+                );
+                apply_wrap(
+                    &mut entities.rectangles,
+                    &mut entities.render_rectangles,
+                    &entities.dead,
+                    map,
+                );
+                apply_wrap(
+                    &mut entities.points,
+                    &mut entities.render_points,
+                    &entities.dead,
+                    map,
+                );
+            }
+            Edge::Zone { size, damage } => {
+                let zone = Bounds {
+                    min: FlintVec2::new(size, size),
+                    max: FlintVec2::new(map.width - size, map.height - size),
+                };
+
+                // triangles, any part of them within the zone hurts
+                for (handle, body) in entities.triangles.iter_mut() {
+                    if entities.dead.contains(handle) {
+                        continue;
+                    }
+
+                    if is_within(&Bounds::from_points(body.calc_axes()), &zone) {
+                        continue;
+                    }
+
+                    if let Some(health) = entities.healths.get_mut(handle) {
+                        let taken = health.hurt(damage, None);
+
+                        if health.life <= 0 {
+                            entities.dead.insert(handle, ());
+                        }
+
+                        send_damage(&entities.owners, handle, None, taken, bus);
+                    }
+                }
+
+                // rectangles, once the whole way they moved is outside of the map
+                for (handle, body) in entities.rectangles.iter_mut() {
+                    if is_outside_map(&Bounds::from_points(body.calc_axes(true)), map) {
+                        entities.dead.insert(handle, ());
+                    }
+                }
+
+                // points
+                for (handle, body) in entities.points.iter() {
+                    if is_outside_map(&Bounds::from_points(&[body.live.shape]), map) {
+                        entities.dead.insert(handle, ());
+                    }
                 }
             }
         }
+    }
 
-        // particles are currently not registered as deaths and will simply be removed
+    fn update_dead_removal(&mut self, entities: &mut Entities) {
+        while let Some(handle) = self.deaths.pop() {
+            let handle = match handle {
+                EntityHandle::Triship(handle) => handle,
+                EntityHandle::Projectile(handle) => handle,
+                EntityHandle::Pickup(handle) => handle,
+                // obstacles never die
+                EntityHandle::Obstacle(_) => continue,
+            };
+
+            // the player is without a ship until respawning
+            if let Some(&pid) = entities.owners.get(handle) {
+                if let Some(player) = entities.players.get_mut(pid) {
+                    if player.ship == Some(handle) {
+                        player.ship = None;
+                    }
+                }
+            }
 
-        // particles - exhausts
-        entities.exhausts.retain(|x| !x.dead);
+            entities.despawn(handle);
+        }
 
-        // particles - explosions
-        entities.explosions.retain(|x| !x.dead);
+        // particles are currently not registered as deaths and will simply be removed
+        for handle in entities.dead.handles() {
+            if let Some(Kind::Exhaust | Kind::Explosion) = entities.kind(handle) {
+                entities.despawn(handle);
+            }
+        }
     }
 
     fn update_lifetime(&self, entities: &mut Entities) {
-        // particles - exhausts
-        entities
-            .exhausts
-            .iter_mut()
-            .for_each(|x| apply_lifetime_decrease(&mut x.lifetime, &mut x.dead));
+        for (handle, lifetime) in entities.lifetimes.iter_mut() {
+            if entities.dead.contains(handle) {
+                continue;
+            }
 
-        // particles - explosions
-        entities
-            .explosions
-            .iter_mut()
-            .for_each(|x| apply_lifetime_decrease(&mut x.lifetime, &mut x.dead));
+            *lifetime -= 1;
+
+            if *lifetime < 1 {
+                entities.dead.insert(handle, ());
+            }
+        }
     }
 
     fn update_counter_toggle(&self, entities: &mut Entities) {
@@ -554,6 +1085,87 @@ impl LogicSystem {
             .for_each(|x| apply_counter_toggle(&mut x.counter, &mut x.toggle));
     }
 
+    fn update_pickups(
+        &self,
+        entities: &mut Entities,
+        map: &Map,
+        spawner: &Spawner,
+        rng: &mut Rng,
+        misc: &mut Miscellaneous,
+    ) {
+        for (idx, counter) in misc.map_pickup_counters.iter_mut() {
+            counter.value -= 1;
+
+            if counter.value > 0 {
+                continue;
+            }
+
+            // a different one every time, unless the map says otherwise
+            let point = &map.pickups[*idx];
+            let pickup = point
+                .kind
+                .unwrap_or_else(|| Pickup::ALL[rng.usize(..Pickup::ALL.len())]);
+
+            spawner.spawn_pickup(entities, pickup, point.point, *idx);
+        }
+
+        misc.map_pickup_counters.retain(|x| x.1.value > 0);
+    }
+
+    // the pickups wear off, leaving the ship as its class is
+    fn update_effects(&self, entities: &mut Entities) {
+        for (handle, effects) in entities.effects.iter_mut() {
+            for effect in effects.iter_mut() {
+                effect.ticks -= 1;
+
+                if effect.ticks > 0 {
+                    continue;
+                }
+
+                let class = entities
+                    .owners
+                    .get(handle)
+                    .and_then(|x| entities.players.get(*x))
+                    .map(|x| &x.class);
+
+                match effect.pickup {
+                    Pickup::Health => (),
+                    Pickup::Shield => {
+                        if let Some(health) = entities.healths.get_mut(handle) {
+                            health.shielded = false;
+                        }
+                    }
+                    Pickup::Weapon => {
+                        if let Some(loadout) = entities.loadouts.get_mut(handle) {
+                            loadout.upgraded = false;
+                        }
+                    }
+                    Pickup::Speed => {
+                        if let (Some(motion), Some(class)) =
+                            (entities.motions.get_mut(handle), class)
+                        {
+                            motion.max_speed = class.motion.max_speed;
+                            motion.acceleration = class.motion.acceleration;
+                            motion.speed = clamp_speed(motion.speed, motion);
+                        }
+                    }
+                }
+            }
+
+            effects.retain(|x| x.ticks > 0);
+        }
+    }
+
+    fn update_healths(&self, entities: &mut Entities) {
+        entities.healths.values_mut().for_each(|x| x.update());
+    }
+
+    fn update_weapons(&self, entities: &mut Entities) {
+        for loadout in entities.loadouts.values_mut() {
+            loadout.cool();
+        }
+    }
+
     fn update_commands(
         &self,
         entities: &mut Entities,
@@ -562,6 +1174,10 @@ impl LogicSystem {
         cmds: &[Vec<Command>],
     ) {
         for (pid, cmds) in cmds.iter().enumerate() {
+            if let Some(player) = entities.players.get_mut(pid) {
+                player.input.clone_from(cmds);
+            }
+
             for cmd in cmds {
                 cmd.exec(pid, entities, spawner, rng);
             }
@@ -580,8 +1196,19 @@ impl LogicSystem {
             counter.value -= 1;
 
             if counter.value <= 0 {
-                let spawn = &map.spawns[pid];
-                entities.players[pid] = spawner.spawn_triship(spawn.point, spawn.direction);
+                let idx = calc_safest_spawn(entities, map, pid)
+                    .unwrap_or(misc.player_map_spawn_indexes[pid]);
+                let spawn = &map.spawns[idx];
+                let class = entities.players[pid].class.clone();
+                let handle =
+                    spawner.spawn_triship(entities, &class, spawn.point, spawn.direction, pid);
+                entities.players[pid].ship = Some(handle);
+                misc.player_map_spawn_indexes[pid] = idx;
+
+                // a moment to get away before anyone can hurt it
+                if let Some(health) = entities.healths.get_mut(handle) {
+                    health.invulnerable = self.spawn_protection;
+                }
             }
         });
 
@@ -591,13 +1218,9 @@ impl LogicSystem {
 
 fn apply_counter_toggle(counter: &mut u8, toggle: &mut bool) {
     if *toggle {
-        if *counter != u8::MAX {
-            *counter += 1;
-        }
+        *counter = counter.saturating_add(1);
     } else {
-        if *counter != u8::MIN {
-            *counter -= 1;
-        }
+        *counter = counter.saturating_sub(1);
     }
 
     if *counter == u8::MAX || *counter == u8::MIN {
@@ -621,24 +1244,16 @@ fn apply_amount_incdec(number: &mut u8, amount: u8, add: bool, minmax: bool) {
     }
 }
 
-fn apply_lifetime_decrease(lifetime: &mut i32, dead: &mut bool) {
-    *lifetime -= 1;
-
-    if *lifetime < 1 {
-        *dead = true;
-    }
-}
-
-fn apply_deceleration(motion: &mut Motion, deceleration: &Flint) {
+fn apply_deceleration(motion: &mut Motion) {
     // apply deceleration until full stop
     if motion.speed > Flint::ZERO {
-        motion.speed -= deceleration;
+        motion.speed -= motion.deceleration;
 
         if motion.speed < Flint::ZERO {
             motion.speed = Flint::ZERO;
         }
     } else if motion.speed < Flint::ZERO {
-        motion.speed += deceleration;
+        motion.speed += motion.deceleration;
 
         if motion.speed > Flint::ZERO {
             motion.speed = Flint::ZERO;
@@ -646,44 +1261,300 @@ fn apply_deceleration(motion: &mut Motion, deceleration: &Flint) {
     }
 }
 
-fn apply_velocity_triangle(body: &mut Body<FlintTriangle>, motion: &Motion) {
-    let velocity = body.live.direction * motion.speed;
+fn apply_motion<T: Movable>(
+    bodies: &mut Components<Body<T>>,
+    motions: &mut Components<Motion>,
+    dead: &Components<()>,
+) {
+    for (handle, body) in bodies.iter_mut() {
+        if dead.contains(handle) {
+            continue;
+        }
+
+        let motion = match motions.get_mut(handle) {
+            Some(motion) => motion,
+            None => continue,
+        };
+
+        body.apply_velocity(motion);
+        apply_deceleration(motion);
 
-    body.live.shape.v1 += velocity;
-    body.live.shape.v2 += velocity;
-    body.live.shape.v3 += velocity;
+        body.dirty = body.past != body.live;
+    }
+}
+
+fn apply_body_past<T: Copy>(bodies: &mut Components<Body<T>>) {
+    bodies.values_mut().for_each(|x| x.past = x.live);
 }
 
-fn apply_velocity_rectangle(body: &mut Body<FlintRectangle>, motion: &Motion) {
-    let velocity = body.live.direction * motion.speed;
+fn apply_render_past<T: Copy>(renders: &mut Components<Renderable<T>>) {
+    renders.values_mut().for_each(|x| x.past = x.live);
+}
 
-    body.live.shape.point += velocity;
+fn apply_render_live<T, R>(renders: &mut Components<Renderable<R>>, bodies: &Components<Body<T>>)
+where
+    for<'a> RenderBody<R>: From<&'a Body<T>>,
+{
+    for (handle, render) in renders.iter_mut() {
+        if let Some(body) = bodies.get(handle) {
+            render.live = body.into();
+        }
+    }
 }
 
-fn apply_velocity_vector2(body: &mut Body<FlintVec2>, motion: &Motion) {
-    let velocity = body.live.direction * motion.speed;
+fn apply_wrap<T: Movable, R: Translatable>(
+    bodies: &mut Components<Body<T>>,
+    renders: &mut Components<Renderable<R>>,
+    dead: &Components<()>,
+    map: &Map,
+) {
+    for (handle, body) in bodies.iter_mut() {
+        if dead.contains(handle) {
+            continue;
+        }
+
+        let offset = calc_wrap(body.live.shape.centroid(), map);
+
+        if offset == FlintVec2::new(Flint::ZERO, Flint::ZERO) {
+            continue;
+        }
+
+        body.teleport(offset);
 
-    body.live.shape += velocity;
+        if let Some(render) = renders.get_mut(handle) {
+            render.translate_past(offset.into());
+        }
+    }
 }
 
-fn is_out_of_bounds_rectangle(body: &Body<FlintRectangle>, map: &Map) -> bool {
-    // TODO: rotations
+fn apply_impact_rectangle(body: &mut Body<FlintRectangle>, shape_beta: &[FlintVec2]) {
+    let velocity = body.live.shape.point - body.past.shape.point;
+
+    body.live = body.past;
+    body.dirty = true;
+
+    // should always hit, but keep going all the way if it's only a near miss
+    let fraction = collisions::time_of_impact(body.calc_axes(false), velocity, shape_beta)
+        .map_or(Flint::ONE, |x| x.fraction);
+
+    body.live.shape.point += velocity * fraction;
+    body.dirty = true;
+}
 
-    if body.live.shape.point.x + body.live.shape.width < Flint::ZERO {
-        return true;
+// owned by players in the same team, never by the same player
+fn is_teammates(players: &[Player], one: Option<usize>, two: Option<usize>) -> bool {
+    match (
+        one.and_then(|x| players.get(x)),
+        two.and_then(|x| players.get(x)),
+    ) {
+        (Some(one), Some(two)) => one.is_teammate(two),
+        _ => false,
     }
+}
 
-    if body.live.shape.point.x > map.width {
-        return true;
+// lets whoever wants to know, like the hud and the log, that a ship was hurt and by whom
+fn send_damage(
+    owners: &Components<usize>,
+    handle: Handle,
+    attacker: Option<usize>,
+    damage: Flint,
+    bus: &mut Bus,
+) {
+    if damage == Flint::ZERO {
+        return;
     }
 
-    if body.live.shape.point.y + body.live.shape.height < Flint::ZERO {
-        return true;
+    if let Some(&pid) = owners.get(handle) {
+        bus.send(Message::Logic(LogicMessage::Damaged {
+            pid,
+            attacker,
+            damage,
+        }));
     }
+}
 
-    if body.live.shape.point.y > map.height {
-        return true;
+// gives the ship what the pickup is named after, picking up one it already has
+// only starts it over
+fn apply_pickup(entities: &mut Entities, handle: Handle, pid: usize, pickup: Pickup) {
+    let class = match entities.players.get(pid) {
+        Some(player) => &player.class,
+        None => return,
+    };
+
+    match pickup {
+        Pickup::Health => {
+            if let Some(health) = entities.healths.get_mut(handle) {
+                health.life = (health.life + Flint::from_num(Pickup::HEALTH)).min(class.life);
+            }
+        }
+        Pickup::Shield => {
+            if let Some(health) = entities.healths.get_mut(handle) {
+                health.shielded = true;
+            }
+        }
+        Pickup::Weapon => {
+            if let Some(loadout) = entities.loadouts.get_mut(handle) {
+                loadout.upgraded = true;
+                loadout.reload();
+            }
+        }
+        Pickup::Speed => {
+            if let Some(motion) = entities.motions.get_mut(handle) {
+                motion.max_speed = class.motion.max_speed * Pickup::speed_boost();
+                motion.acceleration = class.motion.acceleration * Pickup::speed_boost();
+            }
+        }
     }
 
-    false
+    let ticks = pickup.duration();
+
+    if ticks == 0 {
+        return;
+    }
+
+    if let Some(effects) = entities.effects.get_mut(handle) {
+        match effects.iter_mut().find(|x| x.pickup == pickup) {
+            Some(effect) => effect.ticks = ticks,
+            None => effects.push(Effect { pickup, ticks }),
+        }
+    }
+}
+
+// turns the direction toward the offset the shortest way around,
+// but never more than the turn rate
+fn calc_turn(direction: FlintVec2, offset: FlintVec2, turn_rate: Flint) -> FlintVec2 {
+    let radians = direction.radians();
+    let mut diff = offset.radians() - radians;
+
+    if diff > Flint::PI {
+        diff -= Flint::TAU;
+    } else if diff < -Flint::PI {
+        diff += Flint::TAU;
+    }
+
+    if diff == Flint::ZERO {
+        return direction;
+    }
+
+    let (sin, cos) = cordic::sin_cos(radians + diff.clamp(-turn_rate, turn_rate));
+
+    FlintVec2::new(cos, sin)
+}
+
+// the spawn farthest from the nearest enemy, out of the player's team's spawns,
+// or the ones without a team when there are none, the first one when as far,
+// none when there's no enemy around to get away from
+fn calc_safest_spawn(entities: &Entities, map: &Map, pid: usize) -> Option<usize> {
+    let all = || (0..map.spawns.len()).collect::<Vec<usize>>();
+    let of_team = |team: Option<usize>| -> Vec<usize> {
+        all()
+            .into_iter()
+            .filter(|x| map.spawns[*x].team == team)
+            .collect()
+    };
+
+    let candidates = match entities.players.get(pid).and_then(|x| x.team) {
+        Some(team) => [of_team(Some(team)), of_team(None)]
+            .into_iter()
+            .find(|x| !x.is_empty())
+            .unwrap_or_else(all),
+        None => all(),
+    };
+
+    let enemies: Vec<FlintVec2> = entities
+        .triangles
+        .iter()
+        .filter(|(handle, _)| !entities.dead.contains(*handle))
+        .filter(|(handle, _)| {
+            entities
+                .owners
+                .get(*handle)
+                .is_some_and(|x| *x != pid && !is_teammates(&entities.players, Some(pid), Some(*x)))
+        })
+        .map(|(_, body)| body.live.shape.centroid())
+        .collect();
+
+    candidates
+        .into_iter()
+        .filter_map(|idx| {
+            let point = map.spawns[idx].point;
+            let nearest = enemies
+                .iter()
+                .map(|x| calc_distance_squared(*x - point))
+                .min()?;
+
+            Some((idx, nearest))
+        })
+        .min_by_key(|(idx, nearest)| (Reverse(*nearest), *idx))
+        .map(|(idx, _)| idx)
+}
+
+// only good for comparing, it's scaled down to fit in a flint across most maps
+// and saturates across the rest
+fn calc_distance_squared(offset: FlintVec2) -> Flint {
+    let (x, y) = (offset.x / 64, offset.y / 64);
+
+    x.saturating_mul(x).saturating_add(y.saturating_mul(y))
+}
+
+fn clamp_speed(speed: Flint, motion: &Motion) -> Flint {
+    // the same limits as when accelerating and decelerating
+    speed.clamp(-motion.max_speed / 2, motion.max_speed)
+}
+
+fn is_within(bounds: &Bounds, other: &Bounds) -> bool {
+    bounds.min.x >= other.min.x
+        && bounds.min.y >= other.min.y
+        && bounds.max.x <= other.max.x
+        && bounds.max.y <= other.max.y
+}
+
+fn is_within_map(bounds: &Bounds, map: &Map) -> bool {
+    let map = Bounds {
+        min: FlintVec2::new(Flint::ZERO, Flint::ZERO),
+        max: FlintVec2::new(map.width, map.height),
+    };
+
+    is_within(bounds, &map)
+}
+
+fn is_outside_map(bounds: &Bounds, map: &Map) -> bool {
+    bounds.max.x < Flint::ZERO
+        || bounds.min.x > map.width
+        || bounds.max.y < Flint::ZERO
+        || bounds.min.y > map.height
+}
+
+// how far something must move to be entirely within the map again
+fn calc_push_inside(bounds: &Bounds, map: &Map) -> FlintVec2 {
+    let push = |min: Flint, max: Flint, size: Flint| {
+        if min < Flint::ZERO {
+            -min
+        } else if max > size {
+            size - max
+        } else {
+            Flint::ZERO
+        }
+    };
+
+    FlintVec2::new(
+        push(bounds.min.x, bounds.max.x, map.width),
+        push(bounds.min.y, bounds.max.y, map.height),
+    )
+}
+
+// how far something must move to come back on the other side of the map,
+// the centroid decides when, a rotated shape has the same centroid
+fn calc_wrap(centroid: FlintVec2, map: &Map) -> FlintVec2 {
+    let wrap = |x: Flint, size: Flint| {
+        if x < Flint::ZERO {
+            size
+        } else if x >= size {
+            -size
+        } else {
+            Flint::ZERO
+        }
+    };
+
+    FlintVec2::new(wrap(centroid.x, map.width), wrap(centroid.y, map.height))
 }
@@ -1,11 +1,15 @@
-use raylib::prelude::{Camera2D, RaylibDraw, RaylibMode2D};
+use raylib::prelude::{Camera2D, Color, RaylibDraw, RaylibMode2D, Rectangle, Vector2};
 
 use crate::{
-    components::render::{RenderColor, RenderRectangle, RenderTriangle, RenderVector2, Renderable},
+    components::logic::{Body, Motion},
+    components::render::{
+        RenderColor, RenderPolygon, RenderRectangle, RenderTriangle, RenderVector2, Renderable,
+    },
     engine::Engine,
-    entities::{Entities, Particle, Projectile, Triship},
+    entities::Entities,
+    map::{Edge, Map},
+    math::{FlintRectangle, FlintTriangle},
     misc::RaylibRenderHandle,
-    world::Map,
 };
 
 pub struct RenderSystem;
@@ -27,52 +31,57 @@ impl RenderSystem {
         self.draw_world(rrh, map, cam, entities, delta);
 
         entities
-            .players
-            .iter()
-            .filter(|x| !x.dead)
-            .for_each(|x| self.draw_triangle(rrh, map, cam, &x.render, delta));
+            .render_polygons
+            .values()
+            .for_each(|x| self.draw_polygon(rrh, map, cam, x, delta));
 
         entities
-            .projectiles
+            .render_triangles
             .iter()
-            .filter(|x| !x.dead)
-            .for_each(|x| self.draw_rectangle(rrh, map, cam, &x.render, delta));
+            .filter(|(handle, _)| !entities.is_dead(*handle))
+            .for_each(|(_, x)| self.draw_triangle(rrh, map, cam, x, delta));
 
         entities
-            .exhausts
+            .render_rectangles
             .iter()
-            .for_each(|x| self.draw_vector2(rrh, map, cam, &x.render, delta));
+            .filter(|(handle, _)| !entities.is_dead(*handle))
+            .for_each(|(_, x)| self.draw_rectangle(rrh, map, cam, x, delta));
 
         entities
-            .explosions
-            .iter()
-            .for_each(|x| self.draw_vector2(rrh, map, cam, &x.render, delta));
+            .render_points
+            .values()
+            .for_each(|x| self.draw_vector2(rrh, map, cam, x, delta));
 
         if !debug {
             return;
         }
 
-        entities
-            .players
-            .iter()
-            .filter(|x| !x.dead)
-            .for_each(|x| self.draw_triship_debug(rrh, map, cam, &x, delta));
+        for (handle, render) in entities.render_triangles.iter() {
+            if entities.is_dead(handle) {
+                continue;
+            }
 
-        entities
-            .projectiles
-            .iter()
-            .filter(|x| !x.dead)
-            .for_each(|x| self.draw_projectile_debug(rrh, map, cam, &x, delta));
+            if let (Some(body), Some(motion)) =
+                (entities.triangles.get(handle), entities.motions.get(handle))
+            {
+                self.draw_triangle_debug(rrh, map, cam, render, body, motion, delta);
+            }
+        }
 
-        entities
-            .exhausts
-            .iter()
-            .for_each(|x| self.draw_particle_debug(rrh, map, cam, &x, delta));
+        for (handle, render) in entities.render_rectangles.iter() {
+            if entities.is_dead(handle) {
+                continue;
+            }
+
+            if let Some(body) = entities.rectangles.get(handle) {
+                self.draw_rectangle_debug(rrh, map, cam, render, body, delta);
+            }
+        }
 
         entities
-            .explosions
-            .iter()
-            .for_each(|x| self.draw_particle_debug(rrh, map, cam, &x, delta));
+            .render_points
+            .values()
+            .for_each(|x| self.draw_vector2_debug(rrh, map, cam, x, delta));
     }
 
     fn draw_world(
@@ -83,17 +92,10 @@ impl RenderSystem {
         entities: &Entities,
         _delta: f32,
     ) {
-        // draw world outlines
-        rrh.draw_rectangle_lines(
-            0,
-            0,
-            map.width_i32,
-            map.height_i32,
-            Engine::DEBUG_TEXT_COLOR,
-        );
+        self.draw_edge(rrh, map);
 
         // TODO: fix better stars, make stars loop across the whole world
-        let vec = rrh.get_screen_to_world2D(RenderVector2::new(0.0, 0.0), cam);
+        let vec = rrh.get_screen_to_world2D(Vector2::new(0.0, 0.0), cam);
         let (world_x, world_y) = (vec.x as i32, vec.y as i32);
 
         // no reason to draw any stars at all if we're outside the world
@@ -113,13 +115,13 @@ impl RenderSystem {
             world_y + Engine::HEIGHT
         };
 
-        let star_x = 512;
-        let star_y = 512;
+        let star_x = map.stars.width;
+        let star_y = map.stars.height;
 
         for star in entities.stars.iter() {
             // we will only draw what is currently on the screen,
             // to do that we must find the first valid star position,
-            // stars are randomly generated between 0 and 512, in both x and y,
+            // stars are randomly generated within the tile of the map,
             // draw a repeating star pattern
             // TODO: could probably add some more pseudo randomness here,
             // to not make it look repeated
@@ -153,7 +155,7 @@ impl RenderSystem {
 
             loop {
                 loop {
-                    rrh.draw_rectangle(x, y, w, h, star.render.color);
+                    rrh.draw_rectangle(x, y, w, h, Color::from(star.render.color));
                     x += star_x;
 
                     if x + w > max_x {
@@ -171,6 +173,52 @@ impl RenderSystem {
         }
     }
 
+    fn draw_edge(&self, rrh: &mut RaylibMode2D<RaylibRenderHandle>, map: &Map) {
+        let (w, h) = (map.width_i32, map.height_i32);
+
+        match map.edge {
+            Edge::Walls => {
+                // a couple of outlines on the outside, as thick as the walls look
+                for i in 0..4 {
+                    rrh.draw_rectangle_lines(
+                        -i,
+                        -i,
+                        w + i * 2,
+                        h + i * 2,
+                        Color::from(RenderColor::LIGHTGRAY),
+                    );
+                }
+            }
+            Edge::Wrap => {
+                // dashed, since it's possible to fly right through it
+                let dash = 8;
+
+                for x in (0..w).step_by(dash as usize * 2) {
+                    let end = (x + dash).min(w);
+                    rrh.draw_line(x, 0, end, 0, Engine::DEBUG_TEXT_COLOR);
+                    rrh.draw_line(x, h, end, h, Engine::DEBUG_TEXT_COLOR);
+                }
+
+                for y in (0..h).step_by(dash as usize * 2) {
+                    let end = (y + dash).min(h);
+                    rrh.draw_line(0, y, 0, end, Engine::DEBUG_TEXT_COLOR);
+                    rrh.draw_line(w, y, w, end, Engine::DEBUG_TEXT_COLOR);
+                }
+            }
+            Edge::Zone { size, .. } => {
+                // the zone is drawn see through on the inside of the outline
+                let size: i32 = size.to_num();
+                let color = Color::new(220, 20, 60, 40);
+
+                rrh.draw_rectangle(0, 0, w, size, color);
+                rrh.draw_rectangle(0, h - size, w, size, color);
+                rrh.draw_rectangle(0, size, size, h - size * 2, color);
+                rrh.draw_rectangle(w - size, size, size, h - size * 2, color);
+                rrh.draw_rectangle_lines(0, 0, w, h, Engine::DEBUG_TEXT_COLOR);
+            }
+        }
+    }
+
     fn draw_vector2(
         &self,
         rrh: &mut RaylibMode2D<RaylibRenderHandle>,
@@ -185,7 +233,7 @@ impl RenderSystem {
             return;
         }
 
-        rrh.draw_pixel(ren.x as i32, ren.y as i32, vec.color);
+        rrh.draw_pixel(ren.x as i32, ren.y as i32, Color::from(vec.color));
     }
 
     fn draw_triangle(
@@ -207,7 +255,32 @@ impl RenderSystem {
         // rrh.draw_triangle_fan(&[ren.v1, ren.v2, ren.v3], tri.color);
         // rrh.draw_triangle_strip(&[ren.v1, ren.v2, ren.v3], tri.color);
 
-        rrh.draw_triangle_lines(ren.v1, ren.v2, ren.v3, tri.color);
+        rrh.draw_triangle_lines(
+            Vector2::from(ren.v1),
+            Vector2::from(ren.v2),
+            Vector2::from(ren.v3),
+            Color::from(tri.color),
+        );
+    }
+
+    fn draw_polygon(
+        &self,
+        rrh: &mut RaylibMode2D<RaylibRenderHandle>,
+        _map: &Map,
+        _cam: &Camera2D,
+        pol: &Renderable<RenderPolygon>,
+        _delta: f32,
+    ) {
+        // polygons never move, so there's nothing to interpolate
+        let points = &pol.live.shape.points;
+
+        for (idx, point) in points.iter().enumerate() {
+            rrh.draw_line_v(
+                Vector2::from(*point),
+                Vector2::from(points[(idx + 1) % points.len()]),
+                Color::from(pol.color),
+            );
+        }
     }
 
     fn draw_rectangle(
@@ -239,7 +312,7 @@ impl RenderSystem {
         // ren.x += w;
         // ren.y += h;
 
-        let origin = RenderVector2 {
+        let origin = Vector2 {
             x: ren.width / 2.0,
             y: ren.height / 2.0,
         };
@@ -247,104 +320,71 @@ impl RenderSystem {
         ren.x += origin.x;
         ren.y += origin.y;
 
-        rrh.draw_rectangle_pro(ren, origin, rec.lerp_angle(delta).to_degrees(), rec.color);
+        rrh.draw_rectangle_pro(
+            Rectangle::from(ren),
+            origin,
+            rec.lerp_angle(delta).to_degrees(),
+            Color::from(rec.color),
+        );
     }
 
-    fn draw_projectile_debug(
+    fn draw_rectangle_debug(
         &self,
         rrh: &mut RaylibMode2D<RaylibRenderHandle>,
         _map: &Map,
         _cam: &Camera2D,
-        projectile: &Projectile,
+        _rec: &Renderable<RenderRectangle>,
+        body: &Body<FlintRectangle>,
         _delta: f32,
     ) {
-        let mut ren = projectile.render.live.shape;
-
-        // let w = if ren.width < 2.0 {
-        //     ren.width
-        // } else {
-        //     ren.width / 2.0
-        // };
-
-        // let h = if ren.height < 2.0 {
-        //     ren.height
-        // } else {
-        //     ren.height / 2.0
-        // };
-
-        // ren.x += w;
-        // ren.y += h;
-        let origin = RenderVector2 {
-            x: ren.width / 2.0,
-            y: ren.height / 2.0,
-        };
-
-        ren.x += origin.x;
-        ren.y += origin.y;
-
-        let axes = &projectile.body.axes;
+        let axes = &body.axes;
         for i in 0..axes.len() {
             let a = axes[i];
             let b = axes[if i + 1 == axes.len() { 0 } else { i + 1 }];
             let c = match i {
-                0 => RenderColor::BLUE,
-                1 => RenderColor::GREEN,
-                2 => RenderColor::RED,
-                _ => RenderColor::ORANGE,
+                0 => Color::BLUE,
+                1 => Color::GREEN,
+                2 => Color::RED,
+                _ => Color::ORANGE,
             };
 
-            let v1 = Into::<RenderVector2>::into(a);
-            let v2 = Into::<RenderVector2>::into(b);
+            let v1 = Vector2::from(Into::<RenderVector2>::into(a));
+            let v2 = Vector2::from(Into::<RenderVector2>::into(b));
 
             rrh.draw_line_v(v1, v2, c);
         }
-
-        // println!("{:?} {:?} {:?} {:?}", axes[0], axes[1], axes[2], axes[3]);
-        // println!("{:?}", ren);
-
-        // let mut c = Engine::DEBUG_TEXT_COLOR;
-        // c.a = 80;
-
-        // rrh.draw_rectangle_pro(
-        //     ren,
-        //     origin,
-        //     projectile.render.live.angle.to_degrees(),
-        //     c, // Engine::DEBUG_TEXT_COLOR,
-        // );
     }
 
-    fn draw_triship_debug(
+    #[allow(clippy::too_many_arguments)]
+    fn draw_triangle_debug(
         &self,
         rrh: &mut RaylibMode2D<RaylibRenderHandle>,
         map: &Map,
         cam: &Camera2D,
-        triship: &Triship,
+        tri: &Renderable<RenderTriangle>,
+        body: &Body<FlintTriangle>,
+        motion: &Motion,
         delta: f32,
     ) {
-        if !is_visible_tri(&triship.render.live.shape, map, cam) {
+        if !is_visible_tri(&tri.live.shape, map, cam) {
             return;
         }
 
         rrh.draw_triangle_lines(
-            triship.render.live.shape.v1,
-            triship.render.live.shape.v2,
-            triship.render.live.shape.v3,
+            Vector2::from(tri.live.shape.v1),
+            Vector2::from(tri.live.shape.v2),
+            Vector2::from(tri.live.shape.v3),
             Engine::DEBUG_TEXT_COLOR,
         );
 
-        let cen = triship.render.lerp_centroid(delta);
+        let cen = tri.lerp_centroid(delta);
 
         let (x, y) = (cen.x.round() as i32, cen.y.round() as i32);
 
-        let len = triship.body.live.shape.width.to_num::<i32>()
-            + triship.body.live.shape.height.to_num::<i32>();
+        let len = body.live.shape.width.to_num::<i32>() + body.live.shape.height.to_num::<i32>();
 
         rrh.draw_text(
-            &format!(
-                "{} {}",
-                triship.render.live.angle.round() + 180.0,
-                triship.render.live.angle
-            ),
+            &format!("{} {}", tri.live.angle.round() + 180.0, tri.live.angle),
             x - len,
             y - len,
             10,
@@ -360,7 +400,7 @@ impl RenderSystem {
         );
 
         rrh.draw_text(
-            &format!("{} {}", triship.motion.speed, triship.motion.acceleration),
+            &format!("{} {}", motion.speed, motion.acceleration),
             x - len,
             y + len,
             10,
@@ -368,17 +408,17 @@ impl RenderSystem {
         );
     }
 
-    fn draw_particle_debug(
+    fn draw_vector2_debug(
         &self,
         rrh: &mut RaylibMode2D<RaylibRenderHandle>,
         _map: &Map,
         _cam: &Camera2D,
-        par: &Particle,
+        vec: &Renderable<RenderVector2>,
         _delta: f32,
     ) {
         rrh.draw_pixel(
-            par.render.live.shape.x as i32,
-            par.render.live.shape.y as i32,
+            vec.live.shape.x as i32,
+            vec.live.shape.y as i32,
             Engine::DEBUG_TEXT_COLOR,
         );
     }
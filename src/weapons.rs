@@ -0,0 +1,217 @@
+use crate::{components::render::RenderColor, math::Flint};
+
+#[cfg(test)]
+mod tests;
+
+// what a ship class can be armed with, picked by name in ships.toml
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weapon {
+    Cannon,
+    Spread,
+    Missile,
+    Beam,
+}
+
+// how a weapon fires and what it fires, the same for every ship armed with it
+pub struct WeaponStats {
+    // ticks until it can fire again
+    pub cooldown: u32,
+    // added every shot, overheating once it reaches Loadout::MAX_HEAT
+    pub heat: u32,
+    // shots until it's empty, none for as many as wanted
+    pub ammo: Option<u32>,
+    // fired at once, fanned out with the spread in radians between each of them
+    pub projectiles: u32,
+    pub spread: Flint,
+    // on top of the speed of the ship firing it
+    pub speed: Flint,
+    // radians a tick it steers toward the nearest enemy, zero to fly straight
+    pub turn_rate: Flint,
+    pub damage: Flint,
+    // ticks until it's gone
+    pub lifetime: i32,
+    // along and across the way it flies
+    pub length: Flint,
+    pub width: Flint,
+    pub color: RenderColor,
+}
+
+// every weapon a ship is armed with and the state it's in,
+// the ship fires the selected one and can switch to the next one
+#[derive(Clone)]
+pub struct Loadout {
+    pub armaments: Vec<Armament>,
+    pub selected: usize,
+    // cooling down twice as fast
+    pub upgraded: bool,
+}
+
+#[derive(Clone)]
+pub struct Armament {
+    pub weapon: Weapon,
+    // ticks left until it can fire again
+    pub cooldown: u32,
+    pub heat: u32,
+    // can't fire until it has cooled down completely
+    pub overheated: bool,
+    pub ammo: Option<u32>,
+}
+
+impl Weapon {
+    pub fn from_name(name: &str) -> Option<Weapon> {
+        match name {
+            "cannon" => Some(Weapon::Cannon),
+            "spread" => Some(Weapon::Spread),
+            "missile" => Some(Weapon::Missile),
+            "beam" => Some(Weapon::Beam),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weapon::Cannon => "cannon",
+            Weapon::Spread => "spread",
+            Weapon::Missile => "missile",
+            Weapon::Beam => "beam",
+        }
+    }
+
+    pub fn stats(&self) -> WeaponStats {
+        match self {
+            // a steady stream of shots, until it overheats
+            Weapon::Cannon => WeaponStats {
+                cooldown: 4,
+                heat: 8,
+                ammo: None,
+                projectiles: 1,
+                spread: Flint::ZERO,
+                speed: Flint::from_num(14),
+                turn_rate: Flint::ZERO,
+                damage: Flint::from_num(3),
+                // far enough to cross most maps, but not forever around one that wraps
+                lifetime: 100,
+                length: Flint::from_num(2),
+                width: Flint::from_num(1),
+                color: RenderColor::LIGHTYELLOW,
+            },
+            // a fan of slow shots that don't get far
+            Weapon::Spread => WeaponStats {
+                cooldown: 16,
+                heat: 0,
+                ammo: None,
+                projectiles: 5,
+                spread: Flint::from_num(0.1),
+                speed: Flint::from_num(11),
+                turn_rate: Flint::ZERO,
+                damage: Flint::from_num(2),
+                lifetime: 30,
+                length: Flint::from_num(2),
+                width: Flint::from_num(1),
+                color: RenderColor::ORANGE,
+            },
+            // slow and hard hitting, homing in on whoever is nearest,
+            // but there are only a few of them and they run out of fuel
+            Weapon::Missile => WeaponStats {
+                cooldown: 40,
+                heat: 0,
+                ammo: Some(6),
+                projectiles: 1,
+                spread: Flint::ZERO,
+                speed: Flint::from_num(6),
+                turn_rate: Flint::from_num(0.06),
+                damage: Flint::from_num(15),
+                // the fuel, it goes off once it runs out
+                lifetime: 120,
+                length: Flint::from_num(6),
+                width: Flint::from_num(3),
+                color: RenderColor::TOMATO,
+            },
+            // fired every tick, each as long as it travels in a tick
+            // so they line up into a beam, but it overheats quickly
+            Weapon::Beam => WeaponStats {
+                cooldown: 1,
+                heat: 5,
+                ammo: None,
+                projectiles: 1,
+                spread: Flint::ZERO,
+                speed: Flint::from_num(24),
+                turn_rate: Flint::ZERO,
+                damage: Flint::from_num(1),
+                lifetime: 10,
+                length: Flint::from_num(24),
+                width: Flint::from_num(1),
+                color: RenderColor::AQUA,
+            },
+        }
+    }
+}
+
+impl Loadout {
+    pub const MAX_HEAT: u32 = 100;
+
+    pub fn new(weapons: &[Weapon]) -> Self {
+        Loadout {
+            armaments: weapons
+                .iter()
+                .map(|x| Armament {
+                    weapon: *x,
+                    cooldown: 0,
+                    heat: 0,
+                    overheated: false,
+                    ammo: x.stats().ammo,
+                })
+                .collect(),
+            selected: 0,
+            upgraded: false,
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.armaments.is_empty() {
+            self.selected = (self.selected + 1) % self.armaments.len();
+        }
+    }
+
+    // the weapon to fire, if the selected one is ready
+    pub fn fire(&mut self) -> Option<Weapon> {
+        let armament = self.armaments.get_mut(self.selected)?;
+
+        if armament.cooldown > 0 || armament.overheated || armament.ammo == Some(0) {
+            return None;
+        }
+
+        let stats = armament.weapon.stats();
+
+        armament.cooldown = stats.cooldown;
+        armament.heat += stats.heat;
+        armament.overheated = armament.heat >= Loadout::MAX_HEAT;
+        armament.ammo = armament.ammo.map(|x| x - 1);
+
+        Some(armament.weapon)
+    }
+
+    // back to as much ammo as it started with
+    pub fn reload(&mut self) {
+        for armament in self.armaments.iter_mut() {
+            armament.ammo = armament.weapon.stats().ammo;
+        }
+    }
+
+    // every weapon cools down, whether it's selected or not
+    pub fn cool(&mut self) {
+        let amount = match self.upgraded {
+            true => 2,
+            false => 1,
+        };
+
+        for armament in self.armaments.iter_mut() {
+            armament.cooldown = armament.cooldown.saturating_sub(amount);
+            armament.heat = armament.heat.saturating_sub(amount);
+
+            if armament.heat == 0 {
+                armament.overheated = false;
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use super::{Loadout, Weapon};
+
+// fires every tick it can for the ticks, cooling down in between
+fn fire_for(loadout: &mut Loadout, ticks: u32) -> Vec<u32> {
+    let mut fired = Vec::new();
+
+    for tick in 0..ticks {
+        if loadout.fire().is_some() {
+            fired.push(tick);
+        }
+
+        loadout.cool();
+    }
+
+    fired
+}
+
+#[test]
+fn weapons_fire_once_cooled_down() {
+    let mut loadout = Loadout::new(&[Weapon::Cannon]);
+    let cooldown = Weapon::Cannon.stats().cooldown;
+
+    assert_eq!(loadout.fire(), Some(Weapon::Cannon));
+    assert_eq!(loadout.fire(), None);
+
+    for _ in 0..cooldown - 1 {
+        loadout.cool();
+        assert_eq!(loadout.fire(), None);
+    }
+
+    loadout.cool();
+    assert_eq!(loadout.fire(), Some(Weapon::Cannon));
+}
+
+#[test]
+fn upgraded_weapons_cool_down_twice_as_fast() {
+    let mut loadout = Loadout::new(&[Weapon::Spread]);
+    let cooldown = Weapon::Spread.stats().cooldown;
+
+    assert_eq!(fire_for(&mut loadout, cooldown * 2), vec![0, cooldown]);
+
+    let mut loadout = Loadout::new(&[Weapon::Spread]);
+    loadout.upgraded = true;
+
+    assert_eq!(
+        fire_for(&mut loadout, cooldown * 2),
+        vec![0, cooldown / 2, cooldown, cooldown * 3 / 2]
+    );
+}
+
+#[test]
+fn every_shot_heats_up_until_it_overheats() {
+    let mut loadout = Loadout::new(&[Weapon::Beam]);
+    let heat = Weapon::Beam.stats().heat;
+
+    // it cools off a little after every shot, so the heat adds up a little slower
+    let shots = (Loadout::MAX_HEAT - heat).div_ceil(heat - 1) + 1;
+    let fired = fire_for(&mut loadout, shots * 2);
+
+    assert_eq!(fired.len() as u32, shots);
+    assert!(loadout.armaments[0].overheated);
+    assert!(loadout.armaments[0].heat > 0);
+}
+
+#[test]
+fn overheated_weapons_wait_until_cooled_down_completely() {
+    let mut loadout = Loadout::new(&[Weapon::Beam]);
+
+    while !loadout.armaments[0].overheated {
+        loadout.fire();
+        loadout.cool();
+    }
+
+    let heat = loadout.armaments[0].heat;
+
+    // long past its cooldown, but not until all the heat is gone
+    for _ in 0..heat - 1 {
+        assert_eq!(loadout.fire(), None);
+        loadout.cool();
+    }
+
+    loadout.cool();
+    assert_eq!(loadout.armaments[0].heat, 0);
+    assert_eq!(loadout.fire(), Some(Weapon::Beam));
+}
+
+#[test]
+fn ammo_runs_out_until_reloaded() {
+    let mut loadout = Loadout::new(&[Weapon::Missile, Weapon::Cannon]);
+    let stats = Weapon::Missile.stats();
+    let ammo = stats.ammo.unwrap();
+
+    let fired = fire_for(&mut loadout, stats.cooldown * (ammo + 2));
+    assert_eq!(fired.len() as u32, ammo);
+    assert_eq!(loadout.armaments[0].ammo, Some(0));
+
+    // the others never run out
+    loadout.next();
+    assert_eq!(loadout.fire(), Some(Weapon::Cannon));
+    assert_eq!(loadout.armaments[1].ammo, None);
+
+    loadout.next();
+    loadout.reload();
+    assert_eq!(loadout.armaments[0].ammo, Some(ammo));
+    assert_eq!(loadout.fire(), Some(Weapon::Missile));
+}
+
+#[test]
+fn only_the_selected_weapon_fires() {
+    let mut loadout = Loadout::new(&[Weapon::Spread, Weapon::Cannon]);
+
+    assert_eq!(loadout.fire(), Some(Weapon::Spread));
+
+    // the other one is ready while the first one cools down
+    loadout.next();
+    assert_eq!(loadout.fire(), Some(Weapon::Cannon));
+    loadout.next();
+    assert_eq!(loadout.fire(), None);
+
+    assert_eq!(Loadout::new(&[]).fire(), None);
+}
+
+#[test]
+fn only_the_spread_fires_several_shots() {
+    let stats = Weapon::Spread.stats();
+
+    assert_eq!(Loadout::new(&[Weapon::Spread]).fire(), Some(Weapon::Spread));
+    assert_eq!(stats.projectiles, 5);
+    assert!(stats.spread > 0);
+
+    for weapon in [Weapon::Cannon, Weapon::Missile, Weapon::Beam] {
+        assert_eq!(weapon.stats().projectiles, 1, "{}", weapon.name());
+    }
+}
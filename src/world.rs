@@ -1,32 +1,42 @@
 use fastrand::Rng;
+#[cfg(feature = "graphics")]
 use raylib::prelude::*;
 
 use crate::{
     bus::Bus,
+    checksum::Checksum,
     commands::Command,
-    components::{logic::Miscellaneous, render::RenderColor},
-    engine::Engine,
+    components::{
+        logic::{Counter, Miscellaneous},
+        render::RenderColor,
+    },
     entities::Entities,
+    map::Map,
     math::{Directions, Flint, FlintVec2},
-    messages::{Message, Sender},
-    misc::RaylibRenderHandle,
+    messages::{Message, Sender, WorldMessage},
+    modes::{Ending, Mode},
+    pickups::Pickup,
+    players::Player,
+    ships::ShipClass,
     spawner::Spawner,
-    systems::{LogicSystem, RenderSystem},
+    systems::LogicSystem,
 };
 
-pub struct Spawn {
-    pub point: FlintVec2,
-    pub direction: FlintVec2,
-}
+#[cfg(test)]
+mod tests;
+#[cfg(feature = "graphics")]
+use crate::{engine::Engine, misc::RaylibRenderHandle, modes::Team, systems::RenderSystem};
 
-pub struct Map {
-    pub spawns: Vec<Spawn>,
-    pub width: Flint,
-    pub height: Flint,
-    pub width_i32: i32,
-    pub height_i32: i32,
-    pub width_f32: f32,
-    pub height_f32: f32,
+// everything that changes when the world is updated,
+// restoring it will put the world back to the exact same tick
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+#[derive(Clone)]
+pub struct Snapshot {
+    rng: Rng,
+    tick: u64,
+    logic: LogicSystem,
+    entities: Entities,
+    misc: Miscellaneous,
 }
 
 pub struct World {
@@ -34,9 +44,12 @@ pub struct World {
     seed: Option<u64>,
     pid: Option<usize>,
     map: Option<Map>,
+    mode: Option<Mode>,
+    #[cfg(feature = "graphics")]
     camera: Camera2D,
     tick: u64,
     logic: LogicSystem,
+    #[cfg(feature = "graphics")]
     render: RenderSystem,
     entities: Entities,
     spawner: Spawner,
@@ -50,6 +63,8 @@ impl World {
             pid: None,
             seed: None,
             map: None,
+            mode: None,
+            #[cfg(feature = "graphics")]
             camera: Camera2D {
                 offset: Vector2::new(0.0, 0.0),
                 target: Vector2::new(0.0, 0.0),
@@ -58,6 +73,7 @@ impl World {
             },
             tick: 0,
             logic: LogicSystem::new(),
+            #[cfg(feature = "graphics")]
             render: RenderSystem::new(),
             entities: Entities::new(),
             spawner: Spawner::new(),
@@ -65,32 +81,72 @@ impl World {
         }
     }
 
-    pub fn init(&mut self, pid: usize, players: usize, seed: u64, map: Map) {
+    pub fn init(&mut self, pid: usize, classes: Vec<ShipClass>, seed: u64, map: Map, mode: Mode) {
         // players must not be greater than the spawn points in the map
         // TODO: might be fixable without manual checks with const generics somehow, skip for now
 
         // seed the rng so it's synced across clients
         self.rng.seed(seed);
 
-        // randomize spawn points
-        let mut positions: Vec<usize> = (0..players).collect();
+        // randomize spawn points, every one of them is up for grabs when playing in teams,
+        // otherwise it's the first ones, as many as there are players
+        let spawns = match mode.teams {
+            Some(_) => map.spawns.len(),
+            None => classes.len(),
+        };
+        let mut positions: Vec<usize> = (0..spawns).collect();
         self.rng.shuffle(&mut positions);
 
-        // spawn players
-        for pid in positions.iter().take(players) {
-            let spawn = &map.spawns[*pid];
-            let player = self.spawner.spawn_triship(spawn.point, spawn.direction);
+        // spawn players, each in the ship class they picked and in their team,
+        // at one of their team's spawns while there are any left, then at any without a team
+        for (pid, class) in classes.into_iter().enumerate() {
+            let team = mode.team(pid);
+            let position = positions
+                .iter()
+                .position(|x| team.is_none() || map.spawns[*x].team == team)
+                .or_else(|| positions.iter().position(|x| map.spawns[*x].team.is_none()))
+                .unwrap_or(0);
+            let idx = positions.remove(position);
+
+            // the ship is given the color of the player's team
+            let mut player = Player::new(pid, class.clone());
+            player.lives = mode.lives;
+            player.team = team;
             self.entities.players.push(player);
-            self.misc.player_map_spawn_indexes.push(*pid);
+
+            let spawn = &map.spawns[idx];
+            let handle = self.spawner.spawn_triship(
+                &mut self.entities,
+                &class,
+                spawn.point,
+                spawn.direction,
+                pid,
+            );
+            self.entities.players[pid].ship = Some(handle);
+            self.misc.player_map_spawn_indexes.push(idx);
+        }
+
+        // spawn obstacles
+        for obstacle in map.obstacles.iter() {
+            self.spawner
+                .spawn_obstacle(&mut self.entities, &obstacle.points);
         }
 
-        // spawn stars
-        for _ in 0..64 {
-            let width = self.rng.u8(1..2);
-            let height = self.rng.u8(1..2);
+        // spawn stars, the map makes sure they fit in their tile
+        let stars = &map.stars;
+
+        for _ in 0..stars.count {
+            let width = self.rng.u8(stars.min_size..=stars.max_size);
+            let height = self.rng.u8(stars.min_size..=stars.max_size);
             let centroid = FlintVec2::new(
-                Flint::from_num(self.rng.i32((1 + width as i32)..(512 - width as i32))),
-                Flint::from_num(self.rng.i32((1 + height as i32)..(512 - height as i32))),
+                Flint::from_num(
+                    self.rng
+                        .i32((1 + width as i32)..(stars.width - width as i32)),
+                ),
+                Flint::from_num(
+                    self.rng
+                        .i32((1 + height as i32)..(stars.height - height as i32)),
+                ),
             );
             let rotation = Directions::NORTH;
             let color = RenderColor::new(
@@ -114,15 +170,26 @@ impl World {
             self.entities.stars.push(star);
         }
 
+        // the pickups show up a while into the match
+        for idx in 0..map.pickups.len() {
+            let value = self.rng.i32(Pickup::DELAY);
+            self.misc.map_pickup_counters.push((idx, Counter { value }));
+        }
+
+        self.logic.init(&mode);
+
         self.pid = Some(pid);
         self.seed = Some(seed);
         self.map = Some(map);
+        self.mode = Some(mode);
     }
 
+    #[cfg(feature = "graphics")]
     pub fn exit(&mut self) {
         self.pid = None;
         self.seed = None;
         self.map = None;
+        self.mode = None;
         self.tick = 0;
         self.entities.clear();
         self.misc.clear();
@@ -134,9 +201,9 @@ impl World {
         //     None => return,
         // };
 
-        let map = match self.map.as_mut() {
-            Some(map) => map,
-            None => return,
+        let (map, mode) = match (self.map.as_mut(), &self.mode) {
+            (Some(map), Some(mode)) => (map, mode),
+            _ => return,
         };
 
         // update all logic systems
@@ -151,24 +218,110 @@ impl World {
         );
 
         self.tick += 1;
+
+        // the world goes on, but whoever won stays the winner
+        if self.misc.ending.is_none() {
+            self.misc.ending = mode.ending(self.tick, &self.entities.players);
+
+            if let Some(ending) = self.misc.ending {
+                bus.with_sender(Sender::World)
+                    .send(Message::World(WorldMessage::MatchOver(ending)));
+            }
+        }
+
+        // lets anyone compare our world with the other players' or a replay's
+        bus.with_sender(Sender::World)
+            .send(Message::World(WorldMessage::ChecksumCalculated(
+                self.checksum(),
+            )));
+    }
+
+    // updates the world without waiting for the engine to deliver the messages,
+    // they are handled right away so the next update can follow immediately,
+    // this is what we want when simulating several ticks at once
+    pub fn simulate(&mut self, cmds: &[Vec<Command>]) {
+        let mut bus = Bus::new();
+
+        self.update(cmds, &mut bus);
+
+        while let Some((sender, msg)) = bus.pop() {
+            self.message(&sender, &msg);
+        }
     }
 
     pub fn message(&mut self, sender: &Sender, msg: &Message) {
         self.logic.message(sender, msg);
     }
 
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    pub fn ending(&self) -> Option<Ending> {
+        self.misc.ending
+    }
+
+    // the logic system is left out, its tasks and deaths are always
+    // handled before an update has finished
+    pub fn checksum(&self) -> Checksum {
+        Checksum::new(self.tick, &self.rng, &self.entities, &self.misc)
+    }
+
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    // must not be taken between an update and the delivery of its messages,
+    // since those messages will not be part of the snapshot
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rng: self.rng.clone(),
+            tick: self.tick,
+            logic: self.logic.clone(),
+            entities: self.entities.clone(),
+            misc: self.misc.clone(),
+        }
+    }
+
+    #[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.rng = snapshot.rng.clone();
+        self.tick = snapshot.tick;
+        self.logic = snapshot.logic.clone();
+        self.entities = snapshot.entities.clone();
+        self.misc = snapshot.misc.clone();
+    }
+
+    #[cfg(feature = "graphics")]
     pub fn draw(&mut self, rrh: &mut RaylibRenderHandle, debug: bool, delta: f32) {
         let (map, pid) = match (&self.map, &self.pid) {
             (Some(map), Some(pid)) => (map, pid),
             _ => return,
         };
 
-        // make camera follow player
-        let player = &self.entities.players[*pid];
-        let target = player.render.lerp_centroid(delta);
+        // make camera follow player, it stays where the player died until respawning,
+        // once out of lives it follows whoever is still flying instead
+        let pid = match self.entities.players.get(*pid) {
+            Some(player) if player.is_spectating() => self
+                .entities
+                .players
+                .iter()
+                .find(|x| x.is_alive())
+                .map_or(*pid, |x| x.pid),
+            _ => *pid,
+        };
 
-        self.camera.target.x = target.x - Engine::WIDTH as f32 / 2.0;
-        self.camera.target.y = target.y - Engine::HEIGHT as f32 / 2.0;
+        if let Some(render) = self
+            .entities
+            .ship(pid)
+            .and_then(|x| self.entities.render_triangles.get(x))
+        {
+            let target = render.lerp_centroid(delta);
+
+            self.camera.target.x = target.x - Engine::WIDTH as f32 / 2.0;
+            self.camera.target.y = target.y - Engine::HEIGHT as f32 / 2.0;
+        }
 
         // draw all render systems
         self.render.draw(
@@ -192,5 +345,124 @@ impl World {
                 Color::WHITESMOKE,
             );
         }
+
+        // how the match is going for whoever we follow, and how long it has left
+        if let (Some(player), Some(mode)) = (self.entities.players.get(pid), &self.mode) {
+            // the whole team scores together
+            let (score, team) = match player.team {
+                Some(team) => (
+                    Team::score(team, &self.entities.players),
+                    format!("{} team, ", Team::name(team)),
+                ),
+                None => (player.score, String::new()),
+            };
+
+            let mut text = match mode.frag_limit {
+                Some(limit) => format!("{}{} of {} score", team, score, limit),
+                None => format!("{}{} score", team, score),
+            };
+
+            if let Some(lives) = player.lives {
+                text.push_str(&format!(", {} lives", lives));
+            }
+
+            if let Some(limit) = mode.time_limit {
+                text.push_str(&format!(", {} ticks left", limit.saturating_sub(self.tick)));
+            }
+
+            rrh.draw_text(
+                &text,
+                Engine::WIDTH / 2 - raylib::text::measure_text(&text, 10) / 2,
+                Engine::HEIGHT - 14,
+                10,
+                Color::WHITESMOKE,
+            );
+        }
+
+        // how much the ship we follow can still take
+        if let Some(health) = self
+            .entities
+            .ship(pid)
+            .and_then(|x| self.entities.healths.get(x))
+        {
+            let text = match health.invulnerable {
+                0 => format!(
+                    "{} life {} shield",
+                    health.life.ceil(),
+                    health.shield.floor()
+                ),
+                _ => format!("{} life invulnerable", health.life.ceil()),
+            };
+
+            rrh.draw_text(
+                &text,
+                Engine::WIDTH - raylib::text::measure_text(&text, 10) - 4,
+                34,
+                10,
+                Color::WHITESMOKE,
+            );
+        }
+
+        // how long until the player we follow is back
+        if let Some((_, counter)) = self
+            .misc
+            .player_death_counters
+            .iter()
+            .find(|(x, _)| *x == pid)
+        {
+            let text = format!("respawning in {} ticks", counter.value);
+
+            rrh.draw_text(
+                &text,
+                Engine::WIDTH - raylib::text::measure_text(&text, 10) - 4,
+                34,
+                10,
+                Color::WHITESMOKE,
+            );
+        }
+
+        // the weapon the ship we follow fires, and whether it's ready to
+        if let Some(armament) = self
+            .entities
+            .ship(pid)
+            .and_then(|x| self.entities.loadouts.get(x))
+            .and_then(|x| x.armaments.get(x.selected))
+        {
+            let text = match (armament.overheated, armament.ammo) {
+                (true, _) => format!("{} overheated", armament.weapon.name()),
+                (false, Some(ammo)) => format!("{} {} left", armament.weapon.name(), ammo),
+                (false, None) => format!("{} {} heat", armament.weapon.name(), armament.heat),
+            };
+
+            rrh.draw_text(
+                &text,
+                Engine::WIDTH - raylib::text::measure_text(&text, 10) - 4,
+                44,
+                10,
+                Color::WHITESMOKE,
+            );
+        }
+
+        // the pickups it has and the ticks until they wear off
+        if let Some(effects) = self
+            .entities
+            .ship(pid)
+            .and_then(|x| self.entities.effects.get(x))
+            .filter(|x| !x.is_empty())
+        {
+            let text = effects
+                .iter()
+                .map(|x| format!("{} {}", x.pickup.name(), x.ticks))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            rrh.draw_text(
+                &text,
+                Engine::WIDTH - raylib::text::measure_text(&text, 10) - 4,
+                54,
+                10,
+                Color::WHITESMOKE,
+            );
+        }
     }
 }
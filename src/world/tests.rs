@@ -0,0 +1,1182 @@
+use std::{env, fs, path::Path};
+
+use crate::{
+    bus::Bus,
+    checksum::{Checksum, Divergence},
+    commands::Command,
+    entities::Kind,
+    headless,
+    map::Map,
+    math::{Flint, FlintVec2},
+    messages::{Message, WorldMessage},
+    modes::{FriendlyFire, Mode, Modes},
+    pickups::Pickup,
+    ships::ShipClasses,
+    weapons::{Loadout, Weapon},
+    world::World,
+};
+
+// every scenario is played out from the seed with the commands of the script,
+// the scripts use the same format as the headless mode
+struct Scenario {
+    name: &'static str,
+    seed: u64,
+    players: usize,
+    mode: &'static str,
+    // the ship class of every player by pid, the rest get the first class
+    ships: &'static [&'static str],
+    ticks: usize,
+    script: &'static str,
+}
+
+const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "duel",
+        seed: 42,
+        players: 2,
+        mode: "deathmatch",
+        ships: &[],
+        ticks: 512,
+        // player 0 turns around and keeps on shooting until player 1 dies and respawns,
+        // then player 1 fires missiles at player 0
+        script: "
+            0-38 0 rotate_left
+            40-50 0 accelerate
+            60-64 0 decelerate
+            40-400 0 shoot
+            300-340 1 accelerate rotate_left shoot
+            350 0 explode
+            420-500 0 rotate_right accelerate
+            410 1 switch_weapon
+            420-500 1 decelerate shoot
+        ",
+    },
+    Scenario {
+        name: "brawl",
+        seed: 1337,
+        players: 4,
+        mode: "survival",
+        ships: &["scout", "gunship", "fighter"],
+        ticks: 1024,
+        script: "
+            0-160 0 rotate_left shoot
+            0-160 1 rotate_right shoot
+            0-40 2 accelerate
+            40-200 2 rotate_left shoot
+            0-300 3 shoot
+            100-180 3 rotate_right accelerate
+            200-240 0 accelerate
+            250 1 explode
+            260 1 switch_weapon
+            280 0 switch_weapon
+            300-460 0 rotate_right shoot
+            300-460 1 rotate_left shoot accelerate
+            400-500 2 decelerate rotate_right shoot
+            500-700 3 rotate_left shoot
+            600-800 0 accelerate rotate_left shoot
+            700 2 explode
+            720 1 switch_weapon
+            800-1000 1 shoot rotate_right
+        ",
+    },
+    Scenario {
+        name: "skirmish",
+        seed: 42,
+        players: 4,
+        mode: "teams",
+        ships: &["fighter", "scout", "gunship", "scout"],
+        ticks: 512,
+        // player 0 turns on teammate player 2 for a while, then everyone goes at it
+        script: "
+            0-19 0 rotate_left
+            20-60 0 shoot
+            0-30 1 rotate_right
+            30-300 1 accelerate shoot
+            0-200 3 rotate_left shoot
+            100-140 2 rotate_right accelerate
+            140-400 2 shoot
+            200-260 0 rotate_right accelerate
+            260-480 0 shoot
+            320 3 switch_weapon
+            330-500 3 rotate_right shoot
+        ",
+    },
+];
+
+impl Scenario {
+    fn world(&self) -> World {
+        self.world_with(|_| ())
+    }
+
+    // the mode can be changed before the world is initialized with it
+    fn world_with(&self, change: impl Fn(&mut Mode)) -> World {
+        let map = match Map::load("arena") {
+            Ok(map) => map,
+            Err(e) => panic!("{}: failed to load map: {}", self.name, e),
+        };
+
+        let names: Vec<String> = self.ships.iter().map(|x| x.to_string()).collect();
+        let classes = match ShipClasses::load().map(|x| x.pick(&names, self.players)) {
+            Ok(Ok(classes)) => classes,
+            Ok(Err(e)) => panic!("{}: {}", self.name, e),
+            Err(e) => panic!("{}: failed to load ship classes: {}", self.name, e),
+        };
+
+        let mut mode = match Modes::load().map(|x| x.pick(self.mode)) {
+            Ok(Ok(mode)) => mode,
+            Ok(Err(e)) => panic!("{}: {}", self.name, e),
+            Err(e) => panic!("{}: failed to load modes: {}", self.name, e),
+        };
+
+        change(&mut mode);
+
+        let mut world = World::new();
+        world.init(0, classes, self.seed, map, mode);
+        world
+    }
+
+    fn commands(&self) -> Vec<Vec<Vec<Command>>> {
+        let mut cmds = match headless::parse_script(self.script, self.players) {
+            Ok(cmds) => cmds,
+            Err(e) => panic!("{}: {}", self.name, e),
+        };
+
+        cmds.resize(self.ticks, vec![Vec::new(); self.players]);
+        cmds
+    }
+
+    fn run(&self) -> Vec<Checksum> {
+        let mut world = self.world();
+
+        self.commands()
+            .iter()
+            .map(|cmds| update(&mut world, cmds))
+            .collect()
+    }
+}
+
+// updates the world and delivers its messages before the next update,
+// just like the engine does, the checksum is the one sent on the bus
+fn update(world: &mut World, cmds: &[Vec<Command>]) -> Checksum {
+    let mut bus = Bus::new();
+    let mut checksum = None;
+
+    world.update(cmds, &mut bus);
+
+    while let Some((sender, msg)) = bus.pop() {
+        if let Message::World(WorldMessage::ChecksumCalculated(x)) = &msg {
+            checksum = Some(x.clone());
+        }
+
+        world.message(&sender, &msg);
+    }
+
+    checksum.expect("every update sends a checksum")
+}
+
+fn assert_same(name: &str, expected: &[Checksum], actual: &[Checksum]) {
+    assert_eq!(
+        expected.len(),
+        actual.len(),
+        "{}: different amount of ticks",
+        name
+    );
+
+    for (expected, actual) in expected.iter().zip(actual) {
+        assert_eq!(expected.tick, actual.tick, "{}: ticks out of order", name);
+
+        if let Some(divergence) = expected.diverges(actual) {
+            panic!(
+                "{}: diverged at tick {}, {} differs",
+                name, expected.tick, divergence
+            );
+        }
+    }
+}
+
+#[test]
+fn same_commands_give_same_world() {
+    for scenario in SCENARIOS {
+        assert_same(scenario.name, &scenario.run(), &scenario.run());
+    }
+}
+
+#[test]
+fn different_seeds_give_different_worlds() {
+    for scenario in SCENARIOS {
+        let other = Scenario {
+            seed: scenario.seed + 1,
+            ..*scenario
+        };
+
+        let (one, two) = (scenario.run(), other.run());
+
+        assert_ne!(
+            one.last().map(|x| x.total()),
+            two.last().map(|x| x.total()),
+            "{}: the seed is not part of the checksum",
+            scenario.name
+        );
+    }
+}
+
+#[test]
+fn simulate_gives_same_world_as_update() {
+    for scenario in SCENARIOS {
+        let mut world = scenario.world();
+
+        let checksums: Vec<Checksum> = scenario
+            .commands()
+            .iter()
+            .map(|cmds| {
+                world.simulate(cmds);
+                world.checksum()
+            })
+            .collect();
+
+        assert_same(scenario.name, &scenario.run(), &checksums);
+    }
+}
+
+// snapshots are only taken when seeking through a replay
+#[test]
+fn restored_snapshot_gives_same_world() {
+    for scenario in SCENARIOS {
+        let expected = scenario.run();
+        let cmds = scenario.commands();
+        let mut world = scenario.world();
+        let mut snapshots = Vec::new();
+
+        for (tick, cmds) in cmds.iter().enumerate() {
+            if tick % 100 == 0 {
+                snapshots.push((tick, world.snapshot()));
+            }
+
+            update(&mut world, cmds);
+        }
+
+        for (tick, snapshot) in snapshots.iter() {
+            // restoring must work both in a world that's ahead of the snapshot,
+            // and in a world that has only just been initialized
+            let mut fresh = scenario.world();
+
+            for world in [&mut world, &mut fresh] {
+                world.restore(snapshot);
+
+                let checksums: Vec<Checksum> =
+                    cmds[*tick..].iter().map(|x| update(world, x)).collect();
+
+                assert_same(
+                    &format!("{} restored at tick {}", scenario.name, tick),
+                    &expected[*tick..],
+                    &checksums,
+                );
+            }
+        }
+    }
+}
+
+// player 0 shoots player 1 down in the duel, and nobody else is ever hurt
+#[test]
+fn kills_are_credited_to_the_shooter() {
+    let scenario = &SCENARIOS[0];
+    let mut world = scenario.world();
+
+    for cmds in scenario.commands().iter() {
+        update(&mut world, cmds);
+    }
+
+    let players = &world.entities().players;
+
+    assert!(players[0].kills > 0, "player 0 never scored a kill");
+    assert_eq!(players[0].score, players[0].kills as i32);
+    assert_eq!(players[1].deaths, players[0].kills);
+    assert_eq!(players[0].deaths, 0);
+}
+
+// a single entity that differs is pinned down to its index within its kind,
+// with more than one all we can tell is the kind
+#[test]
+fn divergence_points_at_the_entity() {
+    let scenario = &SCENARIOS[0];
+    let mut world = scenario.world();
+
+    // player 0 is accelerating by now
+    for cmds in scenario.commands().iter().take(48) {
+        update(&mut world, cmds);
+    }
+
+    let expected = world.checksum();
+    let exhausts: Vec<_> = world
+        .entities
+        .kinds
+        .iter()
+        .filter(|(_, x)| **x == Kind::Exhaust)
+        .map(|(handle, _)| handle)
+        .collect();
+
+    assert!(exhausts.len() > 3, "the duel has barely any exhausts");
+
+    *world.entities.lifetimes.get_mut(exhausts[2]).unwrap() += 1;
+    assert_eq!(
+        expected.diverges(&world.checksum()),
+        Some(Divergence::Exhaust(Some(2)))
+    );
+
+    *world.entities.lifetimes.get_mut(exhausts[3]).unwrap() += 1;
+    assert_eq!(
+        expected.diverges(&world.checksum()),
+        Some(Divergence::Exhaust(None))
+    );
+
+    let mut world = scenario.world();
+    let expected = world.checksum();
+    let last = world.entities.stars.len() - 1;
+
+    world.entities.stars[last].toggle ^= true;
+    assert_eq!(
+        expected.diverges(&world.checksum()),
+        Some(Divergence::Star(Some(last)))
+    );
+}
+
+// in floats, so the tests don't share the rounding of the systems
+fn distance_squared(a: FlintVec2, b: FlintVec2) -> i64 {
+    let offset = a - b;
+
+    (offset.x.to_num::<f64>().powi(2) + offset.y.to_num::<f64>().powi(2)) as i64
+}
+
+// player 1 comes back in the duel at whichever spawn is farthest from player 0,
+// protected for as long as the mode says
+#[test]
+fn respawns_at_the_spawn_farthest_from_enemies() {
+    let scenario = &SCENARIOS[0];
+    let mut world = scenario.world();
+
+    for cmds in scenario.commands().iter() {
+        let entities = world.entities();
+        let dead = entities.players[1].ship.is_none();
+        let enemy = entities
+            .ship(0)
+            .and_then(|x| entities.triangles.get(x))
+            .map(|x| x.live.shape.centroid());
+
+        update(&mut world, cmds);
+
+        if !dead || world.entities().players[1].ship.is_none() {
+            continue;
+        }
+
+        let (map, mode) = (world.map.as_ref().unwrap(), world.mode.as_ref().unwrap());
+        let enemy = enemy.expect("player 0 is alive");
+        let farthest = (0..map.spawns.len())
+            .max_by_key(|x| distance_squared(map.spawns[*x].point, enemy))
+            .unwrap();
+
+        let entities = world.entities();
+        let health = entities
+            .ship(1)
+            .and_then(|x| entities.healths.get(x))
+            .unwrap();
+
+        assert_eq!(world.misc.player_map_spawn_indexes[1], farthest);
+        assert!(health.invulnerable > 0 && health.invulnerable <= mode.spawn_protection);
+
+        return;
+    }
+
+    panic!("player 1 never respawned");
+}
+
+// with everyone else dead there's nothing to get away from, so player 1 comes back
+// in the duel at the spawn it last spawned at
+#[test]
+fn respawns_where_it_last_spawned_without_enemies() {
+    let mut world = SCENARIOS[0].world_with(|x| x.respawn_delay = 4);
+    let start = world.misc.player_map_spawn_indexes[1];
+
+    let (map, entities) = (world.map.as_ref().unwrap(), world.entities());
+    let enemy = entities
+        .ship(0)
+        .and_then(|x| entities.triangles.get(x))
+        .map(|x| x.live.shape.centroid())
+        .unwrap();
+    let farthest = (0..map.spawns.len())
+        .max_by_key(|x| distance_squared(map.spawns[*x].point, enemy))
+        .unwrap();
+
+    // or there's no telling it apart from getting away from player 0
+    assert_ne!(start, farthest);
+
+    for pid in [1, 0] {
+        let ship = world.entities().ship(pid).unwrap();
+        world.entities.dead.insert(ship, ());
+        update(&mut world, &[Vec::new(), Vec::new()]);
+    }
+
+    while world.entities().ship(1).is_none() {
+        assert!(world.tick < 16, "player 1 never respawned");
+        update(&mut world, &[Vec::new(), Vec::new()]);
+    }
+
+    assert!(
+        world.entities().ship(0).is_none(),
+        "player 0 respawned first"
+    );
+    assert_eq!(world.misc.player_map_spawn_indexes[1], start);
+}
+
+// player 0 fires at teammate player 2 in the skirmish, which hurts it as much as the mode says
+#[test]
+fn friendly_fire_follows_the_mode() {
+    let scenario = &SCENARIOS[2];
+
+    let taken: Vec<_> = [FriendlyFire::Off, FriendlyFire::Reduced, FriendlyFire::Full]
+        .into_iter()
+        .map(|friendly_fire| {
+            let mut world = scenario.world_with(|x| x.friendly_fire = friendly_fire);
+
+            for cmds in scenario.commands().iter().take(100) {
+                update(&mut world, cmds);
+            }
+
+            taken(&world, 2)
+        })
+        .collect();
+
+    let world = scenario.world();
+    let players = &world.entities().players;
+
+    assert_eq!(players[0].team, players[2].team);
+    assert_ne!(players[0].team, players[1].team);
+    assert_eq!(taken[0], 0, "hurt with friendly fire off");
+    assert!(taken[1] > 0, "not hurt with friendly fire reduced");
+    assert!(taken[2] > taken[1], "not hurt more with friendly fire full");
+}
+
+// three ships in a row facing east, the red ones first and the blue one last,
+// the shots of the first red ship go straight through the other two
+const ROW: &str = "
+    width = 800
+    height = 200
+
+    [[spawns]]
+    x = 100
+    y = 100
+    direction = \"east\"
+    team = \"red\"
+
+    [[spawns]]
+    x = 300
+    y = 100
+    direction = \"east\"
+    team = \"red\"
+
+    [[spawns]]
+    x = 500
+    y = 100
+    direction = \"east\"
+    team = \"blue\"
+";
+
+// a world on a map of its own, everyone in the first ship class
+fn world_on(map: &str, players: usize, mode: Mode) -> World {
+    let map = match Map::parse("test", map) {
+        Ok(map) => map,
+        Err(e) => panic!("failed to parse map: {}", e),
+    };
+
+    let classes = match ShipClasses::load().map(|x| x.pick(&[], players)) {
+        Ok(Ok(classes)) => classes,
+        Ok(Err(e)) => panic!("{}", e),
+        Err(e) => panic!("failed to load ship classes: {}", e),
+    };
+
+    let mut world = World::new();
+    world.init(0, classes, 42, map, mode);
+    world
+}
+
+fn mode(name: &str) -> Mode {
+    match Modes::load().map(|x| x.pick(name)) {
+        Ok(Ok(mode)) => mode,
+        Ok(Err(e)) => panic!("{}", e),
+        Err(e) => panic!("failed to load modes: {}", e),
+    }
+}
+
+// what the player's ship has lost of its life and shield
+fn taken(world: &World, pid: usize) -> Flint {
+    let entities = world.entities();
+    let class = &entities.players[pid].class;
+    let health = entities
+        .ship(pid)
+        .and_then(|x| entities.healths.get(x))
+        .expect("the player is alive");
+
+    class.life + class.shield - health.life - health.shield
+}
+
+// with friendly fire off the teammate in the way is left alone and the enemy
+// behind it is hit instead, otherwise the teammate takes every shot
+#[test]
+fn friendly_fire_off_passes_through_teammates() {
+    for friendly_fire in [FriendlyFire::Off, FriendlyFire::Full] {
+        let mut mode = mode("teams");
+        mode.friendly_fire = friendly_fire;
+
+        let mut world = world_on(ROW, 3, mode);
+        let spawns = world.misc.player_map_spawn_indexes.clone();
+        let pid = |idx: usize| spawns.iter().position(|x| *x == idx).unwrap();
+        let (shooter, teammate, enemy) = (pid(0), pid(1), pid(2));
+
+        let mut cmds = vec![Vec::new(); 3];
+        cmds[shooter].push(Command::Shoot);
+
+        for _ in 0..20 {
+            update(&mut world, &cmds);
+        }
+
+        for _ in 0..40 {
+            update(&mut world, &[Vec::new(), Vec::new(), Vec::new()]);
+        }
+
+        match friendly_fire {
+            FriendlyFire::Off => {
+                assert_eq!(taken(&world, teammate), 0, "the teammate was hurt");
+                assert!(taken(&world, enemy) > 0, "the enemy was never hit");
+            }
+            _ => {
+                assert!(taken(&world, teammate) > 0, "the teammate was never hit");
+                assert_eq!(taken(&world, enemy), 0, "the shots went through");
+            }
+        }
+    }
+}
+
+// the blue team has no spawns of its own, so it starts and respawns at the ones
+// without a team, or at any spawn at all when there are none of those either
+#[test]
+fn teams_without_spawns_use_the_others() {
+    let red = ROW.replace("\"blue\"", "\"red\"");
+    let shared = format!(
+        "{}
+    [[spawns]]
+    x = 120
+    y = 160
+    direction = \"east\"
+
+    [[spawns]]
+    x = 700
+    y = 40
+    direction = \"west\"
+",
+        red
+    );
+
+    for (map, teamless) in [(shared.as_str(), true), (red.as_str(), false)] {
+        let mut mode = mode("teams");
+        mode.respawn_delay = 1;
+
+        let mut world = world_on(map, 2, mode);
+        let spawns = &world.map.as_ref().unwrap().spawns;
+
+        assert_eq!(world.entities().players[1].team, Some(1));
+        assert_eq!(
+            spawns[world.misc.player_map_spawn_indexes[1]]
+                .team
+                .is_none(),
+            teamless
+        );
+
+        let entities = world.entities();
+        let enemy = entities
+            .ship(0)
+            .and_then(|x| entities.triangles.get(x))
+            .map(|x| x.live.shape.centroid())
+            .unwrap();
+        let farthest = (0..spawns.len())
+            .filter(|x| !teamless || spawns[*x].team.is_none())
+            .max_by_key(|x| distance_squared(spawns[*x].point, enemy))
+            .unwrap();
+
+        // or there's no telling it apart from respawning where it started
+        assert_ne!(world.misc.player_map_spawn_indexes[1], farthest);
+
+        let ship = world.entities().ship(1).unwrap();
+        world.entities.dead.insert(ship, ());
+
+        for _ in 0..8 {
+            update(&mut world, &[Vec::new(), Vec::new()]);
+        }
+
+        assert_eq!(world.entities().players[1].deaths, 1);
+        assert!(
+            world.entities().ship(1).is_some(),
+            "player 1 never respawned"
+        );
+        assert_eq!(world.misc.player_map_spawn_indexes[1], farthest);
+    }
+}
+
+// a single ship facing east at x, on a map with the given edge
+fn edge_map(x: i32, edge: &str) -> String {
+    format!(
+        "
+    width = 400
+    height = 200
+
+    [[spawns]]
+    x = {}
+    y = 100
+    direction = \"east\"
+
+    [edge]
+    {}
+",
+        x, edge
+    )
+}
+
+fn centroid(world: &World, pid: usize) -> FlintVec2 {
+    let entities = world.entities();
+
+    entities
+        .ship(pid)
+        .and_then(|x| entities.triangles.get(x))
+        .map(|x| x.live.shape.centroid())
+        .expect("the player is alive")
+}
+
+#[test]
+fn ships_wrap_to_the_opposite_edge() {
+    let mut world = world_on(&edge_map(340, "kind = \"wrap\""), 1, mode("deathmatch"));
+    let mut xs = Vec::new();
+
+    for _ in 0..40 {
+        update(&mut world, &[vec![Command::Accelerate]]);
+        xs.push(centroid(&world, 0).x);
+    }
+
+    // it keeps going east, until it shows up on the west side
+    let wrapped = xs
+        .windows(2)
+        .position(|x| x[1] < x[0])
+        .expect("never wrapped");
+
+    assert!(xs[wrapped] > 390 && xs[wrapped + 1] < 10);
+    assert!(xs[wrapped + 1..].windows(2).all(|x| x[1] > x[0]));
+    assert_eq!(taken(&world, 0), 0);
+}
+
+#[test]
+fn ships_are_pushed_back_and_damaged_at_walls() {
+    let mut world = world_on(&edge_map(340, "kind = \"walls\""), 1, mode("deathmatch"));
+
+    for _ in 0..40 {
+        update(&mut world, &[vec![Command::Accelerate]]);
+
+        let ship = world.entities().ship(0).expect("the player is alive");
+        let body = world.entities.triangles.get_mut(ship).unwrap();
+
+        assert!(
+            body.calc_axes().iter().all(|x| x.x <= 400),
+            "went through the wall"
+        );
+    }
+
+    // crashed at speed, and has to speed up all over again
+    let entities = world.entities();
+    let ship = entities.ship(0).unwrap();
+
+    assert!(taken(&world, 0) > 0, "crashed without damage");
+    assert!(entities.motions.get(ship).unwrap().speed < 1);
+}
+
+#[test]
+fn ships_outside_the_zone_are_damaged() {
+    let edge = "kind = \"zone\"\n    size = 50\n    damage = 1.5";
+
+    for (x, hurt) in [(30, true), (200, false)] {
+        let mut world = world_on(&edge_map(x, edge), 1, mode("deathmatch"));
+
+        for _ in 0..10 {
+            update(&mut world, &[Vec::new()]);
+        }
+
+        let expected = if hurt {
+            Flint::from_num(15)
+        } else {
+            Flint::ZERO
+        };
+        assert_eq!(taken(&world, 0), expected, "at x {}", x);
+    }
+}
+
+// one shot of the spread is a fan of projectiles, one for every one the weapon fires
+#[test]
+fn the_spread_fires_a_fan_of_projectiles() {
+    let mut world = world_on(&edge_map(100, "kind = \"walls\""), 1, mode("deathmatch"));
+    let ship = world.entities().ship(0).unwrap();
+    world
+        .entities
+        .loadouts
+        .insert(ship, Loadout::new(&[Weapon::Spread]));
+
+    update(&mut world, &[vec![Command::Shoot]]);
+
+    let mut directions: Vec<FlintVec2> = world
+        .entities()
+        .rectangles
+        .iter()
+        .map(|(_, body)| body.live.direction)
+        .collect();
+
+    directions.sort_by_key(|x| x.y);
+    directions.dedup();
+
+    assert_eq!(directions.len() as u32, Weapon::Spread.stats().projectiles);
+
+    // around the way the ship is facing, east
+    assert!(directions[0].y < 0 && directions[4].y > 0);
+    assert!(directions.iter().all(|x| x.x > 0));
+}
+
+// a ship facing east at x, with the speed pickup point ahead of it at 300
+fn pickup_map(x: i32) -> String {
+    format!(
+        "{}
+    [[pickups]]
+    x = 300
+    y = 100
+    kind = \"speed\"
+",
+        edge_map(x, "kind = \"walls\"")
+    )
+}
+
+fn pickups(world: &World) -> Vec<(Pickup, FlintVec2)> {
+    let entities = world.entities();
+
+    entities
+        .pickups
+        .iter()
+        .map(|(handle, (pickup, _))| {
+            let body = entities.rectangles.get(handle).unwrap();
+            (*pickup, body.live.shape.centroid())
+        })
+        .collect()
+}
+
+#[test]
+fn pickups_show_up_at_their_point_after_the_delay() {
+    let mut world = world_on(&pickup_map(100), 1, mode("deathmatch"));
+    let delay = world.misc.map_pickup_counters[0].1.value;
+
+    assert!(Pickup::DELAY.contains(&delay));
+
+    for _ in 0..delay - 1 {
+        update(&mut world, &[Vec::new()]);
+    }
+
+    assert_eq!(pickups(&world), Vec::new());
+
+    update(&mut world, &[Vec::new()]);
+
+    let point = FlintVec2::new(Flint::from_num(300), Flint::from_num(100));
+    assert_eq!(pickups(&world), vec![(Pickup::Speed, point)]);
+}
+
+// the ship flies over the pickup and is faster for as long as the effect lasts
+#[test]
+fn pickups_are_collected_and_wear_off() {
+    let mut world = world_on(&pickup_map(260), 1, mode("deathmatch"));
+    let class = world.entities().players[0].class.motion.clone();
+    let ship = world.entities().ship(0).unwrap();
+
+    // no need to wait for it
+    world.misc.map_pickup_counters[0].1.value = 1;
+    update(&mut world, &[Vec::new()]);
+    assert_eq!(pickups(&world).len(), 1);
+
+    let mut ticks = 0;
+
+    while world.entities().effects.get(ship).unwrap().is_empty() {
+        assert!(ticks < 20, "never collected");
+        update(&mut world, &[vec![Command::Accelerate]]);
+        ticks += 1;
+    }
+
+    // gone the moment it was collected
+    assert!(world
+        .entities()
+        .pickups
+        .iter()
+        .all(|(x, _)| world.entities().is_dead(x)));
+
+    let motion = world.entities().motions.get(ship).unwrap();
+    assert_eq!(motion.max_speed, class.max_speed * Pickup::speed_boost());
+    assert_eq!(
+        motion.acceleration,
+        class.acceleration * Pickup::speed_boost()
+    );
+
+    // the tick it was collected counts as well
+    for _ in 0..Pickup::Speed.duration() - 2 {
+        update(&mut world, &[Vec::new()]);
+    }
+
+    let motion = world.entities().motions.get(ship).unwrap();
+    assert_eq!(motion.max_speed, class.max_speed * Pickup::speed_boost());
+
+    update(&mut world, &[Vec::new()]);
+
+    let motion = world.entities().motions.get(ship).unwrap();
+    assert_eq!(motion.max_speed, class.max_speed);
+    assert_eq!(motion.acceleration, class.acceleration);
+    assert!(world.entities().effects.get(ship).unwrap().is_empty());
+}
+
+// the missile is fired east past a teammate close by to the north,
+// at an enemy farther away to the south
+const HOMING: &str = "
+    width = 800
+    height = 400
+
+    [[spawns]]
+    x = 100
+    y = 200
+    direction = \"east\"
+    team = \"red\"
+
+    [[spawns]]
+    x = 220
+    y = 140
+    direction = \"east\"
+    team = \"red\"
+
+    [[spawns]]
+    x = 400
+    y = 320
+    direction = \"east\"
+    team = \"blue\"
+";
+
+#[test]
+fn missiles_turn_toward_the_nearest_enemy() {
+    let mut world = world_on(HOMING, 3, mode("teams"));
+    let spawns = world.misc.player_map_spawn_indexes.clone();
+    let shooter = spawns.iter().position(|x| *x == 0).unwrap();
+    let ship = world.entities().ship(shooter).unwrap();
+
+    world
+        .entities
+        .loadouts
+        .insert(ship, Loadout::new(&[Weapon::Missile]));
+
+    let mut cmds = vec![Vec::new(); 3];
+    cmds[shooter].push(Command::Shoot);
+    update(&mut world, &cmds);
+
+    let entities = world.entities();
+    let (missile, _) = entities.homings.iter().next().expect("no missile fired");
+    let enemy = entities.ship(spawns.iter().position(|x| *x == 2).unwrap());
+    let enemy = entities.triangles.get(enemy.unwrap()).unwrap();
+    let target = enemy.live.shape.centroid();
+
+    let turn_rate = Weapon::Missile.stats().turn_rate;
+    let mut radians = Vec::new();
+
+    for _ in 0..20 {
+        update(&mut world, &[Vec::new(), Vec::new(), Vec::new()]);
+
+        let body = world.entities().rectangles.get(missile).unwrap();
+        radians.push(body.live.direction.radians());
+    }
+
+    // south is clockwise, the teammate to the north and the owner behind are ignored,
+    // and it's turned at most as much as the turn rate, give or take the rounding
+    assert!(radians[0] > 0, "never turned");
+
+    for pair in radians.windows(2) {
+        assert!(pair[1] >= pair[0], "turned away from the enemy");
+        assert!(pair[1] - pair[0] <= turn_rate + Flint::from_num(0.002));
+    }
+
+    // until it's headed straight for the enemy
+    let body = world.entities().rectangles.get(missile).unwrap();
+    let offset = target - body.live.shape.centroid();
+    let diff = offset.radians() - body.live.direction.radians();
+
+    assert!(diff.abs() < turn_rate, "not headed for the enemy");
+}
+
+// two red ships facing each other and a blue one out of the way
+const HEAD_ON: &str = "
+    width = 600
+    height = 400
+
+    [[spawns]]
+    x = 150
+    y = 100
+    direction = \"east\"
+    team = \"red\"
+
+    [[spawns]]
+    x = 350
+    y = 100
+    direction = \"west\"
+    team = \"red\"
+
+    [[spawns]]
+    x = 300
+    y = 300
+    direction = \"east\"
+    team = \"blue\"
+";
+
+// the two facing ships speed toward each other and coast into one another,
+// returns how fast they crashed, and what each of them took and their speeds right after bouncing
+fn head_on(mode: Mode) -> (Flint, [(Flint, Flint); 2]) {
+    let mut world = world_on(HEAD_ON, 3, mode);
+    let spawns = world.misc.player_map_spawn_indexes.clone();
+    let pids = [0, 1].map(|idx| spawns.iter().position(|x| *x == idx).unwrap());
+
+    let mut crashed = Flint::ZERO;
+
+    for _ in 0..60 {
+        let distance = distance_squared(centroid(&world, pids[0]), centroid(&world, pids[1]));
+        let mut cmds = vec![Vec::new(); 3];
+
+        if distance > 60 * 60 {
+            for pid in pids {
+                cmds[pid].push(Command::Accelerate);
+            }
+        }
+
+        update(&mut world, &cmds);
+
+        // bounced back
+        match speeds(&world, pids) {
+            [speed, _] if speed < 0 => break,
+            [speed, other] => crashed = speed + other,
+        }
+    }
+
+    let speeds = speeds(&world, pids);
+    (
+        crashed,
+        [0, 1].map(|idx| (taken(&world, pids[idx]), speeds[idx])),
+    )
+}
+
+fn speeds(world: &World, pids: [usize; 2]) -> [Flint; 2] {
+    let entities = world.entities();
+
+    pids.map(|pid| {
+        let ship = entities.ship(pid).expect("the player is alive");
+        entities.motions.get(ship).unwrap().speed
+    })
+}
+
+#[test]
+fn ships_crashing_head_on_bounce_off_each_other() {
+    let motion = ShipClasses::load().unwrap().classes[0].motion.clone();
+    let (crashed, [(taken, speed), other]) = head_on(mode("deathmatch"));
+
+    // both take half of 2 for every unit of speed they closed in at straight
+    // into the side they hit, and are sent back as fast as they can reverse,
+    // less what they have already slowed down since
+    assert!(taken > 0, "never crashed");
+    assert!(taken <= crashed, "more than half of the damage");
+    assert_eq!(other, (taken, speed));
+    assert_eq!(speed, -motion.max_speed / 2 + motion.deceleration);
+
+    // teammates take the friendly fire share of it
+    for (friendly_fire, expected) in [
+        (FriendlyFire::Off, Flint::ZERO),
+        (FriendlyFire::Reduced, taken / 2),
+        (FriendlyFire::Full, taken),
+    ] {
+        let mut mode = mode("teams");
+        mode.friendly_fire = friendly_fire;
+
+        let (_, [one, two]) = head_on(mode);
+        assert_eq!(one, (expected, speed), "{:?}", friendly_fire);
+        assert_eq!(two, (expected, speed), "{:?}", friendly_fire);
+    }
+}
+
+// a ship facing east at a wall of an obstacle, with another ship behind it
+const OBSTACLE: &str = "
+    width = 600
+    height = 200
+
+    [[spawns]]
+    x = 100
+    y = 100
+    direction = \"east\"
+
+    [[spawns]]
+    x = 400
+    y = 100
+    direction = \"west\"
+
+    [[obstacles]]
+    points = [[200, 20], [240, 20], [240, 180], [200, 180]]
+";
+
+#[test]
+fn obstacles_stop_projectiles() {
+    let mut world = world_on(OBSTACLE, 2, mode("deathmatch"));
+    let spawns = world.misc.player_map_spawn_indexes.clone();
+    let pid = |idx: usize| spawns.iter().position(|x| *x == idx).unwrap();
+    let (shooter, behind) = (pid(0), pid(1));
+
+    let mut cmds = vec![Vec::new(); 2];
+    cmds[shooter].push(Command::Shoot);
+
+    let none = vec![Vec::new(); 2];
+    let mut fired = false;
+
+    for tick in 0..60 {
+        update(&mut world, if tick < 20 { &cmds } else { &none });
+
+        for (_, body) in world.entities().rectangles.iter() {
+            assert!(body.live.shape.centroid().x < 240, "went through");
+            fired = true;
+        }
+    }
+
+    // every shot went off at the obstacle, and none of them reached the other ship
+    assert!(fired, "never fired");
+    assert!(world.entities().rectangles.iter().next().is_none());
+    assert_eq!(taken(&world, behind), 0);
+}
+
+#[test]
+fn obstacles_block_and_damage_ships() {
+    let mut world = world_on(OBSTACLE, 2, mode("deathmatch"));
+    let spawns = &world.misc.player_map_spawn_indexes;
+    let pid = spawns.iter().position(|x| *x == 0).unwrap();
+
+    let mut cmds = vec![Vec::new(); 2];
+    cmds[pid].push(Command::Accelerate);
+
+    for _ in 0..40 {
+        update(&mut world, &cmds);
+        assert!(centroid(&world, pid).x < 200, "went through the obstacle");
+    }
+
+    // the crash is found at the end of a tick, so it's only moved back out the next
+    update(&mut world, &[Vec::new(), Vec::new()]);
+
+    let ship = world.entities().ship(pid).expect("the player is alive");
+    let body = world.entities.triangles.get_mut(ship).unwrap();
+
+    assert!(
+        body.calc_axes().iter().all(|x| x.x <= 200),
+        "stuck in the obstacle"
+    );
+    assert!(taken(&world, pid) > 0, "crashed without damage");
+}
+
+// the render components are floats and are updated together with the logic,
+// e.g. accelerating uses them to place the exhaust, but the logic must never
+// depend on them or the worlds of different machines might diverge
+#[test]
+fn render_does_not_affect_logic() {
+    for scenario in SCENARIOS {
+        let mut world = scenario.world();
+
+        let checksums: Vec<Checksum> = scenario
+            .commands()
+            .iter()
+            .map(|cmds| {
+                let entities = &mut world.entities;
+
+                for render in entities.render_triangles.values_mut() {
+                    render.past.angle = f32::NAN;
+                    render.live.angle = f32::NAN;
+                    render.past.shape.v1.x = f32::MAX;
+                    render.live.shape.v3.y = f32::MIN;
+                }
+
+                for render in entities.render_rectangles.values_mut() {
+                    render.live.shape.x = f32::NAN;
+                    render.live.angle = f32::INFINITY;
+                }
+
+                for (handle, render) in entities.render_points.iter_mut() {
+                    if entities.kinds.get(handle) == Some(&Kind::Exhaust) {
+                        render.live.shape.x = f32::NAN;
+                        render.color.a = 0;
+                    }
+                }
+
+                update(&mut world, cmds)
+            })
+            .collect();
+
+        assert_same(scenario.name, &scenario.run(), &checksums);
+    }
+}
+
+// the checksums are compared with the ones in the golden directory, any change that
+// makes them differ also makes old replays and other versions of the game desync,
+// run with UPDATE_GOLDEN=1 to record them again when that's intended,
+// and remember to bump the replay version
+#[test]
+fn checksums_match_golden_files() {
+    let update = env::var("UPDATE_GOLDEN").is_ok_and(|x| x == "1");
+    let directory = Path::new(file!()).with_file_name("golden");
+
+    for scenario in SCENARIOS {
+        let path = directory.join(format!("{}.txt", scenario.name));
+
+        // every 64th tick is enough to tell roughly when things changed
+        let actual: String = scenario
+            .run()
+            .iter()
+            .filter(|x| x.tick % 64 == 0 || x.tick == scenario.ticks as u64)
+            .map(|x| format!("{} {:016x}\n", x.tick, x.total()))
+            .collect();
+
+        if update {
+            fs::create_dir_all(&directory).unwrap();
+            fs::write(&path, &actual).unwrap();
+            println!("recorded golden checksums to {}", path.display());
+            continue;
+        }
+
+        // a missing file fails as well, otherwise a fresh checkout would never catch anything
+        let expected = match fs::read_to_string(&path) {
+            Ok(expected) => expected,
+            Err(e) => panic!(
+                "{}: failed to read {}: {}, run with UPDATE_GOLDEN=1 to record it",
+                scenario.name,
+                path.display(),
+                e
+            ),
+        };
+
+        for (expected, actual) in expected.lines().zip(actual.lines()) {
+            assert_eq!(
+                expected,
+                actual,
+                "{}: checksum differs from {}",
+                scenario.name,
+                path.display()
+            );
+        }
+
+        assert_eq!(
+            expected.lines().count(),
+            actual.lines().count(),
+            "{}: different amount of ticks than {}",
+            scenario.name,
+            path.display()
+        );
+    }
+}